@@ -1,13 +1,20 @@
+mod repl;
+
 use std::env;
 use std::sync::LazyLock;
 
-use miette::{IntoDiagnostic, WrapErr};
+use miette::{IntoDiagnostic, NamedSource, WrapErr};
 
 use mazer_atog::Atog;
 use mazer_html::document::{DocOutputType, Document, Metadata};
-use mazer_lisp::{environment::EnvironmentExt, interpreter::Interpreter};
+use mazer_lisp::{
+    environment::EnvironmentExt,
+    interpreter::{Engine, Interpreter, parameter_list},
+    modules::Modules,
+    trace::Trace,
+};
 use mazer_parser::Parser;
-use mazer_types::Environment;
+use mazer_types::{Environment, LispAST};
 use mazer_types::implfuncs::ShowFunc;
 
 #[derive(Debug, Default)]
@@ -18,6 +25,26 @@ struct Args {
     help: bool,
     help_topic: Option<String>,
     doc_query: Option<String>,
+    /// `mazer check <paths...>`: evaluate notes without writing HTML.
+    check: Option<Vec<String>>,
+    /// `mazer repl`: evaluate Lisp interactively.
+    repl: bool,
+    /// `--engine tree|bytecode`: how eval blocks are evaluated.
+    engine: Engine,
+    /// `--trace`: print the call tree of the eval blocks to stderr.
+    trace: bool,
+    /// `--trace-json <file>`: write the call tree as Chrome trace events.
+    trace_json: Option<String>,
+    /// `--profile`: print calls and time per function to stderr.
+    profile: bool,
+    /// `--echo-output`: also write what `print` and `debug` capture to stderr.
+    echo_output: bool,
+}
+
+impl Args {
+    fn tracing(&self) -> bool {
+        self.trace || self.profile || self.trace_json.is_some()
+    }
 }
 
 // Global singleton for parsed arguments - initialized once on first access
@@ -39,6 +66,21 @@ fn parse() -> Args {
             }
             "--open" | "-o" => result.open = true,
             "--verbose" | "-v" => result.verbose = true,
+            "--engine" => match args.next().as_deref() {
+                Some("tree") => result.engine = Engine::Tree,
+                Some("bytecode") => result.engine = Engine::Bytecode,
+                other => eprintln!(
+                    "--engine takes tree or bytecode, not {}",
+                    other.unwrap_or("nothing")
+                ),
+            },
+            "--trace" => result.trace = true,
+            "--trace-json" => match args.next() {
+                Some(file) => result.trace_json = Some(file),
+                None => eprintln!("--trace-json takes a file name"),
+            },
+            "--profile" => result.profile = true,
+            "--echo-output" => result.echo_output = true,
             "--help" | "-h" => {
                 result.help = true;
                 result.help_topic = args.next();
@@ -47,6 +89,13 @@ fn parse() -> Args {
             val if val.starts_with('-') => {
                 eprintln!("Unknown flag: {val}");
             }
+            "check" if !seen_file && result.check.is_none() => result.check = Some(Vec::new()),
+            "repl" if !seen_file && result.check.is_none() => result.repl = true,
+            val if result.check.is_some() => {
+                if let Some(paths) = result.check.as_mut() {
+                    paths.push(val.to_string());
+                }
+            }
             val => {
                 if !seen_file {
                     result.filename = Some(val.to_string());
@@ -65,21 +114,36 @@ fn parse() -> Args {
 
 fn print_help_message() {
     println!("Usage: mazer-cli <input-file> [options]");
+    println!("       mazer-cli check <files or directories...>");
+    println!("       mazer-cli repl");
     println!();
     println!("Options:");
     println!("  --open, -o             Open the output in the default web browser");
     println!("  --verbose, -v          Enable verbose logging");
-    println!("  --query, -q <search>   Search functions and symbols (e.g. `mazer -q real`)");
+    println!("  --engine <name>        Evaluate with `tree` (the default) or the `bytecode` VM");
+    println!("  --trace                Print every call in the eval blocks as a tree, to stderr");
+    println!("  --trace-json <file>    Write the calls as Chrome trace events (chrome://tracing)");
+    println!("  --profile              Print calls and time per function, to stderr");
+    println!("  --echo-output          Also print what print and debug show in the note to stderr");
+    println!("  --query, -q <search>   Search functions and symbols (e.g. `mazer -q real`); after a");
+    println!("                         note, also the functions it defines (`mazer note.zr -q sq`)");
     println!("  --help, -h             Show this help message");
+    println!();
+    println!("check evaluates every note (.zr and .md files in directories) and reports");
+    println!("errors and failed assert/expect forms without writing HTML.");
+    println!();
+    println!("repl evaluates Lisp interactively; type :help inside it for commands.");
 }
 
-/// Search functions and symbols, printing matches to stdout.
-fn run_doc_search(query: &str) {
+/// Search functions and symbols, printing matches to stdout. Given a note,
+/// the functions its eval blocks define are searched too.
+fn run_doc_search(query: &str, note: Option<&str>) {
     let query_lower = query.to_lowercase();
     // Collect all results into a unified list sorted by name
     enum DocResult {
         Func { name: String, doc: String, aliases: Vec<String> },
         Symbol { name: String, symbol: String, doc: String },
+        Defined { name: String, params: String, doc: String },
     }
 
     let mut results: Vec<DocResult> = Vec::new();
@@ -115,12 +179,51 @@ fn run_doc_search(query: &str) {
         }
     }
 
+    // Gather matching user functions
+    if let Some(note) = note {
+        let env = match std::fs::read_to_string(note)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to read input file '{note}'"))
+            .and_then(|content| evaluate(&content, note))
+        {
+            Ok((_, env)) => env,
+            Err(report) => {
+                eprintln!("{report:?}");
+                std::process::exit(1);
+            }
+        };
+        for (name, value) in env.bindings.iter() {
+            let LispAST::UserFunc { doc, .. } = value else {
+                continue;
+            };
+            // Prelude functions are documented as built-ins.
+            if !matches!(ShowFunc::from_name(name), ShowFunc::MaybeFunc(_)) {
+                continue;
+            }
+            let doc = doc.as_deref().unwrap_or_default();
+            let matches = query.is_empty()
+                || name.to_lowercase().contains(&query_lower)
+                || doc.to_lowercase().contains(&query_lower);
+            if matches {
+                results.push(DocResult::Defined {
+                    name: name.clone(),
+                    params: parameter_list(value).unwrap_or_default(),
+                    doc: doc.to_string(),
+                });
+            }
+        }
+    }
+
     results.sort_by(|a, b| {
         let name_a = match a {
-            DocResult::Func { name, .. } | DocResult::Symbol { name, .. } => name,
+            DocResult::Func { name, .. }
+            | DocResult::Symbol { name, .. }
+            | DocResult::Defined { name, .. } => name,
         };
         let name_b = match b {
-            DocResult::Func { name, .. } | DocResult::Symbol { name, .. } => name,
+            DocResult::Func { name, .. }
+            | DocResult::Symbol { name, .. }
+            | DocResult::Defined { name, .. } => name,
         };
         name_a.to_lowercase().cmp(&name_b.to_lowercase())
     });
@@ -143,6 +246,12 @@ fn run_doc_search(query: &str) {
                 println!("{} → {}", name, symbol);
                 println!("  {}", doc);
             }
+            DocResult::Defined { name, params, doc } => {
+                println!("{} {}", name, params);
+                if !doc.is_empty() {
+                    println!("  {}", doc);
+                }
+            }
         }
         println!();
     }
@@ -154,10 +263,19 @@ fn main() -> miette::Result<()> {
 
     // Handle `doc` subcommand
     if let Some(ref query) = args.doc_query {
-        run_doc_search(query);
+        run_doc_search(query, args.filename.as_deref());
         return Ok(());
     }
 
+    if let Some(ref paths) = args.check {
+        run_check(paths);
+        return Ok(());
+    }
+
+    if args.repl {
+        return repl::run(args.engine);
+    }
+
     let Some(file_name) = args.filename.as_deref() else {
         eprintln!("No input file specified.");
         print_help_message();
@@ -179,6 +297,13 @@ fn main() -> miette::Result<()> {
 }
 
 fn compile(content: &str, file_name: &str) -> miette::Result<String> {
+    Ok(evaluate(content, file_name)?.0.output())
+}
+
+/// Parse and build a note, run its eval blocks and format the rest, reporting
+/// interpolation problems to stderr as warnings. The environment the blocks
+/// left is returned with the document.
+fn evaluate(content: &str, file_name: &str) -> miette::Result<(Document, Environment)> {
     let p = Parser::new(content);
     let r = p.parse()?;
     let mut d = Document::new(r).dockind(DocOutputType::FullBody);
@@ -188,14 +313,114 @@ fn compile(content: &str, file_name: &str) -> miette::Result<String> {
     });
     d.build();
 
-    let ctx = Environment::new().with_native().with_prelude();
+    let mut ctx = Environment::new().with_native().with_prelude();
+    ctx.number_format = d.number_format();
     let frg = d.fragments();
-    let mut interp = Interpreter::new(frg, ctx);
-    interp.run()?;
+    let mut interp = Interpreter::new(frg, ctx)
+        .with_modules(Modules::for_file(file_name))
+        .with_engine(PARSED_ARGS.engine)
+        .with_echo(PARSED_ARGS.echo_output);
+    if PARSED_ARGS.tracing() {
+        interp = interp.with_trace();
+    }
+    let run = interp.run();
+    // A trace is most wanted when the run failed.
+    if let Some(trace) = interp.trace() {
+        report_trace(trace)?;
+    }
+    run?;
     let rst = interp.results();
-    d.inject(rst);
-    d.fmt(interp.env());
+    d.inject(rst, interp.outputs());
+    d.fmt(&interp);
+
+    // Failed interpolations still render (marked up as errors); report where
+    // they are without failing the build.
+    for diagnostic in d.diagnostics() {
+        let report = miette::Report::new(diagnostic.clone())
+            .with_source_code(NamedSource::new(file_name, content.to_string()));
+        eprintln!("{report:?}");
+    }
+
+    Ok((d, interp.env().clone()))
+}
 
-    Ok(d.output())
+/// Print or write `trace` as the tracing flags ask.
+fn report_trace(trace: &Trace) -> miette::Result<()> {
+    if PARSED_ARGS.trace {
+        eprint!("{}", trace.call_tree());
+    }
+    if PARSED_ARGS.profile {
+        eprint!("{}", trace.profile_table());
+    }
+    if let Some(path) = &PARSED_ARGS.trace_json {
+        std::fs::write(path, trace.chrome_json())
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to write trace to '{path}'"))?;
+    }
+    Ok(())
 }
 
+/// `mazer check`: evaluate each note, report failures, and exit non-zero if
+/// any note failed. A calc block or interpolation that renders as an error
+/// fails its note too.
+fn run_check(paths: &[String]) {
+    let mut files = Vec::new();
+    for path in paths {
+        collect_notes(std::path::Path::new(path), &mut files);
+    }
+    if files.is_empty() {
+        eprintln!("No notes to check.");
+        std::process::exit(1);
+    }
+
+    let mut failed = 0;
+    for file in &files {
+        let name = file.display().to_string();
+        let result = std::fs::read_to_string(file)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to read input file '{name}'"))
+            .and_then(|content| evaluate(&content, &name));
+        match result {
+            // Their reports were printed as the note was built.
+            Ok((doc, _)) if !doc.diagnostics().is_empty() => {
+                failed += 1;
+                println!("FAILED  {name}");
+            }
+            Ok(_) => println!("ok      {name}"),
+            Err(report) => {
+                failed += 1;
+                println!("FAILED  {name}");
+                eprintln!("{report:?}");
+            }
+        }
+    }
+
+    println!();
+    println!("checked {} note(s): {} failed", files.len(), failed);
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Files are taken as given; directories are searched for `.zr` and `.md`
+/// notes, in name order.
+fn collect_notes(path: &std::path::Path, files: &mut Vec<std::path::PathBuf>) {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(path) else {
+        eprintln!("cannot read directory '{}'", path.display());
+        return;
+    };
+    let mut entries: Vec<_> = entries.filter_map(Result::ok).map(|e| e.path()).collect();
+    entries.sort();
+    for entry in entries {
+        let is_note = entry
+            .extension()
+            .is_some_and(|ext| ext == "zr" || ext == "md");
+        if entry.is_dir() || is_note {
+            collect_notes(&entry, files);
+        }
+    }
+}
@@ -0,0 +1,387 @@
+//! `mazer repl`: evaluate Lisp interactively against the same environment a
+//! note's eval blocks see.
+//!
+//! Input is read until its parens and brackets balance, so a `defunc` can be
+//! typed over several lines. Lines starting with `:` are REPL commands; see
+//! [`HELP`].
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use miette::{IntoDiagnostic, NamedSource};
+use rustyline::DefaultEditor;
+use rustyline::error::ReadlineError;
+
+use mazer_atog::Atog;
+use mazer_lisp::{
+    environment::EnvironmentExt,
+    interpreter::{Engine, Interpreter, parameter_list},
+    parser::{LispToken, Parser, Spanned, Tokenizer},
+};
+use mazer_render::MathMLFormatter;
+use mazer_types::implfuncs::ShowFunc;
+use mazer_types::pretty::DEFAULT_WIDTH;
+use mazer_types::{Environment, LispAST, LispError};
+
+const HELP: &str = "\
+:show <expr>    render an expression as a show block would, as Unicode text
+:mathml <expr>  the same rendering as MathML
+:env            bindings, macros and units defined this session
+:doc <name>     documentation for a function or symbol
+:help           this message
+:quit           leave (as does Ctrl-D)";
+
+pub fn run(engine: Engine) -> miette::Result<()> {
+    let mut editor = DefaultEditor::new().into_diagnostic()?;
+    let history = history_file();
+    if let Some(path) = &history {
+        // A missing history file is the normal first run.
+        let _ = editor.load_history(path);
+    }
+
+    let base = Environment::new().with_native().with_prelude();
+    let mut interp = Interpreter::new(BTreeMap::new(), base.clone()).with_engine(engine);
+    let mut input = String::new();
+
+    println!("mazer {} (:help for commands)", env!("CARGO_PKG_VERSION"));
+    loop {
+        let prompt = if input.is_empty() {
+            "mazer> "
+        } else {
+            "  ...> "
+        };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                input.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e).into_diagnostic(),
+        };
+        if input.is_empty() && line.trim().is_empty() {
+            continue;
+        }
+        if !input.is_empty() {
+            input.push('\n');
+        }
+        input.push_str(&line);
+        if !is_complete(&input) {
+            continue;
+        }
+
+        let src = std::mem::take(&mut input);
+        let _ = editor.add_history_entry(src.as_str());
+        match src.trim().strip_prefix(':') {
+            Some(command) => {
+                if !command_line(command, &mut interp, &base) {
+                    break;
+                }
+            }
+            None => {
+                let result = evaluate(&src, &mut interp);
+                print!("{}", interp.take_printed());
+                match result {
+                    Ok(value) => println!("{}", value.pretty(DEFAULT_WIDTH)),
+                    Err(e) => report(e, &src),
+                }
+            }
+        }
+    }
+
+    if let Some(path) = &history {
+        let _ = editor.save_history(path);
+    }
+    Ok(())
+}
+
+/// Run a `:command`; false means leave the REPL.
+fn command_line(command: &str, interp: &mut Interpreter, base: &Environment) -> bool {
+    let (name, rest) = command
+        .split_once(char::is_whitespace)
+        .map_or((command, ""), |(name, rest)| (name, rest.trim()));
+    match name {
+        "q" | "quit" | "exit" => return false,
+        "h" | "help" => println!("{HELP}"),
+        "env" => print!("{}", env_listing(interp.env(), base)),
+        "doc" if !rest.is_empty() => match doc(rest, interp.env()) {
+            Some(text) => print!("{text}"),
+            None => eprintln!("No documentation for '{rest}' (try `mazer -q {rest}`)"),
+        },
+        "show" | "mathml" if !rest.is_empty() => match render(rest, interp) {
+            Ok(mathml) if name == "show" => println!("{}", unicode_text(&mathml)),
+            Ok(mathml) => println!("{mathml}"),
+            Err(e) => report(e, rest),
+        },
+        "doc" | "show" | "mathml" => eprintln!(":{name} needs an argument"),
+        _ => eprintln!("Unknown command :{name} (:help lists them)"),
+    }
+    true
+}
+
+fn evaluate(src: &str, interp: &mut Interpreter) -> Result<LispAST, LispError> {
+    let expr = Parser::new(src).parse()?;
+    let expr = interp.expand(expr)?;
+    interp.evaluate(expr)
+}
+
+/// The MathML a show block of `src` would produce.
+fn render(src: &str, interp: &mut Interpreter) -> Result<String, LispError> {
+    let expr = Parser::new(src).parse()?;
+    let expr = interp.expand(expr)?;
+    Ok(MathMLFormatter::new(Some(interp.env().clone())).format(&expr))
+}
+
+fn report(e: LispError, src: &str) {
+    let report = miette::Report::new(e).with_source_code(NamedSource::new("repl", src.to_string()));
+    eprintln!("{report:?}");
+}
+
+/// Everything the session has added to or changed in the starting
+/// environment, a line each.
+fn env_listing(env: &Environment, base: &Environment) -> String {
+    let mut out = String::new();
+    for (name, value) in env.bindings.iter() {
+        if base.bindings.get(name) != Some(value) {
+            let _ = writeln!(out, "{name} = {value}");
+        }
+    }
+    for name in env.macros.keys() {
+        let _ = writeln!(out, "{name} (macro)");
+    }
+    for name in env.units.keys().filter(|u| !base.units.contains_key(*u)) {
+        let _ = writeln!(out, "{name} (unit)");
+    }
+    if out.is_empty() {
+        out.push_str("(nothing defined yet)\n");
+    }
+    out
+}
+
+/// What `:doc name` prints: a built-in's signature and doc, a symbol's
+/// rendering, or a user function's parameters and docstring.
+fn doc(name: &str, env: &Environment) -> Option<String> {
+    let mut out = String::new();
+    let functions = ShowFunc::all_functions();
+    if let Some(f) = functions.iter().find(|f| f.names.contains(&name)) {
+        let _ = writeln!(out, "{} ({})", f.canonical_name(), f.arity_display());
+        let _ = writeln!(out, "  {}", f.doc);
+        let aliases: Vec<_> = f
+            .names
+            .iter()
+            .filter(|n| **n != f.canonical_name())
+            .collect();
+        if !aliases.is_empty() {
+            let _ = writeln!(
+                out,
+                "  Aliases: {}",
+                aliases
+                    .iter()
+                    .map(|n| n.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+    } else if let Some(entry) = Atog::get_entry(name) {
+        let _ = writeln!(out, "{name} → {}", entry.symbol);
+        let _ = writeln!(out, "  {}", entry.doc);
+    } else if let Some(value @ LispAST::UserFunc { doc, .. }) = env.bindings.get(name) {
+        let _ = writeln!(out, "{name} {}", parameter_list(value).unwrap_or_default());
+        if let Some(doc) = doc {
+            let _ = writeln!(out, "  {doc}");
+        }
+    } else {
+        let _ = writeln!(out, "{name} = {}", env.bindings.get(name)?);
+    }
+    Some(out)
+}
+
+fn history_file() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".mazer_history"))
+}
+
+/// Whether `src` has closed every paren and bracket it opened. It is read
+/// with the reader's own tokenizer, so strings and both kinds of comment are
+/// skipped exactly as evaluation will skip them.
+fn is_complete(src: &str) -> bool {
+    let mut depth = 0i32;
+    for Spanned { token, .. } in Tokenizer::new(src).tokenize() {
+        match token {
+            LispToken::OpenParen | LispToken::OpenBracket => depth += 1,
+            LispToken::CloseParen | LispToken::CloseBracket => depth -= 1,
+            LispToken::UnterminatedString | LispToken::UnterminatedComment => return false,
+            _ => {}
+        }
+    }
+    // Extra closers are left for the reader to report.
+    depth <= 0
+}
+
+enum Node {
+    Text(String),
+    Element(String, Vec<Node>),
+}
+
+/// Read the MathML the renderer produces into a tree. Attributes are dropped.
+fn parse_mathml(src: &str) -> Vec<Node> {
+    fn children(rest: &mut &str) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        while !rest.is_empty() {
+            if rest.starts_with("</") {
+                *rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+                break;
+            }
+            if let Some(tag) = rest.strip_prefix('<') {
+                let end = tag.find('>').unwrap_or(tag.len());
+                let inner = &tag[..end];
+                *rest = tag.get(end + 1..).unwrap_or("");
+                let name = inner
+                    .split(|c: char| c.is_whitespace() || c == '/')
+                    .next()
+                    .unwrap_or("")
+                    .to_string();
+                let kids = if inner.ends_with('/') {
+                    Vec::new()
+                } else {
+                    children(rest)
+                };
+                nodes.push(Node::Element(name, kids));
+            } else {
+                let end = rest.find('<').unwrap_or(rest.len());
+                nodes.push(Node::Text(unescape(&rest[..end])));
+                *rest = &rest[end..];
+            }
+        }
+        nodes
+    }
+    let mut rest = src;
+    children(&mut rest)
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// A one-line Unicode rendering of MathML: `<mfrac>` becomes `a/b`,
+/// `<msup>` becomes `a^b`, and so on, with parentheses around any part that
+/// is more than a single name or number.
+fn unicode_text(mathml: &str) -> String {
+    fn text(nodes: &[Node]) -> String {
+        nodes.iter().map(node).collect()
+    }
+
+    fn part(n: Option<&Node>) -> String {
+        wrap(&n.map(node).unwrap_or_default())
+    }
+
+    fn wrap(s: &str) -> String {
+        let s = s.trim();
+        if s.chars().all(|c| c.is_alphanumeric() || c == '.') || s.chars().count() == 1 {
+            s.to_string()
+        } else {
+            format!("({s})")
+        }
+    }
+
+    fn node(n: &Node) -> String {
+        let (name, kids) = match n {
+            Node::Text(s) => return s.clone(),
+            Node::Element(name, kids) => (name, kids),
+        };
+        match name.as_str() {
+            "mfrac" => format!("{}/{}", part(kids.first()), part(kids.get(1))),
+            "msup" | "mover" => format!("{}^{}", part(kids.first()), part(kids.get(1))),
+            "msub" | "munder" => format!("{}_{}", part(kids.first()), part(kids.get(1))),
+            "msubsup" | "munderover" => format!(
+                "{}_{}^{}",
+                part(kids.first()),
+                part(kids.get(1)),
+                part(kids.get(2))
+            ),
+            "msqrt" => format!("√{}", wrap(&text(kids))),
+            "mroot" => format!("{}√{}", part(kids.get(1)), part(kids.first())),
+            "mspace" => " ".to_string(),
+            "mo" => {
+                let op = text(kids);
+                match op.trim() {
+                    "=" | "≠" | "<" | ">" | "≤" | "≥" | "≈" | "→" | "⇒" | "⇔" | "∈" =>
+                    {
+                        format!(" {} ", op.trim())
+                    }
+                    "," => ", ".to_string(),
+                    _ => op,
+                }
+            }
+            "mtable" => format!("[{}]", kids.iter().map(node).collect::<Vec<_>>().join("; ")),
+            "mtr" => kids.iter().map(node).collect::<Vec<_>>().join(", "),
+            _ => text(kids),
+        }
+    }
+
+    text(&parse_mathml(mathml)).trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn balance_ignores_strings_and_comments() {
+        assert!(is_complete("(+ 1 2)"));
+        assert!(!is_complete("(defunc f (x)\n  (* x"));
+        assert!(is_complete("(concat \"(\" \")\") ; )"));
+        assert!(!is_complete("(f ; (\n"));
+        assert!(!is_complete("(f \"unclosed)"));
+    }
+
+    #[test]
+    fn balance_ignores_block_comments() {
+        assert!(!is_complete("(f #| ) |#"));
+        assert!(is_complete("#| ( #| nested ( |# |# (+ 1 2)"));
+        assert!(!is_complete("(+ 1 #| still open )"));
+        assert!(!is_complete("#| open"));
+    }
+
+    fn session(src: &str) -> (Interpreter, Environment) {
+        let base = Environment::new().with_native().with_prelude();
+        let mut interp = Interpreter::new(BTreeMap::new(), base.clone());
+        evaluate(src, &mut interp).unwrap();
+        (interp, base)
+    }
+
+    #[test]
+    fn env_lists_what_the_session_defined() {
+        let (interp, base) = session("(begin (define k 3) (defmacro twice (x) `(* 2 ,x)))");
+        assert_eq!(env_listing(interp.env(), &base), "k = 3\ntwice (macro)\n");
+        assert_eq!(env_listing(&base, &base), "(nothing defined yet)\n");
+    }
+
+    #[test]
+    fn doc_covers_builtins_symbols_and_user_functions() {
+        let (interp, _) = session(r#"(defunc sq (x) "The square of x." (* x x))"#);
+        let env = interp.env();
+        assert!(doc("sqrt", env).unwrap().starts_with("sqrt (1 argument)\n  Square root"));
+        assert!(doc("alpha", env).unwrap().starts_with("alpha → α"));
+        assert_eq!(doc("sq", env).unwrap(), "sq (x)\n  The square of x.\n");
+        assert_eq!(doc("nothing-here", env), None);
+    }
+
+    #[test]
+    fn show_renders_as_text() {
+        let (mut interp, _) = session("(define k 2)");
+        let mathml = render("(/ 1 (+ x k))", &mut interp).unwrap();
+        assert_eq!(unicode_text(&mathml), "1/(x+k)");
+    }
+
+    #[test]
+    fn mathml_as_text() {
+        let mathml = "<mrow><mfrac><mn>1</mn><mrow><mi>x</mi><mo>+</mo><mn>2</mn></mrow></mfrac>\
+                      <mo>=</mo><msup><mi>α</mi><mn>2</mn></msup></mrow>";
+        assert_eq!(unicode_text(mathml), "1/(x+2) = α^2");
+    }
+}
@@ -0,0 +1,78 @@
+//! `mazer check` as a user runs it.
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// A fresh directory of notes, `(file name, contents)`.
+fn notes(name: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mazer-check-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    for (file, contents) in files {
+        fs::write(dir.join(file), contents).unwrap();
+    }
+    dir
+}
+
+fn check(dir: &PathBuf) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_mazer-cli"))
+        .arg("check")
+        .arg(dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn failures_are_reported_and_nothing_is_written() {
+    let dir = notes(
+        "failing",
+        &[
+            ("a.md", "(eval (expect (* 6 7) 42))\n"),
+            ("b.md", "(eval (expect (* 6 7) 41))\n"),
+        ],
+    );
+    let written = || fs::metadata("/tmp/output.html").and_then(|m| m.modified()).ok();
+    let before = written();
+
+    let output = check(&dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{stdout}");
+    assert!(stdout.contains(&format!("ok      {}", dir.join("a.md").display())));
+    assert!(stdout.contains(&format!("FAILED  {}", dir.join("b.md").display())));
+    assert!(stdout.contains("checked 2 note(s): 1 failed"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("assertion"));
+    assert_eq!(written(), before);
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn failed_calc_blocks_and_interpolations_fail_the_note() {
+    let dir = notes(
+        "inline",
+        &[
+            ("a.md", "(eval (define x 2))\n\n(calc (expect (* x 3) 7 0))\n"),
+            ("b.md", "(eval (define x 2))\n\nx is {{undefined-name}}.\n"),
+            ("c.md", "(eval (define x 2))\n\n(calc (expect (* x 3) 6 0)) and {{x}}.\n"),
+        ],
+    );
+    let output = check(&dir);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(output.status.code(), Some(1), "{stdout}");
+    assert!(stdout.contains(&format!("FAILED  {}", dir.join("a.md").display())));
+    assert!(stdout.contains(&format!("FAILED  {}", dir.join("b.md").display())));
+    assert!(stdout.contains(&format!("ok      {}", dir.join("c.md").display())));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("expected 7"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn passing_notes_exit_zero() {
+    let dir = notes("passing", &[("a.md", "(eval (assert (= (* 6 7) 42)))\n")]);
+    let output = check(&dir);
+    assert!(output.status.success());
+    fs::remove_dir_all(dir).unwrap();
+}
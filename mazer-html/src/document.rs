@@ -1,10 +1,14 @@
 use std::{collections::BTreeMap, rc::Rc};
 
-use mazer_lisp::parser::Parser;
+use miette::{Diagnostic, SourceSpan};
+use thiserror::Error;
+
+use mazer_lisp::{interpreter::Interpreter, parser::Parser};
 use mazer_parser::MdAst;
 use mazer_render::{MathMLFormatter, ToMathML};
 use mazer_types::Environment;
 use mazer_types::LispAST;
+use mazer_types::{LispError, NumberFormat, Precision};
 
 
 #[derive(Clone)]
@@ -20,11 +24,136 @@ enum FontKind {
     Strikethrough,
 }
 
+/// Decimal places a calc block shows when neither it nor the document asks
+/// for a precision; trailing zeros are then trimmed.
+const DEFAULT_CALC_DIGITS: u32 = 10;
+
 #[derive(Debug, Clone)]
 pub enum DocAst {
     Html(Rc<str>),
     Eval(LispAST),
     Show(LispAST),
+    Calc(Calc),
+    Interpolate(Interpolation),
+}
+
+/// A `(calc expr options...)` block, rendered as `expr = value` once the eval
+/// blocks have run.
+#[derive(Debug, Clone)]
+pub struct Calc {
+    pub expr: LispAST,
+    /// Number format options such as `(digits n)` or `(sig n)`, layered over
+    /// the document's policy for the value.
+    pub options: Vec<LispAST>,
+    /// From `(value-only)`: render just the value, without `expr =`.
+    pub value_only: bool,
+}
+
+impl Calc {
+    /// Split trailing option forms off a parsed block. Several top-level
+    /// expressions arrive wrapped in `begin`; `(calc (fmt-number e opts))` is
+    /// the same as `(calc e opts)`.
+    fn new(ast: LispAST) -> Self {
+        let mut calc = Calc {
+            expr: ast,
+            options: Vec::new(),
+            value_only: false,
+        };
+        if let LispAST::List(items) = &calc.expr
+            && matches!(items.first(), Some(LispAST::Symbol(s)) if s == "begin")
+        {
+            let mut items = items[1..].to_vec();
+            while items.len() > 1 {
+                let option = &items[items.len() - 1];
+                match option {
+                    LispAST::List(o) if matches!(&o[..], [LispAST::Symbol(s)] if s == "value-only") => {
+                        calc.value_only = true;
+                    }
+                    o if NumberFormat::is_option(o) => calc.options.insert(0, o.clone()),
+                    _ => break,
+                }
+                items.pop();
+            }
+            calc.expr = if items.len() == 1 {
+                items.remove(0)
+            } else {
+                let mut begin = vec![LispAST::Symbol("begin".to_string())];
+                begin.extend(items);
+                LispAST::List(begin.into())
+            };
+        }
+
+        if let LispAST::List(items) = &calc.expr
+            && let [LispAST::Symbol(s), expr, options @ ..] = &items[..]
+            && s == "fmt-number"
+        {
+            let mut options = options.to_vec();
+            options.append(&mut calc.options);
+            calc.options = options;
+            calc.expr = expr.clone();
+        }
+        calc
+    }
+}
+
+/// The environment a computed value is rendered in: the document's number
+/// format with `options` applied. Without any precision the value is cut to
+/// [`DEFAULT_CALC_DIGITS`] places and trimmed.
+fn value_env(env: &Environment, options: &[LispAST]) -> Result<Environment, LispError> {
+    let mut env = env.clone();
+    let policy = &mut env.number_format;
+    for option in options {
+        policy.apply("calc", option)?;
+    }
+    if policy.precision == Precision::Full {
+        policy.precision = Precision::Decimals(DEFAULT_CALC_DIGITS);
+        policy.trim_zeros = true;
+    }
+    Ok(env)
+}
+
+/// A `{{expr}}` in prose, replaced by the value of `expr` once the eval blocks
+/// have run.
+#[derive(Debug, Clone)]
+pub struct Interpolation {
+    pub expr: LispAST,
+    /// The text between the braces, as written.
+    pub code: String,
+    pub span: SourceSpan,
+}
+
+/// A `{{expr}}` whose value could not be computed, pointing at it in the source.
+#[derive(Debug, Clone, Error, Diagnostic)]
+#[error("cannot interpolate {{{{{code}}}}}")]
+#[diagnostic(code(mazer::document::interpolation), severity(Warning))]
+pub struct InterpolationError {
+    pub code: String,
+    pub reason: String,
+    #[label("{reason}")]
+    pub span: SourceSpan,
+    #[help]
+    pub help: Option<String>,
+}
+
+/// A calc block whose value could not be computed, such as a failed `expect`.
+#[derive(Debug, Clone, Error, Diagnostic)]
+#[error("calc block {code} failed: {error}")]
+#[diagnostic(code(mazer::document::calc), severity(Warning))]
+pub struct CalcError {
+    /// The block's expression, as source.
+    pub code: String,
+    pub error: LispError,
+}
+
+/// A block that rendered as an error in place of its value.
+#[derive(Debug, Clone, Error, Diagnostic)]
+pub enum DocumentError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Interpolation(#[from] InterpolationError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Calc(#[from] CalcError),
 }
 
 pub struct Metadata<'a> {
@@ -40,17 +169,21 @@ pub struct Document {
     body: Vec<DocAst>,
     nodes: Vec<MdAst>,
     doc_type: DocOutputType,
+    number_format: NumberFormat,
+    diagnostics: Vec<DocumentError>,
 }
 
 impl Document {
     pub fn new(nodes: Vec<MdAst>) -> Self {
-        let head = "<!DOCTYPE html><html lang=\"en\"><head><meta charset=\"UTF-8\"><link rel=\"icon\" href=\"data:image/svg+xml,<svg xmlns=%22http://www.w3.org/2000/svg%22 viewBox=%220 0 32 32%22><text y=%2232%22 font-size=%2232%22>🍁</text></svg>\"><script src=\"https://cdn.jsdelivr.net/npm/@arborium/arborium/dist/arborium.iife.js\" data-theme=\"github-light\" data-selector=\"pre code\"></script></head>";
+        let head = "<!DOCTYPE html><html lang=\"en\"><head><meta charset=\"UTF-8\"><link rel=\"icon\" href=\"data:image/svg+xml,<svg xmlns=%22http://www.w3.org/2000/svg%22 viewBox=%220 0 32 32%22><text y=%2232%22 font-size=%2232%22>🍁</text></svg>\"><style>.mazer-stdout{background:#f6f8fa;border-left:3px solid #d0d7de;padding:0.5rem 0.75rem;white-space:pre-wrap}</style><script src=\"https://cdn.jsdelivr.net/npm/@arborium/arborium/dist/arborium.iife.js\" data-theme=\"github-light\" data-selector=\"pre code\"></script></head>";
 
         Document {
             head: String::from(head),
             body: Vec::new(),
             nodes,
             doc_type: DocOutputType::FullBody,
+            number_format: NumberFormat::default(),
+            diagnostics: Vec::new(),
         }
     }
 
@@ -59,6 +192,18 @@ impl Document {
         self.clone()
     }
 
+    /// The number format set by the `numbers:` front matter entry, once
+    /// [`Document::build`] has run. Seed the interpreter's environment with it.
+    pub fn number_format(&self) -> NumberFormat {
+        self.number_format
+    }
+
+    /// Calc blocks and interpolations that failed during [`Document::fmt`], in
+    /// document order.
+    pub fn diagnostics(&self) -> &[DocumentError] {
+        &self.diagnostics
+    }
+
     pub fn body(&self) -> Vec<DocAst> {
         self.body.clone()
     }
@@ -99,11 +244,9 @@ impl Document {
         // Only return Eval blocks for evaluation - Show blocks are formatted symbolically
         self.body
             .iter()
-            .filter_map(|content| match content {
-                DocAst::Eval(ast) => {
-                    let key = format!("{:?}", ast);
-                    Some((key, ast.clone()))
-                }
+            .enumerate()
+            .filter_map(|(index, content)| match content {
+                DocAst::Eval(ast) => Some((fragment_key(index, ast), ast.clone())),
                 _ => None,
             })
             .collect()
@@ -111,12 +254,20 @@ impl Document {
 
     /// Inject evaluated results for Eval blocks.
     /// Show blocks are handled separately via format_show_blocks().
-    pub fn inject(&mut self, results: &BTreeMap<String, LispAST>) {
-        for content in &mut self.body {
+    /// An eval block that printed is replaced by its output, as a notebook
+    /// cell shows it; see [`Interpreter::outputs`].
+    pub fn inject(
+        &mut self,
+        results: &BTreeMap<String, LispAST>,
+        outputs: &BTreeMap<String, String>,
+    ) {
+        for (index, content) in self.body.iter_mut().enumerate() {
             match content {
                 DocAst::Eval(e) => {
-                    let key = format!("{:?}", e);
-                    if results.get(&key).is_some() {
+                    let key = fragment_key(index, e);
+                    if let Some(printed) = outputs.get(&key) {
+                        *content = DocAst::Html(format_output(printed).into());
+                    } else if results.get(&key).is_some() {
                         // Eval blocks execute for side effects only, don't display
                         *content = DocAst::Html("".into());
                     }
@@ -132,19 +283,66 @@ impl Document {
     /// Call this after inject() to format show blocks with the environment from evaluation.
     // Format show blocks symbolically using the environment from evaluation
     // This allows show blocks to use variables defined in eval blocks
-    pub fn fmt(&mut self, env: &Environment) {
+    // Numbers written in source are shown as written; the number format only
+    // applies to computed values and explicit fmt-number forms
+    // Calc blocks and interpolations are evaluated with `interp`'s engine, and
+    // what they print follows them like an eval block's output
+    pub fn fmt(&mut self, interp: &Interpreter) {
+        let env = interp.env();
+        let mut source_env = env.clone();
+        source_env.number_format = NumberFormat::default();
+        let mut expander = Expander::new(env);
         for content in &mut self.body {
-            if let DocAst::Show(ast) = content {
-                let mathml_fmtr = MathMLFormatter::new(Some(env.clone()));
-                let formatted = mathml_fmtr.format(ast);
-
-                // Wrap in <math> tags for proper MathML rendering
-                let mathml = format!(
-                    "<math display=\"inline\"><mstyle displaystyle=\"true\">{}</mstyle></math>",
-                    formatted
-                );
-                *content = DocAst::Html(mathml.into());
+            let mut printed = String::new();
+            let formatted = match content {
+                DocAst::Show(ast) => match expander.expand(ast) {
+                    Ok(ast) => MathMLFormatter::new(Some(source_env.clone())).format(&ast),
+                    Err(e) => {
+                        format!("<merror><mtext>{}</mtext></merror>", escape_html(&e.to_string()))
+                    }
+                },
+                DocAst::Calc(calc) => {
+                    let mut forked = interp.fork();
+                    let (html, error) = format_calc(calc, &mut forked, &source_env, &mut expander);
+                    if let Some(error) = error {
+                        self.diagnostics.push(error.into());
+                    }
+                    printed = forked.take_printed();
+                    html
+                }
+                DocAst::Interpolate(interpolation) => {
+                    let mut forked = interp.fork();
+                    let mut html = match format_interpolation(interpolation, &mut forked) {
+                        Ok(html) => html,
+                        Err(e) => {
+                            let html = format!(
+                                "<span class=\"mazer-error\" title=\"{}\">{{{{{}}}}}</span>",
+                                escape_html(&e.reason).replace('"', "&quot;"),
+                                escape_html(&e.code)
+                            );
+                            self.diagnostics.push(e.into());
+                            html
+                        }
+                    };
+                    let printed = forked.take_printed();
+                    if !printed.is_empty() {
+                        html.push_str(&format_output(&printed));
+                    }
+                    *content = DocAst::Html(html.into());
+                    continue;
+                }
+                _ => continue,
+            };
+
+            // Wrap in <math> tags for proper MathML rendering
+            let mut mathml = format!(
+                "<math display=\"inline\"><mstyle displaystyle=\"true\">{}</mstyle></math>",
+                formatted
+            );
+            if !printed.is_empty() {
+                mathml.push_str(&format_output(&printed));
             }
+            *content = DocAst::Html(mathml.into());
         }
     }
 
@@ -171,7 +369,9 @@ impl Document {
                     // NOTE: eval is expected to be in its final transformed state
                     unreachable!("Interpreter should have processed all Eval blocks before output");
                 }
-                DocAst::Show(s) => {
+                DocAst::Show(s)
+                | DocAst::Calc(Calc { expr: s, .. })
+                | DocAst::Interpolate(Interpolation { expr: s, .. }) => {
                     // Fallback if format_show_blocks wasn't called - use ToMathML
                     let s: String = s.to_mathml();
                     let mathml = format!("<math display=\"inline\">{}</math>", s);
@@ -257,6 +457,15 @@ impl Document {
                 let dast = DocAst::Show(r);
                 self.append(dast);
             }
+            MdAst::CalcBlock { code } => {
+                let mut p = Parser::new(&code);
+                let r = p
+                    .parse()
+                    .unwrap_or_else(|e| LispAST::Error(e.to_string()));
+
+                let dast = DocAst::Calc(Calc::new(r));
+                self.append(dast);
+            }
             MdAst::Text { content } => {
                 let dast = if content == "\n" {
                     DocAst::Html("<br/>".into())
@@ -272,9 +481,45 @@ impl Document {
                     self.append_node(c);
                 }
             }
+            MdAst::Interpolation { code, span } => {
+                let expr = Parser::new(&code)
+                    .parse()
+                    .unwrap_or_else(|e| LispAST::Error(e.to_string()));
+                self.append(DocAst::Interpolate(Interpolation { expr, code, span }));
+            }
+            MdAst::FrontMatter { entries } => {
+                for (key, value) in entries {
+                    if key == "numbers"
+                        && let Err(e) = self.set_number_format(&value)
+                    {
+                        let html = format!(
+                            "<pre class=\"mazer-error\">front matter: {}</pre>",
+                            escape_html(&e.to_string())
+                        );
+                        self.append(DocAst::Html(html.into()));
+                    }
+                }
+            }
         }
     }
 
+    /// `numbers: (sig 4) (sci)` in front matter: option forms applied in order.
+    fn set_number_format(&mut self, options: &str) -> Result<(), LispError> {
+        let ast = Parser::new(options).parse()?;
+        let options = match ast {
+            LispAST::List(items)
+                if matches!(items.first(), Some(LispAST::Symbol(s)) if s == "begin") =>
+            {
+                items[1..].to_vec()
+            }
+            single => vec![single],
+        };
+        for option in &options {
+            self.number_format.apply("numbers", option)?;
+        }
+        Ok(())
+    }
+
     #[inline]
     fn append_header(&mut self, level: u8, text: String) {
         let level = level.clamp(1, 6);
@@ -378,9 +623,234 @@ impl Document {
     }
 }
 
+/// The interpreter runs fragments in key order, so keys lead with the block's
+/// position to evaluate eval blocks in document order (and keep identical
+/// blocks apart).
+fn fragment_key(index: usize, ast: &LispAST) -> String {
+    format!("{index:08} {ast:?}")
+}
+
+/// What an eval block printed, as preformatted text below it.
+fn format_output(printed: &str) -> String {
+    format!(
+        "<pre class=\"mazer-stdout\">{}</pre>",
+        escape_html(printed.strip_suffix('\n').unwrap_or(printed))
+    )
+}
+
+/// Evaluate a calc block in `interp`, over the environment the eval blocks
+/// left behind, and render `expr = value`; an evaluation error takes the
+/// value's place and is returned as well. The expression is set in
+/// `source_env`, the value under the number format.
+fn format_calc(
+    calc: &Calc,
+    interp: &mut Interpreter,
+    source_env: &Environment,
+    expander: &mut Expander,
+) -> (String, Option<CalcError>) {
+    let mathml_fmtr = MathMLFormatter::new(Some(source_env.clone()));
+    let value = value_env(interp.env(), &calc.options).and_then(|value_env| {
+        let value = interp.evaluate(calc.expr.clone())?;
+        Ok(MathMLFormatter::new(Some(value_env)).format(&value))
+    });
+    let (value, error) = match value {
+        Ok(value) => (value, None),
+        Err(error) => (
+            format!("<merror><mtext>{}</mtext></merror>", escape_html(&error.to_string())),
+            Some(CalcError {
+                code: calc.expr.to_string(),
+                error,
+            }),
+        ),
+    };
+    if calc.value_only {
+        return (value, error);
+    }
+    let expr = expander.expand(&calc.expr).unwrap_or_else(|_| calc.expr.clone());
+    (format!("{}<mo>=</mo>{}", mathml_fmtr.format(&expr), value), error)
+}
+
+/// Expands the macros the eval blocks defined, so a show block sets the form a
+/// macro call stands for. One serves a whole document; there is no interpreter
+/// at all when no macros were defined.
+struct Expander(Option<Interpreter>);
+
+impl Expander {
+    fn new(env: &Environment) -> Self {
+        Self((!env.macros.is_empty()).then(|| Interpreter::new(BTreeMap::new(), env.clone())))
+    }
+
+    fn expand(&mut self, ast: &LispAST) -> Result<LispAST, LispError> {
+        match &mut self.0 {
+            Some(interp) => interp.expand(ast.clone()),
+            None => Ok(ast.clone()),
+        }
+    }
+}
+
+/// Evaluate an interpolation and render its value for prose: numbers,
+/// quantities, strings and booleans as text, anything else as inline MathML.
+fn format_interpolation(
+    interpolation: &Interpolation,
+    interp: &mut Interpreter,
+) -> Result<String, InterpolationError> {
+    let value = interp.evaluate(interpolation.expr.clone()).map_err(|e| InterpolationError {
+        code: interpolation.code.clone(),
+        reason: e.to_string(),
+        span: interpolation.span,
+        help: matches!(
+            e,
+            LispError::UnboundSymbol { .. } | LispError::UnboundSymbolDidYouMean { .. }
+        )
+        .then(|| "bind the name with define in an eval block".to_string()),
+    })?;
+
+    // No options to apply, so this cannot fail.
+    let env = interp.env();
+    let value_env = value_env(env, &[]).unwrap_or_else(|_| env.clone());
+    let policy = value_env.number_format;
+    Ok(match value {
+        LispAST::Number(n) => policy.format(n).to_string(),
+        LispAST::Quantity { value, unit } => format!("{}\u{2009}{}", policy.format(value), unit),
+        LispAST::String(s) => escape_html(&s),
+        LispAST::Bool(b) => b.to_string(),
+        other => {
+            let mathml_fmtr = MathMLFormatter::new(Some(value_env));
+            format!("<math display=\"inline\">{}</math>", mathml_fmtr.format(&other))
+        }
+    })
+}
+
 #[inline]
 fn escape_html(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('<', "&lt;")
         .replace('>', "&gt;")
 }
+
+#[cfg(test)]
+mod tests {
+    use mazer_lisp::environment::EnvironmentExt;
+
+    use super::*;
+
+    fn render(src: &str) -> String {
+        render_echoing(src, false)
+    }
+
+    fn render_echoing(src: &str, echo: bool) -> String {
+        let nodes = mazer_parser::Parser::new(src).parse().unwrap();
+        let mut doc = Document::new(nodes).dockind(DocOutputType::InnerHtml);
+        doc.build();
+        let env = Environment::new().with_native().with_prelude();
+        let mut interp = Interpreter::new(doc.fragments(), env).with_echo(echo);
+        interp.run().unwrap();
+        doc.inject(interp.results(), interp.outputs());
+        doc.fmt(&interp);
+        doc.output()
+    }
+
+    fn math(html: &str) -> &str {
+        let start = html.find("<math").expect("a math element");
+        let end = html[start..].find("</math>").expect("a closed math element");
+        &html[start..start + end]
+    }
+
+    /// The value a calc block renders, as MathML.
+    fn calc_value(src: &str) -> String {
+        let html = render(&format!("{src}\n"));
+        let body = math(&html).split_once("displaystyle=\"true\">").unwrap().1;
+        let value = body.split_once("<mo>=</mo>").map_or(body, |(_, value)| value);
+        value.trim_end_matches("</mstyle>").to_string()
+    }
+
+    fn read(src: &str) -> LispAST {
+        Parser::new(src).parse().unwrap()
+    }
+
+    #[test]
+    fn calc_splits_off_trailing_options() {
+        let calc = Calc::new(read("(* 2 x) (digits 2) (value-only)"));
+        assert_eq!(calc.expr, read("(* 2 x)"));
+        assert_eq!(calc.options, [read("(digits 2)")]);
+        assert!(calc.value_only);
+
+        let calc = Calc::new(read("(fmt-number (/ 1 3) (sig 3)) (sci)"));
+        assert_eq!(calc.expr, read("(/ 1 3)"));
+        assert_eq!(calc.options, [read("(sig 3)"), read("(sci)")]);
+
+        // Only trailing options are taken; the rest stays one expression.
+        let calc = Calc::new(read("(define y 2) (digits 1) (* y 3)"));
+        assert_eq!(calc.expr, read("(begin (define y 2) (digits 1) (* y 3))"));
+        assert!(calc.options.is_empty());
+    }
+
+    #[test]
+    fn calc_values_follow_their_precision_options() {
+        assert_eq!(calc_value("(calc (/ 1 3))"), "<mn>0.3333333333</mn>");
+        assert_eq!(calc_value("(calc (/ 1 3) (digits 2))"), "<mn>0.33</mn>");
+        assert_eq!(calc_value("(calc (fmt-number (/ 1 3) (digits 1)))"), "<mn>0.3</mn>");
+        assert_eq!(calc_value("(calc (* 1234 100) (sig 2))"), "<mn>120000</mn>");
+        assert_eq!(
+            calc_value("(calc (* 1234 100) (sig 2) (sci))"),
+            "<mrow><mn>1.2</mn><mo>×</mo><msup><mn>10</mn><mn>5</mn></msup></mrow>"
+        );
+        assert_eq!(calc_value("(calc (* 2 3) (value-only))"), "<mn>6</mn>");
+        assert!(calc_value("(calc (/ 1 0))").starts_with("<merror>"));
+    }
+
+    #[test]
+    fn calc_precision_keeps_trailing_zeros() {
+        assert_eq!(calc_value("(calc (/ 1 4))"), "<mn>0.25</mn>");
+        assert_eq!(calc_value("(calc (/ 1 4) (digits 3))"), "<mn>0.250</mn>");
+    }
+
+    #[test]
+    fn show_blocks_render_macro_expansions() {
+        let html = render("(eval (defmacro sq (x) `(pow ,x 2)))\n\n(show (+ (sq y) 1))\n");
+        let expected = render("(show (+ (pow y 2) 1))\n");
+        assert_eq!(math(&html), math(&expected));
+        assert!(math(&html).contains("<msup>"));
+        assert!(!html.contains("sq"));
+    }
+
+    #[test]
+    fn printed_output_follows_its_block() {
+        let src = "(eval (print \"one <1>\"))\n\nbetween\n\n(eval (define x 2))\n\n\
+                   (eval (print \"x = \" x) (debug x))\n";
+        let html = render(src);
+        let first = html
+            .find("<pre class=\"mazer-stdout\">one &lt;1&gt;</pre>")
+            .expect("the first block's output");
+        let second = html
+            .find("<pre class=\"mazer-stdout\">x = 2\n2 ; Number</pre>")
+            .expect("the last block's output");
+        assert!(first < html.find("between").unwrap());
+        assert!(html.find("between").unwrap() < second);
+        assert_eq!(html.matches("mazer-stdout").count(), 2);
+    }
+
+    #[test]
+    fn echoing_keeps_the_output_in_the_document() {
+        let src = "(eval (print \"shown\") (debug (vec 1 2)))\n";
+        let html = render_echoing(src, true);
+        assert!(html.contains("<pre class=\"mazer-stdout\">shown\n(vec 1 2) ; List</pre>"));
+        assert_eq!(html, render(src));
+    }
+
+    #[test]
+    fn calc_blocks_and_interpolations_capture_their_output() {
+        let src = "(calc (begin (print \"in calc\") 2))\n\n\
+                   said {{ (begin (print \"inline\") 3) }}\n";
+        let html = render(src);
+        let calc = html
+            .find("<pre class=\"mazer-stdout\">in calc</pre>")
+            .expect("the calc block's output");
+        let inline = html
+            .find("<pre class=\"mazer-stdout\">inline</pre>")
+            .expect("the interpolation's output");
+        assert!(html.find("</math>").unwrap() < calc);
+        assert!(calc < html.find("said").unwrap());
+        assert!(html.find("said").unwrap() < inline);
+    }
+}
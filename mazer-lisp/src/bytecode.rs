@@ -0,0 +1,313 @@
+//! The bytecode compiler: [`LispAST`] to [`Code`], a flat list of [`Op`]s run
+//! by [`crate::vm`].
+//!
+//! Variables are resolved to numbered slots at compile time, so running code
+//! indexes a vector instead of searching the environment by name. Only the
+//! core forms are compiled: literals, variables, `quote`, `if`, `begin`,
+//! `and`, `or`, `define`, `defunc` and applications. Every other special form
+//! (and every macro call) compiles to [`Op::Interp`], which hands the form to
+//! the tree-walker, so the two engines cannot disagree about what it means.
+//!
+//! Errors the tree-walker would report about a form's shape, such as `(if c)`,
+//! are compiled to [`Op::Raise`] so they surface when, and only if, the form is
+//! reached.
+
+use std::collections::{BTreeMap, HashMap};
+
+use mazer_types::{LispAST, LispError};
+
+use crate::interpreter::{define_name, defunc_value};
+
+/// Special forms left to the tree-walker.
+const DELEGATED: &[&str] = &[
+    "forall",
+    "exists",
+    "string",
+    "qty",
+    "convert",
+    "defunit",
+    "series",
+    "fmt-number",
+    "expect",
+    "try",
+    "defmacro",
+    "quasiquote",
+    "import",
+    "require",
+];
+
+/// Forms that can make new macros. Code mentioning one runs on the
+/// tree-walker as a whole: compiled code would already have decided which of
+/// its calls are macro calls.
+const DEFINES_MACROS: &[&str] = &["defmacro", "import", "require"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Op {
+    /// Push `consts[i]`.
+    Const(usize),
+    /// Push a variable's value.
+    Load(usize),
+    /// Set a variable to the value on top of the stack, leaving it there.
+    Store(usize),
+    Pop,
+    Jump(usize),
+    /// Pop an `if` condition and jump when it is false.
+    JumpUnless(usize),
+    /// One operand of `and` (`short_on` false) or `or` (true): pop it, and if
+    /// it equals `short_on` push it back and jump to `exit`.
+    Test {
+        short_on: bool,
+        exit: usize,
+    },
+    /// Apply the function below the top `argc` values to them. `first_arg`
+    /// indexes the call's first argument as written, in `consts`, for naming
+    /// failed assertions.
+    Call {
+        argc: usize,
+        first_arg: Option<usize>,
+    },
+    /// Fail with `errors[i]`.
+    Raise(usize),
+    /// Evaluate `consts[i]` with the tree-walker and push the result.
+    Interp(usize),
+    /// Finish the current function (or the top-level expression).
+    Return,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Code {
+    pub(crate) ops: Vec<Op>,
+    pub(crate) consts: Vec<LispAST>,
+    pub(crate) errors: Vec<LispError>,
+}
+
+/// Slot numbers for variable names, shared by all code one interpreter
+/// compiles.
+#[derive(Debug, Default)]
+pub(crate) struct Names {
+    slots: HashMap<String, usize>,
+    names: Vec<String>,
+}
+
+impl Names {
+    pub(crate) fn slot(&mut self, name: &str) -> usize {
+        if let Some(&slot) = self.slots.get(name) {
+            return slot;
+        }
+        self.names.push(name.to_string());
+        self.slots.insert(name.to_string(), self.names.len() - 1);
+        self.names.len() - 1
+    }
+
+    pub(crate) fn name(&self, slot: usize) -> &str {
+        &self.names[slot]
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.names.len()
+    }
+}
+
+pub(crate) struct Compiler<'a> {
+    names: &'a mut Names,
+    macros: &'a BTreeMap<String, LispAST>,
+    code: Code,
+}
+
+impl<'a> Compiler<'a> {
+    /// Compile `expr`, treating calls to `macros` as macro calls.
+    pub(crate) fn compile(
+        expr: &LispAST,
+        names: &'a mut Names,
+        macros: &'a BTreeMap<String, LispAST>,
+    ) -> Code {
+        let mut compiler = Compiler {
+            names,
+            macros,
+            code: Code::default(),
+        };
+        if mentions(expr, DEFINES_MACROS) {
+            compiler.delegate(expr);
+        } else {
+            compiler.expr(expr);
+        }
+        compiler.emit(Op::Return);
+        compiler.code
+    }
+
+    fn emit(&mut self, op: Op) -> usize {
+        self.code.ops.push(op);
+        self.code.ops.len() - 1
+    }
+
+    /// Point the jump at `at` to the next op emitted.
+    fn patch(&mut self, at: usize) {
+        let here = self.code.ops.len();
+        match &mut self.code.ops[at] {
+            Op::Jump(target) | Op::JumpUnless(target) | Op::Test { exit: target, .. } => {
+                *target = here;
+            }
+            other => unreachable!("patching {other:?}"),
+        }
+    }
+
+    fn constant(&mut self, value: LispAST) -> usize {
+        self.code.consts.push(value);
+        self.code.consts.len() - 1
+    }
+
+    fn push(&mut self, value: LispAST) {
+        let i = self.constant(value);
+        self.emit(Op::Const(i));
+    }
+
+    fn raise(&mut self, error: LispError) {
+        self.code.errors.push(error);
+        self.emit(Op::Raise(self.code.errors.len() - 1));
+    }
+
+    fn delegate(&mut self, expr: &LispAST) {
+        let i = self.constant(expr.clone());
+        self.emit(Op::Interp(i));
+    }
+
+    fn expr(&mut self, expr: &LispAST) {
+        match expr {
+            LispAST::Error(e) => self.raise(LispError::Message(e.clone())),
+            LispAST::Symbol(s) => {
+                let slot = self.names.slot(s);
+                self.emit(Op::Load(slot));
+            }
+            LispAST::List(items) if items.is_empty() => self.push(expr.clone()),
+            LispAST::List(items) => self.list(expr, items),
+            LispAST::Application { name, args } => {
+                let slot = self.names.slot(name);
+                self.emit(Op::Load(slot));
+                for arg in args {
+                    self.expr(arg);
+                }
+                self.emit(Op::Call {
+                    argc: args.len(),
+                    first_arg: None,
+                });
+            }
+            other => self.push(other.clone()),
+        }
+    }
+
+    fn list(&mut self, expr: &LispAST, items: &[LispAST]) {
+        let args = &items[1..];
+        if let LispAST::Symbol(head) = &items[0] {
+            match head.as_str() {
+                "define" => return self.define(args),
+                "defunc" => {
+                    return match defunc_value(args) {
+                        Ok((name, func)) => {
+                            self.push(func);
+                            let slot = self.names.slot(&name);
+                            self.emit(Op::Store(slot));
+                        }
+                        Err(e) => self.raise(e),
+                    };
+                }
+                "if" => return self.conditional(args),
+                "begin" => return self.begin(args),
+                "and" => return self.connective(args, false),
+                "or" => return self.connective(args, true),
+                "quote" => {
+                    return match args.first() {
+                        Some(quoted) => self.push(quoted.clone()),
+                        None => self.raise(LispError::Arity {
+                            form: "quote".to_string(),
+                            expected: "1".to_string(),
+                            got: 0,
+                        }),
+                    };
+                }
+                name if DELEGATED.contains(&name) || self.macros.contains_key(name) => {
+                    return self.delegate(expr);
+                }
+                _ => {}
+            }
+        }
+
+        for item in items {
+            self.expr(item);
+        }
+        let first_arg = args.first().map(|arg| self.constant(arg.clone()));
+        self.emit(Op::Call {
+            argc: args.len(),
+            first_arg,
+        });
+    }
+
+    fn define(&mut self, args: &[LispAST]) {
+        match define_name(args) {
+            Ok(name) => {
+                self.expr(&args[1]);
+                let slot = self.names.slot(&name);
+                self.emit(Op::Store(slot));
+            }
+            Err(e) => self.raise(e),
+        }
+    }
+
+    fn conditional(&mut self, args: &[LispAST]) {
+        let [cond, then, otherwise] = args else {
+            return self.raise(LispError::Arity {
+                form: "if".to_string(),
+                expected: "3".to_string(),
+                got: args.len(),
+            });
+        };
+        self.expr(cond);
+        let to_else = self.emit(Op::JumpUnless(0));
+        self.expr(then);
+        let to_end = self.emit(Op::Jump(0));
+        self.patch(to_else);
+        self.expr(otherwise);
+        self.patch(to_end);
+    }
+
+    fn begin(&mut self, args: &[LispAST]) {
+        let Some((last, init)) = args.split_last() else {
+            return self.raise(LispError::Arity {
+                form: "begin".to_string(),
+                expected: "at least 1".to_string(),
+                got: 0,
+            });
+        };
+        for expr in init {
+            self.expr(expr);
+            self.emit(Op::Pop);
+        }
+        self.expr(last);
+    }
+
+    fn connective(&mut self, args: &[LispAST], short_on: bool) {
+        let exits: Vec<usize> = args
+            .iter()
+            .map(|arg| {
+                self.expr(arg);
+                self.emit(Op::Test { short_on, exit: 0 })
+            })
+            .collect();
+        self.push(LispAST::Bool(!short_on));
+        for exit in exits {
+            self.patch(exit);
+        }
+    }
+}
+
+/// Whether `expr` contains a list headed by one of `forms`.
+fn mentions(expr: &LispAST, forms: &[&str]) -> bool {
+    match expr {
+        LispAST::List(items) => {
+            matches!(items.first(), Some(LispAST::Symbol(head)) if forms.contains(&head.as_str()))
+                || items.iter().any(|item| mentions(item, forms))
+        }
+        LispAST::Application { args, .. } => args.iter().any(|arg| mentions(arg, forms)),
+        LispAST::UserFunc { body, .. } => mentions(body, forms),
+        _ => false,
+    }
+}
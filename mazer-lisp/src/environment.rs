@@ -1,6 +1,8 @@
 use mazer_stdlib::{Native, Prelude};
+use mazer_types::implfuncs::ShowFunc;
 use mazer_types::{Environment, LispAST};
 use std::collections::BTreeMap;
+use std::rc::Rc;
 
 use crate::{interpreter::Interpreter, parser::Parser};
 
@@ -20,51 +22,97 @@ impl EnvironmentExt for Environment {
         for (_k, v) in prelude {
             let mut parser = Parser::new(&v);
             let ast = parser.parse().expect("Failed to parse prelude function");
-            let mut interp = Interpreter::new(
-                BTreeMap::new(),
-                Self {
-                    bindings: self.bindings.clone(),
-                },
-            );
+            let mut interp = Interpreter::new(BTreeMap::new(), self.clone());
             interp
                 .eval(ast)
                 .expect("Failed to evaluate prelude function");
             self.bindings = interp.env().bindings.clone();
         }
 
-        Self {
-            bindings: self.bindings.clone(),
-        }
+        self.clone()
     }
 
     fn with_native(&mut self) -> Self {
         let mut env = EnvMap::new();
 
-        // TODO: add more stdlib functions here
-        env.insert("+".into(), mazer_types::LispAST::NativeFunc(Native::add));
-        env.insert("add".into(), mazer_types::LispAST::NativeFunc(Native::add));
-        env.insert("-".into(), mazer_types::LispAST::NativeFunc(Native::sub));
-        env.insert("sub".into(), mazer_types::LispAST::NativeFunc(Native::sub));
-        env.insert("*".into(), mazer_types::LispAST::NativeFunc(Native::mul));
-        env.insert("mul".into(), mazer_types::LispAST::NativeFunc(Native::mul));
-        env.insert("/".into(), mazer_types::LispAST::NativeFunc(Native::div));
-        env.insert("div".into(), mazer_types::LispAST::NativeFunc(Native::div));
+        // Every `ShowFunc` with an `impl`, under each of its names; see
+        // `mazer_types::implfuncs`.
+        for (func, native) in mazer_types::show_func_natives!() {
+            for name in func.names() {
+                env.insert(name.to_string(), LispAST::NativeFunc(native));
+            }
+        }
+
+        self.extend(&env);
+
+        Self {
+            bindings: Rc::new(env),
+            units: self.units.clone(),
+            number_format: self.number_format,
+            macros: self.macros.clone(),
+        }
+    }
+}
 
-        env.insert(
-            "reflect".into(),
-            mazer_types::LispAST::NativeFunc(Native::reflect),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mazer_types::LispError;
+
+    fn eval(src: &str) -> Result<LispAST, LispError> {
+        let env = Environment::new().with_native();
+        let mut interp = Interpreter::new(BTreeMap::new(), env);
+        interp.eval(Parser::new(src).parse()?)
+    }
+
+    #[test]
+    fn every_native_is_documented() {
+        let env = Environment::new().with_native();
+        for (name, value) in env.bindings.iter() {
+            if matches!(value, LispAST::NativeFunc(_)) {
+                assert!(
+                    !matches!(ShowFunc::from_name(name), ShowFunc::MaybeFunc(_)),
+                    "{name} has no ShowFunc entry"
+                );
+            }
+        }
+        assert!(env.bindings.contains_key("sqrt"));
+        assert!(env.bindings.contains_key("<"));
+    }
+
+    #[test]
+    fn arity_is_checked_from_metadata() {
+        assert_eq!(eval("(< 1 2 3)"), Ok(LispAST::Bool(true)));
+        assert_eq!(
+            eval("(^ 2)"),
+            Err(LispError::Arity {
+                form: "^".to_string(),
+                expected: "2".to_string(),
+                got: 1,
+            })
         );
-        env.insert(
-            "print".into(),
-            mazer_types::LispAST::NativeFunc(Native::print),
+        assert_eq!(
+            eval("(get (dict) 1 2 3)"),
+            Err(LispError::Arity {
+                form: "get".to_string(),
+                expected: "2 or 3".to_string(),
+                got: 4,
+            })
         );
-        env.insert(
-            "debug".into(),
-            mazer_types::LispAST::NativeFunc(Native::debug),
+        assert_eq!(
+            eval("(+)"),
+            Err(LispError::Arity {
+                form: "+".to_string(),
+                expected: "at least 1".to_string(),
+                got: 0,
+            })
         );
+    }
 
-        self.extend(&env);
-
-        Self { bindings: env }
+    #[test]
+    fn series_takes_a_quoted_expression() {
+        let quoted = eval("(series '(exp x) x 0 3)");
+        assert!(quoted.is_ok(), "{quoted:?}");
+        assert_eq!(quoted, eval("(series (exp x) x 0 3)"));
     }
 }
@@ -1,32 +1,122 @@
 use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::sync::Arc;
 
 use mazer_atog::Atog;
-use mazer_types::{Environment, LispAST, LispError};
+use mazer_stdlib::{Native, output};
+use mazer_types::implfuncs::Arguments;
+use mazer_types::{Dimension, Environment, LispAST, LispError, Unit};
 use strsim::levenshtein;
 use unicode_segmentation::UnicodeSegmentation;
 
+use crate::macros::sequence;
+use crate::modules::Modules;
+use crate::trace::Trace;
+use crate::vm::Vm;
+
 /// Maximum edit distance for a "did you mean" suggestion to be offered.
 const SUGGESTION_THRESHOLD: usize = 2;
 
+/// How [`Interpreter::run`] and [`Interpreter::evaluate`] evaluate code. Both
+/// engines give the same results and errors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Engine {
+    /// Walk the [`LispAST`] directly, as [`Interpreter::eval`] does.
+    #[default]
+    Tree,
+    /// Compile to bytecode and run it on a stack VM; see [`crate::vm`].
+    Bytecode,
+}
+
 pub struct Interpreter {
     fragments: BTreeMap<String, LispAST>,
-    env: Environment,
+    pub(crate) env: Environment,
+    pub(crate) modules: Modules,
+    engine: Engine,
+    vm: Vm,
+    trace: Option<Trace>,
+    /// What `print` and `debug` wrote since the last [`Interpreter::take_printed`].
+    printed: String,
+    /// Output per fragment, for those that printed anything.
+    outputs: BTreeMap<String, String>,
+    echo: bool,
 }
 
 impl Interpreter {
     pub fn new(fragments: BTreeMap<String, LispAST>, env: Environment) -> Self {
-        Self { fragments, env }
+        Self {
+            fragments,
+            env,
+            modules: Modules::default(),
+            engine: Engine::default(),
+            vm: Vm::default(),
+            trace: None,
+            printed: String::new(),
+            outputs: BTreeMap::new(),
+            echo: false,
+        }
+    }
+
+    pub fn with_engine(mut self, engine: Engine) -> Self {
+        self.engine = engine;
+        self
+    }
+
+    /// A fresh interpreter over a copy of this one's environment, with the same
+    /// engine and echo, for evaluating expressions on the side.
+    pub fn fork(&self) -> Self {
+        Self::new(BTreeMap::new(), self.env.clone())
+            .with_engine(self.engine)
+            .with_echo(self.echo)
+    }
+
+    /// Record calls, returns, definitions and errors from now on; see
+    /// [`crate::trace`].
+    pub fn with_trace(mut self) -> Self {
+        self.trace = Some(Trace::new());
+        self
+    }
+
+    pub fn trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
     }
 
     pub fn results(&self) -> &BTreeMap<String, LispAST> {
         &self.fragments
     }
 
+    /// Also write what `print` and `debug` capture to stderr as it happens.
+    pub fn with_echo(mut self, echo: bool) -> Self {
+        self.echo = echo;
+        self
+    }
+
+    /// What `print` and `debug` wrote during [`Interpreter::run`], keyed like
+    /// the fragments. A fragment that failed keeps what it printed first.
+    pub fn outputs(&self) -> &BTreeMap<String, String> {
+        &self.outputs
+    }
+
+    /// What `print` and `debug` wrote since the last call, outside of
+    /// [`Interpreter::run`].
+    pub fn take_printed(&mut self) -> String {
+        std::mem::take(&mut self.printed)
+    }
+
     pub fn run(&mut self) -> Result<LispAST, LispError> {
         let mut result = LispAST::Bool(false);
 
         for (name, fragment) in self.fragments.clone() {
-            result = self.eval(fragment)?;
+            let evaluated = self.captured(|interp| {
+                interp
+                    .expand(fragment)
+                    .and_then(|fragment| interp.evaluate(fragment))
+            });
+            let printed = self.take_printed();
+            if !printed.is_empty() {
+                self.outputs.insert(name.clone(), printed);
+            }
+            result = evaluated?;
             // Update the fragments map with the evaluated result
             self.fragments.insert(name, result.clone());
             // dbg!(&result);
@@ -35,9 +125,32 @@ impl Interpreter {
         Ok(result)
     }
 
+    /// Evaluate `expr` with the selected [`Engine`], or the tree-walker when
+    /// tracing.
+    pub fn evaluate(&mut self, expr: LispAST) -> Result<LispAST, LispError> {
+        self.captured(|interp| match interp.engine {
+            Engine::Tree => interp.eval(expr),
+            Engine::Bytecode if interp.trace.is_some() => interp.eval(expr),
+            Engine::Bytecode => {
+                let mut vm = std::mem::take(&mut interp.vm);
+                let result = vm.execute(interp, &expr);
+                interp.vm = vm;
+                result
+            }
+        })
+    }
+
+    /// Run `f` with what `print` and `debug` write going to this interpreter's
+    /// output; see [`mazer_stdlib::output`].
+    pub(crate) fn captured<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let (result, printed) = output::capture(self.echo, || f(self));
+        self.printed.push_str(&printed);
+        result
+    }
+
     /// Build an unbound-symbol error, attaching a "did you mean" suggestion when a
     /// close match exists among the bindings or the symbol table.
-    fn unbound(&self, name: &str) -> LispError {
+    pub(crate) fn unbound(&self, name: &str) -> LispError {
         self.suggest(name).map_or_else(
             || LispError::UnboundSymbol { name: name.to_string() },
             |suggestion| LispError::UnboundSymbolDidYouMean {
@@ -71,10 +184,16 @@ impl Interpreter {
         match expr {
             LispAST::Error(e) => Err(LispError::Message(e)),
             LispAST::Number(_)
+            | LispAST::Quantity { .. }
+            | LispAST::Poly { .. }
             | LispAST::Bool(_)
             | LispAST::String(_)
+            | LispAST::Keyword(_)
+            | LispAST::Set(_)
+            | LispAST::Dict(_)
             | LispAST::NativeFunc(_)
-            | LispAST::UserFunc { .. } => Ok(expr),
+            | LispAST::UserFunc { .. }
+            | LispAST::ErrorValue { .. } => Ok(expr),
 
             LispAST::Symbol(ref s) => self.env.get(s).cloned().ok_or_else(|| self.unbound(s)),
 
@@ -88,6 +207,10 @@ impl Interpreter {
                         "defunc" => return self.eval_defunc(&exprs[1..]),
                         "if" => return self.eval_if(&exprs[1..]),
                         "begin" => return self.eval_begin(&exprs[1..]),
+                        "and" => return self.eval_connective(&exprs[1..], false),
+                        "or" => return self.eval_connective(&exprs[1..], true),
+                        "forall" => return self.eval_quantifier(&exprs[1..], false),
+                        "exists" => return self.eval_quantifier(&exprs[1..], true),
                         "quote" => {
                             return exprs.get(1).cloned().ok_or_else(|| LispError::Arity {
                                 form: "quote".to_string(),
@@ -96,6 +219,21 @@ impl Interpreter {
                             });
                         }
                         "string" => return self.eval_string(&exprs[1..]),
+                        "qty" => return self.eval_qty(&exprs[1..], false),
+                        "convert" => return self.eval_qty(&exprs[1..], true),
+                        "defunit" => return self.eval_defunit(&exprs[1..]),
+                        "series" => return self.eval_series(&exprs[1..]),
+                        "fmt-number" => return self.eval_fmt_number(&exprs[1..]),
+                        "expect" => return self.eval_expect(&exprs[1..]),
+                        "try" => return self.eval_try(&exprs[1..]),
+                        "defmacro" => return self.eval_defmacro(&exprs[1..]),
+                        "quasiquote" => return self.eval_quasiquote(&exprs[1..]),
+                        "import" => return self.eval_import(&exprs[1..], true),
+                        "require" => return self.eval_import(&exprs[1..], false),
+                        name if self.env.macros.contains_key(name) => {
+                            let expanded = self.expand(LispAST::List(exprs.clone()))?;
+                            return self.eval(expanded);
+                        }
                         _ => {}
                     }
                 }
@@ -106,7 +244,20 @@ impl Interpreter {
                     exprs[1..].iter().map(|e| self.eval(e.clone())).collect();
                 let args = args?;
 
-                self.apply(func, args)
+                // A native cannot see its source, so an assertion that fails
+                // without naming itself is named after its first argument here.
+                self.call(&exprs[0], func, args).map_err(|e| match e {
+                    LispError::AssertionFailed {
+                        expr,
+                        expected,
+                        actual,
+                    } if expr.is_empty() => LispError::AssertionFailed {
+                        expr: exprs.get(1).map(LispAST::to_string).unwrap_or_default(),
+                        expected,
+                        actual,
+                    },
+                    other => other,
+                })
             }
 
             // Application is lazy - args aren't evaluated yet
@@ -122,34 +273,72 @@ impl Interpreter {
                     args.iter().map(|e| self.eval(e.clone())).collect();
                 let evaled_args = evaled_args?;
 
-                self.apply(func, evaled_args)
+                self.call(&name, func, evaled_args)
             }
         }
     }
 
-    fn apply(&mut self, func: LispAST, args: Vec<LispAST>) -> Result<LispAST, LispError> {
+    /// [`Interpreter::apply`], recorded in the trace as a call to `name`, which
+    /// is only formatted when tracing.
+    fn call(
+        &mut self,
+        name: &dyn Display,
+        func: LispAST,
+        args: Vec<LispAST>,
+    ) -> Result<LispAST, LispError> {
+        let Some(trace) = self.trace.as_mut() else {
+            return self.apply(func, args);
+        };
+        trace.call(&name.to_string(), &args);
+        let result = self.apply(func, args);
+        if let Some(trace) = self.trace.as_mut() {
+            trace.finish(&result);
+        }
+        result
+    }
+
+    pub(crate) fn apply(
+        &mut self,
+        func: LispAST,
+        args: Vec<LispAST>,
+    ) -> Result<LispAST, LispError> {
         match func {
             LispAST::NativeFunc(f) => f(&args),
-            LispAST::UserFunc { params, body } => {
-                if params.len() != args.len() {
-                    return Err(LispError::Arity {
-                        form: "function".to_string(),
-                        expected: params.len().to_string(),
-                        got: args.len(),
-                    });
-                }
+            LispAST::UserFunc {
+                params,
+                defaults,
+                body,
+                ..
+            } => {
+                let signature = Signature::of(&params);
+                signature.arity().check("function", &args)?;
 
-                // Create a new scope with parameters bound to arguments
+                // Bind the parameters in a new scope, evaluating the default
+                // of each optional one not passed after those before it.
                 let mut saved_bindings = BTreeMap::new();
-                for (param, arg) in params.iter().zip(args.iter()) {
-                    if let Some(existing) = self.env.get(param) {
-                        saved_bindings.insert(param.clone(), existing.clone());
-                    }
-                    self.env.set(param.clone(), arg.clone());
+                let mut bound = Ok(());
+                let mut args = args.into_iter();
+                let named = signature.required.iter().chain(signature.optional);
+                for (i, param) in named.enumerate() {
+                    let value = match args.next() {
+                        Some(arg) => arg,
+                        None => match self.eval(defaults[i - signature.required.len()].clone()) {
+                            Ok(value) => value,
+                            Err(e) => {
+                                bound = Err(e);
+                                break;
+                            }
+                        },
+                    };
+                    self.bind_param(&mut saved_bindings, param, value);
+                }
+                if let (Ok(()), Some(rest)) = (&bound, signature.rest) {
+                    let rest_list = LispAST::List(args.collect::<Vec<_>>().into());
+                    self.bind_param(&mut saved_bindings, rest, rest_list);
                 }
 
                 // Evaluate the function body
-                let result = self.eval((*body).clone());
+                let result = bound.and_then(|()| self.eval((*body).clone()));
 
                 // Restore the original bindings
                 for (param, original) in &saved_bindings {
@@ -164,117 +353,379 @@ impl Interpreter {
         }
     }
 
+    /// Bind `param` to `value`, saving what it shadows in `saved` the first
+    /// time.
+    fn bind_param(&mut self, saved: &mut BTreeMap<String, LispAST>, param: &str, value: LispAST) {
+        if !saved.contains_key(param)
+            && let Some(existing) = self.env.get(param)
+        {
+            saved.insert(param.to_string(), existing.clone());
+        }
+        self.env.set(param.to_string(), value);
+    }
+
     fn eval_define(&mut self, args: &[LispAST]) -> Result<LispAST, LispError> {
+        let name = define_name(args)?;
+        let value = self.eval(args[1].clone())?;
+        if let Some(trace) = self.trace.as_mut() {
+            trace.define(&name, &value);
+        }
+        self.env.set(name, value.clone());
+        Ok(value)
+    }
+
+    fn eval_defunc(&mut self, args: &[LispAST]) -> Result<LispAST, LispError> {
+        let (name, user_func) = defunc_value(args)?;
+        if let Some(trace) = self.trace.as_mut() {
+            trace.define(&name, &user_func);
+        }
+        self.env.set(name, user_func.clone());
+        Ok(user_func)
+    }
+
+    fn eval_if(&mut self, args: &[LispAST]) -> Result<LispAST, LispError> {
+        if args.len() != 3 {
+            return Err(LispError::Arity {
+                form: "if".to_string(),
+                expected: "3".to_string(),
+                got: args.len(),
+            });
+        }
+
+        let cond = self.eval(args[0].clone())?;
+        match cond {
+            LispAST::Bool(true) => self.eval(args[1].clone()),
+            LispAST::Bool(false) => self.eval(args[2].clone()),
+            other => Err(LispError::TypeMismatch {
+                form: "if condition".to_string(),
+                expected: "Bool".to_string(),
+                got: other.type_name().to_string(),
+            }),
+        }
+    }
+
+    fn eval_begin(&mut self, args: &[LispAST]) -> Result<LispAST, LispError> {
+        if args.is_empty() {
+            return Err(LispError::Arity {
+                form: "begin".to_string(),
+                expected: "at least 1".to_string(),
+                got: 0,
+            });
+        }
+        let mut result = LispAST::Bool(false);
+        for expr in args {
+            result = self.eval(expr.clone())?;
+        }
+        Ok(result)
+    }
+
+    /// `and` / `or`: evaluate operands left to right, stopping at the first one
+    /// equal to `short_on` (`false` for `and`, `true` for `or`). An empty `(and)`
+    /// is true and an empty `(or)` is false.
+    fn eval_connective(&mut self, args: &[LispAST], short_on: bool) -> Result<LispAST, LispError> {
+        let form = if short_on { "or" } else { "and" };
+        for arg in args {
+            match self.eval(arg.clone())? {
+                LispAST::Bool(b) if b == short_on => return Ok(LispAST::Bool(short_on)),
+                LispAST::Bool(_) => {}
+                other => {
+                    return Err(LispError::TypeMismatch {
+                        form: form.to_string(),
+                        expected: "Bool".to_string(),
+                        got: other.type_name().to_string(),
+                    });
+                }
+            }
+        }
+        Ok(LispAST::Bool(!short_on))
+    }
+
+    /// `(forall (in x S) P)` / `(exists (in x S) P)`: bind `x` to each element of
+    /// the finite set `S` in turn and evaluate `P`, stopping as soon as the
+    /// answer is known.
+    fn eval_quantifier(&mut self, args: &[LispAST], exists: bool) -> Result<LispAST, LispError> {
+        let form = if exists { "exists" } else { "forall" };
         if args.len() != 2 {
             return Err(LispError::Arity {
-                form: "define".to_string(),
-                expected: "2".to_string(),
+                form: form.to_string(),
+                expected: "2 ((in var set), body)".to_string(),
                 got: args.len(),
             });
         }
 
-        let name = match &args[0] {
-            LispAST::Symbol(s) => s.clone(),
+        let (var, domain) = match &args[0] {
+            LispAST::List(binding) => match &binding[..] {
+                [LispAST::Symbol(op), LispAST::Symbol(var), domain] if op == "in" => {
+                    (var.clone(), domain.clone())
+                }
+                _ => {
+                    return Err(LispError::TypeMismatch {
+                        form: form.to_string(),
+                        expected: "a binding of the form (in var set)".to_string(),
+                        got: "List".to_string(),
+                    });
+                }
+            },
             other => {
                 return Err(LispError::TypeMismatch {
-                    form: "define".to_string(),
-                    expected: "Symbol".to_string(),
+                    form: form.to_string(),
+                    expected: "a binding of the form (in var set)".to_string(),
                     got: other.type_name().to_string(),
                 });
             }
         };
 
-        let value = self.eval(args[1].clone())?;
-        self.env.set(name, value.clone());
-        Ok(value)
+        let elements = match self.eval(domain)? {
+            LispAST::Set(items) => items,
+            other => {
+                return Err(LispError::TypeMismatch {
+                    form: form.to_string(),
+                    expected: "Set".to_string(),
+                    got: other.type_name().to_string(),
+                });
+            }
+        };
+
+        // Same save/restore dance as `apply`, so the bound variable does not
+        // leak into (or clobber) the surrounding scope.
+        let saved = self.env.get(&var).cloned();
+        let mut result = Ok(LispAST::Bool(!exists));
+        for element in elements.iter() {
+            self.env.set(var.clone(), element.clone());
+            match self.eval(args[1].clone()) {
+                Ok(LispAST::Bool(b)) if b == exists => {
+                    result = Ok(LispAST::Bool(exists));
+                    break;
+                }
+                Ok(LispAST::Bool(_)) => {}
+                Ok(other) => {
+                    result = Err(LispError::TypeMismatch {
+                        form: form.to_string(),
+                        expected: "Bool".to_string(),
+                        got: other.type_name().to_string(),
+                    });
+                    break;
+                }
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        match saved {
+            Some(original) => self.env.set(var, original),
+            None => {
+                self.env.remove(&var);
+            }
+        }
+        result
     }
 
-    fn eval_defunc(&mut self, args: &[LispAST]) -> Result<LispAST, LispError> {
-        if args.len() != 3 {
+    /// Resolve a unit expression written as a symbol, e.g. `m/s^2`, including
+    /// any units introduced with `defunit`.
+    fn unit(&self, form: &str, expr: &LispAST) -> Result<Unit, LispError> {
+        let text = match expr {
+            LispAST::Symbol(s) | LispAST::String(s) => s,
+            other => {
+                return Err(LispError::TypeMismatch {
+                    form: form.to_string(),
+                    expected: "a unit such as m/s^2".to_string(),
+                    got: other.type_name().to_string(),
+                });
+            }
+        };
+        self.env.unit(text).map_err(|e| LispError::UnknownUnit {
+            form: form.to_string(),
+            reason: e.to_string(),
+        })
+    }
+
+    /// `(qty value unit)` and `(convert q unit)`: the first argument is
+    /// evaluated, the unit is taken as written.
+    fn eval_qty(&mut self, args: &[LispAST], convert: bool) -> Result<LispAST, LispError> {
+        let form = if convert { "convert" } else { "qty" };
+        if args.len() != 2 {
             return Err(LispError::Arity {
-                form: "defunc".to_string(),
-                expected: "3 (name, (params...), body)".to_string(),
+                form: form.to_string(),
+                expected: "2".to_string(),
+                got: args.len(),
+            });
+        }
+        let value = self.eval(args[0].clone())?;
+        let unit = self.unit(form, &args[1])?;
+        if convert {
+            Native::convert(&value, unit)
+        } else {
+            Native::quantity(&value, unit)
+        }
+    }
+
+    /// `(defunit furlong (qty 201.168 m))`: make `furlong` usable in unit
+    /// expressions. A plain number defines a dimensionless multiple.
+    fn eval_defunit(&mut self, args: &[LispAST]) -> Result<LispAST, LispError> {
+        if args.len() != 2 {
+            return Err(LispError::Arity {
+                form: "defunit".to_string(),
+                expected: "2".to_string(),
                 got: args.len(),
             });
         }
 
         let name = match &args[0] {
-            LispAST::Symbol(s) => s.clone(),
+            LispAST::Symbol(s) if !s.contains(['*', '·', '.', '/', '^']) => s.clone(),
+            LispAST::Symbol(s) => {
+                return Err(LispError::UnknownUnit {
+                    form: "defunit".to_string(),
+                    reason: format!("'{s}' cannot name a unit: it contains an operator"),
+                });
+            }
             other => {
                 return Err(LispError::TypeMismatch {
-                    form: "defunc".to_string(),
+                    form: "defunit".to_string(),
                     expected: "Symbol".to_string(),
                     got: other.type_name().to_string(),
                 });
             }
         };
 
-        let params = match &args[1] {
-            LispAST::List(param_list) => param_list
-                .iter()
-                .map(|p| match p {
-                    LispAST::Symbol(s) => Ok(s.clone()),
-                    other => Err(LispError::TypeMismatch {
-                        form: "defunc parameter".to_string(),
-                        expected: "Symbol".to_string(),
-                        got: other.type_name().to_string(),
-                    }),
-                })
-                .collect::<Result<Vec<_>, _>>()?,
+        let value = self.eval(args[1].clone())?;
+        let unit = match &value {
+            LispAST::Quantity { value, unit } => Unit::named(&name, *value * unit.scale, unit.dim),
+            LispAST::Number(n) => Unit::named(&name, *n, Dimension::NONE),
             other => {
                 return Err(LispError::TypeMismatch {
-                    form: "defunc".to_string(),
-                    expected: "List (parameter list)".to_string(),
+                    form: "defunit".to_string(),
+                    expected: "Quantity".to_string(),
                     got: other.type_name().to_string(),
                 });
             }
         };
-
-        let body = args[2].clone();
-
-        let user_func = LispAST::UserFunc {
-            params,
-            body: Box::new(body),
-        };
-
-        self.env.set(name, user_func.clone());
-        Ok(user_func)
+        self.env.set_unit(name, unit);
+        Ok(value)
     }
 
-    fn eval_if(&mut self, args: &[LispAST]) -> Result<LispAST, LispError> {
-        if args.len() != 3 {
+    /// `(series f x a n)`: `f` and `x` are taken as written, the expansion
+    /// point and order are evaluated.
+    fn eval_series(&mut self, args: &[LispAST]) -> Result<LispAST, LispError> {
+        if args.len() != 4 {
             return Err(LispError::Arity {
-                form: "if".to_string(),
-                expected: "3".to_string(),
+                form: "series".to_string(),
+                expected: "4 (expr, var, point, order)".to_string(),
                 got: args.len(),
             });
         }
+        let LispAST::Symbol(var) = &args[1] else {
+            return Err(LispError::TypeMismatch {
+                form: "series".to_string(),
+                expected: "Symbol".to_string(),
+                got: args[1].type_name().to_string(),
+            });
+        };
+        // `(series '(sin x) x 0 3)` reads naturally too; the quote is dropped.
+        let expr = match &args[0] {
+            LispAST::List(items) => match &items[..] {
+                [LispAST::Symbol(q), quoted] if q == "quote" => quoted,
+                _ => &args[0],
+            },
+            expr => expr,
+        };
+        let point = self.eval(args[2].clone())?;
+        let order = self.eval(args[3].clone())?;
+        let mut at = |expr: &LispAST, x| self.eval_with(expr, var, LispAST::Number(x));
+        Native::series(expr, var, &point, &order, &mut at)
+    }
 
-        let cond = self.eval(args[0].clone())?;
-        match cond {
-            LispAST::Bool(true) => self.eval(args[1].clone()),
-            LispAST::Bool(false) => self.eval(args[2].clone()),
+    /// `(fmt-number x (sig 3) (sci))`: `x` is evaluated, the options are taken
+    /// as written and layered over the environment's number format. The result
+    /// is the formatted text, with the unit appended for a quantity.
+    fn eval_fmt_number(&mut self, args: &[LispAST]) -> Result<LispAST, LispError> {
+        let Some((value, options)) = args.split_first() else {
+            return Err(LispError::Arity {
+                form: "fmt-number".to_string(),
+                expected: "at least 1".to_string(),
+                got: 0,
+            });
+        };
+        let mut policy = self.env.number_format;
+        for option in options {
+            policy.apply("fmt-number", option)?;
+        }
+        match self.eval(value.clone())? {
+            LispAST::Number(n) => Ok(LispAST::String(policy.format(n).to_string())),
+            LispAST::Quantity { value, unit } => {
+                Ok(LispAST::String(format!("{} {}", policy.format(value), unit)))
+            }
             other => Err(LispError::TypeMismatch {
-                form: "if condition".to_string(),
-                expected: "Bool".to_string(),
+                form: "fmt-number".to_string(),
+                expected: "Number or Quantity".to_string(),
                 got: other.type_name().to_string(),
             }),
         }
     }
 
-    fn eval_begin(&mut self, args: &[LispAST]) -> Result<LispAST, LispError> {
-        if args.is_empty() {
+    /// `(expect expr value)` or `(expect expr value tolerance)`: all three are
+    /// evaluated, `expr` is also kept as written for the failure message.
+    fn eval_expect(&mut self, args: &[LispAST]) -> Result<LispAST, LispError> {
+        if !(2..=3).contains(&args.len()) {
             return Err(LispError::Arity {
-                form: "begin".to_string(),
-                expected: "at least 1".to_string(),
-                got: 0,
+                form: "expect".to_string(),
+                expected: "2 or 3 (expr, value, tolerance)".to_string(),
+                got: args.len(),
             });
         }
-        let mut result = LispAST::Bool(false);
-        for expr in args {
-            result = self.eval(expr.clone())?;
+        let actual = self.eval(args[0].clone())?;
+        let expected = self.eval(args[1].clone())?;
+        let tolerance = args.get(2).map(|t| self.eval(t.clone())).transpose()?;
+        Native::expect(&args[0], &actual, &expected, tolerance.as_ref())
+    }
+
+    /// `(try expr (catch e handler...))`: the value of `expr`, or if evaluating
+    /// it raises, the value of the handler with `e` bound to the error.
+    fn eval_try(&mut self, args: &[LispAST]) -> Result<LispAST, LispError> {
+        let malformed = || LispError::TypeMismatch {
+            form: "try".to_string(),
+            expected: "(try expr (catch e handler))".to_string(),
+            got: format!("{} argument(s)", args.len()),
+        };
+        let [body, LispAST::List(clause)] = args else {
+            return Err(malformed());
+        };
+        let [LispAST::Symbol(catch), LispAST::Symbol(var), handler @ ..] = &clause[..] else {
+            return Err(malformed());
+        };
+        if catch != "catch" || handler.is_empty() {
+            return Err(malformed());
+        }
+
+        match self.eval(body.clone()) {
+            Ok(value) => Ok(value),
+            Err(error) => {
+                let handler = match handler {
+                    [single] => single.clone(),
+                    several => {
+                        let mut begin = vec![LispAST::Symbol("begin".to_string())];
+                        begin.extend_from_slice(several);
+                        LispAST::List(begin.into())
+                    }
+                };
+                self.eval_with(&handler, var, error.into_value())
+            }
         }
-        Ok(result)
+    }
+
+    /// Evaluate `expr` with `var` temporarily bound to `value`, restoring any
+    /// outer binding afterwards.
+    fn eval_with(&mut self, expr: &LispAST, var: &str, value: LispAST) -> Result<LispAST, LispError> {
+        let saved = self.env.get(var).cloned();
+        self.env.set(var.to_string(), value);
+        let result = self.eval(expr.clone());
+        match saved {
+            Some(original) => self.env.set(var.to_string(), original),
+            None => {
+                self.env.remove(var);
+            }
+        }
+        result
     }
 
     fn eval_string(&mut self, args: &[LispAST]) -> Result<LispAST, LispError> {
@@ -300,25 +751,19 @@ impl Interpreter {
         };
 
         let string_repr = match &value {
-            LispAST::String(s) => s.clone(),
-            LispAST::Symbol(s) => s.clone(),
-            LispAST::Number(n) => n.to_string(),
-            LispAST::Bool(b) => b.to_string(),
+            LispAST::String(s) | LispAST::Symbol(s) => s.clone(),
             LispAST::List(items) => {
                 // Convert list to string representation using graphemes
                 let parts: Vec<String> = items
                     .iter()
                     .map(|item| match item {
-                        LispAST::String(s) => s.clone(),
-                        LispAST::Symbol(s) => s.clone(),
-                        LispAST::Number(n) => n.to_string(),
-                        LispAST::Bool(b) => b.to_string(),
-                        _ => format!("{:?}", item),
+                        LispAST::String(s) | LispAST::Symbol(s) => s.clone(),
+                        other => other.to_string(),
                     })
                     .collect();
                 parts.join("")
             }
-            _ => format!("{:?}", value),
+            other => other.to_string(),
         };
 
         // Validate UTF-8 by collecting graphemes
@@ -332,3 +777,345 @@ impl Interpreter {
         &self.env
     }
 }
+
+/// The name a `(define name value)` binds, once the form's shape is checked.
+pub(crate) fn define_name(args: &[LispAST]) -> Result<String, LispError> {
+    if args.len() != 2 {
+        return Err(LispError::Arity {
+            form: "define".to_string(),
+            expected: "2".to_string(),
+            got: args.len(),
+        });
+    }
+
+    let name = match &args[0] {
+        LispAST::Symbol(s) => s.clone(),
+        other => {
+            return Err(LispError::TypeMismatch {
+                form: "define".to_string(),
+                expected: "Symbol".to_string(),
+                got: other.type_name().to_string(),
+            });
+        }
+    };
+
+    Ok(name)
+}
+
+/// The name and [`LispAST::UserFunc`] a
+/// `(defunc name (params...) "doc" body...)` binds. The docstring is optional
+/// and a body of several expressions runs as a `begin`.
+pub(crate) fn defunc_value(args: &[LispAST]) -> Result<(String, LispAST), LispError> {
+    let [name, params, body @ ..] = args else {
+        return Err(defunc_arity(args));
+    };
+    if body.is_empty() {
+        return Err(defunc_arity(args));
+    }
+
+    let name = match name {
+        LispAST::Symbol(s) => s.clone(),
+        other => {
+            return Err(LispError::TypeMismatch {
+                form: "defunc".to_string(),
+                expected: "Symbol".to_string(),
+                got: other.type_name().to_string(),
+            });
+        }
+    };
+
+    let (params, defaults) = match params {
+        LispAST::List(param_list) => lambda_list(&name, param_list)?,
+        other => {
+            return Err(LispError::TypeMismatch {
+                form: "defunc".to_string(),
+                expected: "List (parameter list)".to_string(),
+                got: other.type_name().to_string(),
+            });
+        }
+    };
+
+    // A lone string is the body, not a docstring.
+    let (doc, body) = match body {
+        [LispAST::String(doc), rest @ ..] if !rest.is_empty() => (Some(doc.as_str().into()), rest),
+        _ => (None, body),
+    };
+
+    let user_func = LispAST::UserFunc {
+        params: params.into(),
+        defaults: defaults.into(),
+        body: Arc::new(sequence(body)),
+        doc,
+    };
+
+    Ok((name, user_func))
+}
+
+fn defunc_arity(args: &[LispAST]) -> LispError {
+    LispError::Arity {
+        form: "defunc".to_string(),
+        expected: "at least 3 (name, (params...), body)".to_string(),
+        got: args.len(),
+    }
+}
+
+/// The parameter names of a `defunc` (markers included) and the defaults of
+/// its optional parameters, which are written `(name default)` or just
+/// `name` for a default of `false`.
+fn lambda_list(func: &str, items: &[LispAST]) -> Result<(Vec<String>, Vec<LispAST>), LispError> {
+    let malformed = |reason: &str| LispError::ParameterList {
+        form: "defunc".to_string(),
+        name: func.to_string(),
+        reason: reason.to_string(),
+    };
+    let mut params = Vec::new();
+    let mut defaults = Vec::new();
+    for item in items {
+        let optional = params.iter().any(|p| p == OPTIONAL) && !params.iter().any(|p| p == REST);
+        let name = match item {
+            LispAST::Symbol(s) if s == OPTIONAL && params.iter().any(|p| p == OPTIONAL || p == REST) => {
+                return Err(malformed(&format!("{OPTIONAL} must come once, before any {REST}")));
+            }
+            LispAST::Symbol(s) if s == REST && params.iter().any(|p| p == REST) => {
+                return Err(malformed(&format!("{REST} must come once")));
+            }
+            LispAST::Symbol(s) if s == OPTIONAL || s == REST => s.clone(),
+            LispAST::Symbol(s) => {
+                if optional {
+                    defaults.push(LispAST::Bool(false));
+                }
+                s.clone()
+            }
+            LispAST::List(pair) if optional => match &pair[..] {
+                [LispAST::Symbol(s), default] => {
+                    defaults.push(default.clone());
+                    s.clone()
+                }
+                _ => return Err(malformed("an optional parameter is written (name default)")),
+            },
+            other => {
+                return Err(LispError::TypeMismatch {
+                    form: "defunc parameter".to_string(),
+                    expected: "Symbol".to_string(),
+                    got: other.type_name().to_string(),
+                });
+            }
+        };
+        params.push(name);
+    }
+    if let Some(i) = params.iter().position(|p| p == REST)
+        && i + 2 != params.len()
+    {
+        return Err(malformed(&format!(
+            "{REST} must be followed by exactly one parameter"
+        )));
+    }
+    Ok((params, defaults))
+}
+
+/// Marks the start of the optional parameters.
+pub(crate) const OPTIONAL: &str = "&optional";
+/// Marks the parameter that collects the remaining arguments as a list.
+pub(crate) const REST: &str = "&rest";
+
+/// A parameter list split at its [`OPTIONAL`] and [`REST`] markers.
+pub(crate) struct Signature<'a> {
+    pub(crate) required: &'a [String],
+    pub(crate) optional: &'a [String],
+    pub(crate) rest: Option<&'a String>,
+}
+
+impl<'a> Signature<'a> {
+    pub(crate) fn of(params: &'a [String]) -> Self {
+        let (named, rest) = match params.iter().position(|p| p == REST) {
+            Some(i) => (&params[..i], params.get(i + 1)),
+            None => (params, None),
+        };
+        let (required, optional) = match named.iter().position(|p| p == OPTIONAL) {
+            Some(i) => (&named[..i], &named[i + 1..]),
+            None => (named, &named[named.len()..]),
+        };
+        Self {
+            required,
+            optional,
+            rest,
+        }
+    }
+
+    /// Whether every parameter is required.
+    pub(crate) fn is_plain(&self) -> bool {
+        self.optional.is_empty() && self.rest.is_none()
+    }
+
+    pub(crate) fn arity(&self) -> Arguments {
+        let min = self.required.len();
+        match (self.optional.len(), self.rest) {
+            (_, Some(_)) => Arguments::Atleast(min),
+            (0, None) => Arguments::Fixed(min),
+            (n, None) => Arguments::Range(min, min + n),
+        }
+    }
+}
+
+/// A user function's parameters as they would be written in its `defunc`,
+/// for documentation: `(x &optional (y 2) &rest more)`.
+#[must_use]
+pub fn parameter_list(func: &LispAST) -> Option<String> {
+    let LispAST::UserFunc {
+        params, defaults, ..
+    } = func
+    else {
+        return None;
+    };
+    let signature = Signature::of(params);
+    let mut words: Vec<String> = signature.required.to_vec();
+    if !signature.optional.is_empty() {
+        words.push(OPTIONAL.to_string());
+        for (param, default) in signature.optional.iter().zip(defaults.iter()) {
+            words.push(match default {
+                LispAST::Bool(false) => param.clone(),
+                default => format!("({param} {default})"),
+            });
+        }
+    }
+    if let Some(rest) = signature.rest {
+        words.push(REST.to_string());
+        words.push(rest.clone());
+    }
+    Some(format!("({})", words.join(" ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{environment::EnvironmentExt, parser::Parser};
+
+    fn run(src: &str) -> Result<LispAST, LispError> {
+        let env = Environment::new().with_native().with_prelude();
+        let mut interp = Interpreter::new(BTreeMap::new(), env);
+        interp.evaluate(Parser::new(src).parse()?)
+    }
+
+    fn string(s: &str) -> LispAST {
+        LispAST::String(s.to_string())
+    }
+
+    #[test]
+    fn expect_honors_its_tolerance() {
+        assert_eq!(run("(expect (/ 22 7) 3.14 (/ 1 100))"), Ok(LispAST::Bool(true)));
+        assert_eq!(run("(expect (qty 1001 m) (qty 1 km) 0.002)"), Ok(LispAST::Bool(true)));
+        let Err(LispError::AssertionFailed { expr, .. }) = run("(expect (/ 22 7) 3.14 0.001)")
+        else {
+            panic!("22/7 is not within 0.001 of 3.14");
+        };
+        assert_eq!(expr, "(/ 22 7)");
+    }
+
+    #[test]
+    fn handlers_see_kind_message_and_data() {
+        let raise = r#"(error "too cold" (qty -300 degC))"#;
+        assert_eq!(
+            run(&format!("(try {raise} (catch e (error-kind e)))")),
+            Ok(string("mazer::lisp::raised"))
+        );
+        assert_eq!(
+            run(&format!("(try {raise} (catch e (error-message e)))")),
+            Ok(string("too cold"))
+        );
+        assert_eq!(
+            run(&format!("(try {raise} (catch e (error-data e)))")),
+            run("(qty -300 degC)")
+        );
+    }
+
+    #[test]
+    fn native_errors_are_caught() {
+        assert_eq!(
+            run("(try (/ 1 0) (catch e (error-kind e)))"),
+            Ok(string("mazer::lisp::division_by_zero"))
+        );
+        assert_eq!(
+            run("(try (sqrt 1 2) (catch e (error? e)))"),
+            Ok(LispAST::Bool(true))
+        );
+        assert_eq!(run("(try 7 (catch e 0))"), run("7"));
+    }
+
+    #[test]
+    fn nested_tries_catch_innermost_first() {
+        assert_eq!(
+            run(r#"(try (+ 1 (try (error "inner") (catch e 10))) (catch e 0))"#),
+            run("11")
+        );
+        assert_eq!(
+            run(r#"(try (try (error "inner") (catch e (error (concat "outer: " (error-message e)))))
+                        (catch e (error-message e)))"#),
+            Ok(string("outer: inner"))
+        );
+        // The handler's binding does not outlive it.
+        assert_eq!(run("(begin (define e 5) (try (/ 1 0) (catch e 1)) e)"), run("5"));
+    }
+
+    #[test]
+    fn handlers_can_re_raise() {
+        assert_eq!(
+            run(r#"(try (error "lost" 42) (catch e (error e)))"#),
+            Err(LispError::Raised {
+                message: "lost".to_string(),
+                data: Some(Box::new(LispAST::Number(42.into()))),
+            })
+        );
+        assert_eq!(
+            run("(try (try (/ 1 0) (catch e (error e))) (catch e (error-message e)))"),
+            Ok(string(&LispError::DivisionByZero.to_string()))
+        );
+    }
+
+    #[test]
+    fn missing_optional_arguments_take_their_defaults() {
+        let def = "(defunc f (a &optional b (c (+ a 1))) (vec a b c))";
+        assert_eq!(run(&format!("{def} (f 1)")), run("(vec 1 false 2)"));
+        assert_eq!(run(&format!("{def} (f 1 2)")), run("(vec 1 2 2)"));
+        assert_eq!(run(&format!("{def} (f 1 2 7)")), run("(vec 1 2 7)"));
+    }
+
+    #[test]
+    fn rest_parameters_may_collect_nothing() {
+        let def = "(defunc f (a &rest more) more)";
+        assert_eq!(run(&format!("{def} (f 1)")), Ok(LispAST::List([].into())));
+        assert_eq!(run(&format!("{def} (f 1 2 3)")), run("'(2 3)"));
+    }
+
+    #[test]
+    fn extra_arguments_are_an_arity_error() {
+        assert_eq!(
+            run("(defunc f (a &optional b) a) (f 1 2 3)"),
+            Err(LispError::Arity {
+                form: "function".to_string(),
+                expected: "1 or 2".to_string(),
+                got: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn malformed_parameter_lists_have_their_own_kind() {
+        for params in ["(a &rest)", "(&rest a b)", "(&optional a &optional b)", "(&optional (a))"] {
+            assert_eq!(
+                run(&format!("(try (defunc f {params} a) (catch e (error-kind e)))")),
+                Ok(string("mazer::lisp::parameter_list")),
+                "{params}"
+            );
+        }
+    }
+
+    #[test]
+    fn a_lone_string_is_the_body_not_a_docstring() {
+        let Ok(LispAST::UserFunc { body, doc, .. }) = run(r#"(defunc f (x) "Just a doc.")"#) else {
+            panic!("defunc returns the function");
+        };
+        assert_eq!(*body, string("Just a doc."));
+        assert_eq!(doc, None);
+        assert_eq!(run(r#"(defunc f (x) "Just a doc.") (f 1)"#), Ok(string("Just a doc.")));
+    }
+}
@@ -1,7 +1,12 @@
 pub mod ast;
+mod bytecode;
 pub mod environment;
 pub mod interpreter;
+pub mod macros;
+pub mod modules;
 pub mod parser;
+pub mod trace;
+mod vm;
 pub mod wrapper;
 
 pub use environment::EnvironmentExt;
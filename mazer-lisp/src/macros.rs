@@ -0,0 +1,335 @@
+//! `defmacro` and quasiquotation.
+//!
+//! Macros are expanded in a pass over each form before it is evaluated: a
+//! call `(name args...)` to a macro binds its parameters to the unevaluated
+//! arguments, evaluates the macro body to build a replacement form, and
+//! expands that in turn. Templates are usually written with quasiquote:
+//!
+//! ```text
+//! (defmacro unless (cond &rest body) `(if ,cond false (begin ,@body)))
+//! ```
+
+use std::sync::Arc;
+
+use mazer_types::{LispAST, LispError};
+
+use crate::interpreter::{Interpreter, REST, Signature};
+
+/// How many expansions one form may go through before it is taken to be a
+/// macro that expands into itself.
+const MAX_EXPANSIONS: usize = 512;
+
+fn malformed(form: &str, expected: &str, got: &LispAST) -> LispError {
+    LispError::TypeMismatch {
+        form: form.to_string(),
+        expected: expected.to_string(),
+        got: got.type_name().to_string(),
+    }
+}
+
+pub(crate) fn sequence(body: &[LispAST]) -> LispAST {
+    match body {
+        [single] => single.clone(),
+        several => {
+            let mut begin = vec![LispAST::Symbol("begin".to_string())];
+            begin.extend_from_slice(several);
+            LispAST::List(begin.into())
+        }
+    }
+}
+
+impl Interpreter {
+    /// Expand every macro call in `expr`, registering any `defmacro` met on
+    /// the way so later forms can use it. Quoted data is left alone.
+    ///
+    /// # Errors
+    /// Malformed `defmacro`s, macro bodies that fail to evaluate, arity
+    /// mismatches, and runaway expansion.
+    pub fn expand(&mut self, expr: LispAST) -> Result<LispAST, LispError> {
+        self.captured(|interp| interp.expand_counted(expr, 0))
+    }
+
+    fn expand_counted(&mut self, expr: LispAST, depth: usize) -> Result<LispAST, LispError> {
+        let LispAST::List(items) = expr else {
+            return Ok(expr);
+        };
+        let Some(LispAST::Symbol(head)) = items.first() else {
+            return items
+                .iter()
+                .map(|item| self.expand_counted(item.clone(), depth))
+                .collect::<Result<_, _>>()
+                .map(LispAST::List);
+        };
+
+        match head.as_str() {
+            "quote" | "quasiquote" => Ok(LispAST::List(items)),
+            "defmacro" => {
+                self.eval_defmacro(&items[1..])?;
+                Ok(LispAST::List(items))
+            }
+            // Only the body of a function definition holds code.
+            "defunc" if items.len() > 3 => {
+                let mut items = items.to_vec();
+                let body = items.split_off(3);
+                for form in body {
+                    items.push(self.expand_counted(form, depth)?);
+                }
+                Ok(LispAST::List(items.into()))
+            }
+            name if self.env.macros.contains_key(name) => {
+                if depth >= MAX_EXPANSIONS {
+                    return Err(LispError::MacroExpansion {
+                        name: name.to_string(),
+                        reason: format!("expansion did not finish after {MAX_EXPANSIONS} steps"),
+                    });
+                }
+                let expansion = self.apply_macro(name, &items[1..])?;
+                self.expand_counted(expansion, depth + 1)
+            }
+            _ => items
+                .iter()
+                .map(|item| self.expand_counted(item.clone(), depth))
+                .collect::<Result<_, _>>()
+                .map(LispAST::List),
+        }
+    }
+
+    /// Bind the macro's parameters to `args` as written and evaluate its body.
+    fn apply_macro(&mut self, name: &str, args: &[LispAST]) -> Result<LispAST, LispError> {
+        let Some(LispAST::UserFunc { params, body, .. }) = self.env.macros.get(name).cloned() else {
+            return Err(LispError::UnboundSymbol {
+                name: name.to_string(),
+            });
+        };
+
+        let signature = Signature::of(&params);
+        signature.arity().check(name, args)?;
+        let (fixed, rest) = (signature.required, signature.rest);
+
+        let mut bindings: Vec<(String, LispAST)> =
+            fixed.iter().cloned().zip(args.iter().cloned()).collect();
+        if let Some(rest) = rest {
+            bindings.push((rest.clone(), LispAST::List(args[fixed.len()..].into())));
+        }
+
+        let saved: Vec<_> = bindings
+            .iter()
+            .map(|(param, _)| (param.clone(), self.env.get(param).cloned()))
+            .collect();
+        for (param, value) in bindings {
+            self.env.set(param, value);
+        }
+        let expansion = self.eval(Arc::unwrap_or_clone(body));
+        for (param, original) in saved {
+            match original {
+                Some(value) => self.env.set(param, value),
+                None => {
+                    self.env.remove(&param);
+                }
+            }
+        }
+        expansion
+    }
+
+    /// `(defmacro name (params... &rest rest) body...)`: register a macro.
+    pub(crate) fn eval_defmacro(&mut self, args: &[LispAST]) -> Result<LispAST, LispError> {
+        let [name, params, body @ ..] = args else {
+            return Err(LispError::Arity {
+                form: "defmacro".to_string(),
+                expected: "at least 3 (name, (params...), body)".to_string(),
+                got: args.len(),
+            });
+        };
+        if body.is_empty() {
+            return Err(LispError::Arity {
+                form: "defmacro".to_string(),
+                expected: "at least 3 (name, (params...), body)".to_string(),
+                got: args.len(),
+            });
+        }
+        let LispAST::Symbol(name) = name else {
+            return Err(malformed("defmacro", "Symbol", name));
+        };
+        let LispAST::List(params) = params else {
+            return Err(malformed("defmacro", "List (parameter list)", params));
+        };
+        let params = params
+            .iter()
+            .map(|p| match p {
+                LispAST::Symbol(s) => Ok(s.clone()),
+                other => Err(malformed("defmacro parameter", "Symbol", other)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(i) = params.iter().position(|p| p == REST)
+            && i + 2 != params.len()
+        {
+            return Err(LispError::ParameterList {
+                form: "defmacro".to_string(),
+                name: name.clone(),
+                reason: format!("{REST} must be followed by exactly one parameter"),
+            });
+        }
+
+        self.env.set_macro(
+            name.clone(),
+            LispAST::UserFunc {
+                params: params.into(),
+                defaults: [].into(),
+                body: Arc::new(sequence(body)),
+                doc: None,
+            },
+        );
+        Ok(LispAST::Symbol(name.clone()))
+    }
+
+    /// `(quasiquote template)`: the template as data, except that
+    /// `(unquote e)` is replaced by the value of `e` and `(unquote-splicing e)`
+    /// by the elements of the list `e` evaluates to.
+    pub(crate) fn eval_quasiquote(&mut self, args: &[LispAST]) -> Result<LispAST, LispError> {
+        let [template] = args else {
+            return Err(LispError::Arity {
+                form: "quasiquote".to_string(),
+                expected: "1".to_string(),
+                got: args.len(),
+            });
+        };
+        self.quasi(template, 1)
+    }
+
+    /// Fill in a template at quasiquote nesting `level`; only unquotes at
+    /// level 1 are evaluated.
+    fn quasi(&mut self, template: &LispAST, level: usize) -> Result<LispAST, LispError> {
+        let LispAST::List(items) = template else {
+            return Ok(template.clone());
+        };
+        if let [LispAST::Symbol(head), inner] = &items[..] {
+            match head.as_str() {
+                "unquote" if level == 1 => return self.eval(inner.clone()),
+                "unquote" | "unquote-splicing" => {
+                    return Ok(LispAST::List(vec![
+                        LispAST::Symbol(head.clone()),
+                        self.quasi(inner, level - 1)?,
+                    ].into()));
+                }
+                "quasiquote" => {
+                    return Ok(LispAST::List(vec![
+                        LispAST::Symbol(head.clone()),
+                        self.quasi(inner, level + 1)?,
+                    ].into()));
+                }
+                _ => {}
+            }
+        }
+
+        let mut out = Vec::with_capacity(items.len());
+        for item in items.iter() {
+            if level == 1
+                && let LispAST::List(parts) = item
+                && let [LispAST::Symbol(head), inner] = &parts[..]
+                && head == "unquote-splicing"
+            {
+                match self.eval(inner.clone())? {
+                    LispAST::List(spliced) | LispAST::Set(spliced) => {
+                        out.extend(spliced.iter().cloned());
+                    }
+                    other => return Err(malformed("unquote-splicing", "List", &other)),
+                }
+            } else {
+                out.push(self.quasi(item, level)?);
+            }
+        }
+        Ok(LispAST::List(out.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use mazer_types::Environment;
+
+    use super::*;
+    use crate::{environment::EnvironmentExt, parser::Parser};
+
+    fn interpreter() -> Interpreter {
+        Interpreter::new(BTreeMap::new(), Environment::new().with_native().with_prelude())
+    }
+
+    fn run(interp: &mut Interpreter, src: &str) -> Result<LispAST, LispError> {
+        let expr = Parser::new(src).parse()?;
+        let expr = interp.expand(expr)?;
+        interp.evaluate(expr)
+    }
+
+    fn read(src: &str) -> LispAST {
+        Parser::new(src).parse().unwrap()
+    }
+
+    #[test]
+    fn calls_expand_into_their_template() {
+        let mut interp = interpreter();
+        run(&mut interp, "(defmacro unless (c a b) `(if ,c ,b ,a))").unwrap();
+        assert_eq!(
+            interp.expand(read("(+ 1 (unless false 2 3))")),
+            Ok(read("(+ 1 (if false 3 2))"))
+        );
+        assert_eq!(run(&mut interp, "(unless false 2 3)"), Ok(read("2")));
+    }
+
+    #[test]
+    fn rest_parameters_collect_the_remaining_forms() {
+        let mut interp = interpreter();
+        run(&mut interp, "(defmacro count-forms (first &rest more) (length more))").unwrap();
+        assert_eq!(run(&mut interp, "(count-forms a)"), Ok(read("0")));
+        assert_eq!(run(&mut interp, "(count-forms a (b c) d)"), Ok(read("2")));
+    }
+
+    #[test]
+    fn splicing_inserts_elements_in_place() {
+        let mut interp = interpreter();
+        assert_eq!(
+            run(&mut interp, "(define xs '(1 2)) `(a ,@xs b ,xs)"),
+            Ok(read("(a 1 2 b (1 2))"))
+        );
+        assert!(matches!(
+            run(&mut interp, "`(a ,@3)"),
+            Err(LispError::TypeMismatch { form, .. }) if form == "unquote-splicing"
+        ));
+    }
+
+    #[test]
+    fn only_the_outermost_level_is_unquoted() {
+        let mut interp = interpreter();
+        let nested = run(&mut interp, "(define x 1) `(a `(b ,(c ,x)))").unwrap();
+        assert_eq!(nested.to_string(), "(a `(b ,(c 1)))");
+    }
+
+    #[test]
+    fn macro_arity_is_checked() {
+        let mut interp = interpreter();
+        run(&mut interp, "(defmacro two (a b) a)").unwrap();
+        assert_eq!(
+            run(&mut interp, "(two 1)"),
+            Err(LispError::Arity {
+                form: "two".to_string(),
+                expected: "2".to_string(),
+                got: 1,
+            })
+        );
+        assert!(matches!(
+            run(&mut interp, "(defmacro bad (&rest) 1)"),
+            Err(LispError::ParameterList { name, .. }) if name == "bad"
+        ));
+    }
+
+    #[test]
+    fn runaway_expansion_stops() {
+        let mut interp = interpreter();
+        run(&mut interp, "(defmacro forever () '(forever))").unwrap();
+        let Err(LispError::MacroExpansion { name, reason }) = run(&mut interp, "(forever)") else {
+            panic!("expected the expansion limit");
+        };
+        assert_eq!(name, "forever");
+        assert!(reason.contains(&MAX_EXPANSIONS.to_string()));
+    }
+}
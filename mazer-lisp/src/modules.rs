@@ -0,0 +1,446 @@
+//! `import` and `require`: sharing definitions across notes.
+//!
+//! A module is a `.zr` note, whose eval blocks are run in order, or a `.mzl`
+//! file of plain Lisp. Either is evaluated once in a fresh standard
+//! environment, and whatever it defines (bindings, macros and units) is
+//! exported:
+//!
+//! ```text
+//! (import "lib/geometry.zr")                 ; geometry/area, geometry/perimeter
+//! (import "lib/geometry.zr" :as g)           ; g/area, g/perimeter
+//! (import "lib/geometry.zr" :only [area])    ; area, plus the qualified names
+//! (require "helpers.mzl")                    ; every definition, unqualified
+//! ```
+//!
+//! Paths are relative to the importing file. Loaded modules are cached for the
+//! rest of the run, and a module that ends up importing itself is an
+//! [`LispError::ImportCycle`].
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+
+use mazer_types::{Environment, LispAST, LispError, Unit};
+
+use crate::environment::EnvironmentExt;
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+
+/// Where module sources are read from.
+#[derive(Default)]
+pub enum Files {
+    /// The real filesystem.
+    #[default]
+    Disk,
+    /// An in-memory map from path to source, for builds without a filesystem
+    /// such as wasm.
+    Virtual(BTreeMap<PathBuf, String>),
+}
+
+/// What a module defined, before any prefix is applied.
+#[derive(Clone)]
+struct Module {
+    /// The file stem, the default namespace.
+    name: String,
+    bindings: Vec<(String, LispAST)>,
+    macros: Vec<(String, LispAST)>,
+    units: Rc<BTreeMap<String, Unit>>,
+}
+
+/// The module state of one interpreter: where relative paths start, which
+/// files are being loaded (to catch cycles), and the cache shared by the whole
+/// run.
+#[derive(Clone, Default)]
+pub struct Modules {
+    files: Rc<Files>,
+    dir: PathBuf,
+    loading: Vec<PathBuf>,
+    cache: Rc<RefCell<BTreeMap<PathBuf, Module>>>,
+}
+
+/// Resolve `.` and `..` without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !out.pop() {
+                    out.push("..");
+                }
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |n| n.to_string_lossy().into_owned(),
+    )
+}
+
+/// The source of every eval block in a note, in document order.
+fn eval_blocks(nodes: &[mazer_parser::MdAst], out: &mut Vec<String>) {
+    for node in nodes {
+        match node {
+            mazer_parser::MdAst::EvalBlock { code } => out.push(code.clone()),
+            mazer_parser::MdAst::Paragraph { children } => eval_blocks(children, out),
+            _ => {}
+        }
+    }
+}
+
+/// The top-level forms of a module.
+fn forms(path: &Path, source: &str) -> Result<Vec<LispAST>, String> {
+    let blocks = match path.extension().and_then(|e| e.to_str()) {
+        Some("mzl") => vec![source.to_string()],
+        Some("zr" | "md") => {
+            let nodes = mazer_parser::Parser::new(source)
+                .parse()
+                .map_err(|e| e.to_string())?;
+            let mut blocks = Vec::new();
+            eval_blocks(&nodes, &mut blocks);
+            blocks
+        }
+        _ => return Err("expected a .zr note or a .mzl file".to_string()),
+    };
+    blocks
+        .iter()
+        .filter(|code| !code.trim().is_empty())
+        .map(|code| Parser::new(code).parse().map_err(|e| e.to_string()))
+        .collect()
+}
+
+/// `value` with references to the module's own `names` qualified by
+/// `prefix`, so an imported function still finds its helpers. Only function
+/// and macro bodies hold such references; parameters shadow.
+fn qualify(value: &LispAST, names: &BTreeSet<String>, prefix: &str) -> LispAST {
+    match value {
+        LispAST::UserFunc {
+            params,
+            defaults,
+            body,
+            doc,
+        } if !prefix.is_empty() => LispAST::UserFunc {
+            params: params.clone(),
+            defaults: defaults
+                .iter()
+                .map(|default| rewrite(default, names, prefix, params))
+                .collect(),
+            body: Arc::new(rewrite(body, names, prefix, params)),
+            doc: doc.clone(),
+        },
+        other => other.clone(),
+    }
+}
+
+fn rewrite(expr: &LispAST, names: &BTreeSet<String>, prefix: &str, bound: &[String]) -> LispAST {
+    match expr {
+        LispAST::Symbol(s) if names.contains(s) && !bound.contains(s) => {
+            LispAST::Symbol(format!("{prefix}{s}"))
+        }
+        LispAST::List(items) if matches!(items.first(), Some(LispAST::Symbol(q)) if q == "quote") => {
+            expr.clone()
+        }
+        LispAST::List(items) => LispAST::List(
+            items
+                .iter()
+                .map(|item| rewrite(item, names, prefix, bound))
+                .collect(),
+        ),
+        LispAST::UserFunc { .. } => qualify(expr, names, prefix),
+        other => other.clone(),
+    }
+}
+
+impl Modules {
+    /// Modules for the note at `path` on disk: imports resolve from its
+    /// directory, and it counts as loading for cycle detection.
+    pub fn for_file(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        Self {
+            files: Rc::new(Files::Disk),
+            dir,
+            loading: vec![std::fs::canonicalize(path).unwrap_or_else(|_| normalize(path))],
+            cache: Rc::default(),
+        }
+    }
+
+    /// Modules served from `files`, a map from path to source, for the note at
+    /// `entry` (itself not necessarily in the map).
+    pub fn in_memory(files: impl IntoIterator<Item = (String, String)>, entry: &str) -> Self {
+        let files = files
+            .into_iter()
+            .map(|(path, source)| (normalize(Path::new(&path)), source))
+            .collect();
+        let entry = normalize(Path::new(entry));
+        Self {
+            files: Rc::new(Files::Virtual(files)),
+            dir: entry.parent().map(Path::to_path_buf).unwrap_or_default(),
+            loading: vec![entry],
+            cache: Rc::default(),
+        }
+    }
+
+    fn resolve(&self, path: &str) -> PathBuf {
+        let joined = self.dir.join(path);
+        match *self.files {
+            Files::Disk => std::fs::canonicalize(&joined).unwrap_or_else(|_| normalize(&joined)),
+            Files::Virtual(_) => normalize(&joined),
+        }
+    }
+
+    fn read(&self, path: &Path) -> Result<String, String> {
+        match &*self.files {
+            Files::Disk => std::fs::read_to_string(path).map_err(|e| e.to_string()),
+            Files::Virtual(files) => files
+                .get(path)
+                .cloned()
+                .ok_or_else(|| "no such file".to_string()),
+        }
+    }
+
+    /// Load (or fetch from the cache) the module `written` refers to.
+    fn load(&self, written: &str) -> Result<Module, LispError> {
+        let path = self.resolve(written);
+        if let Some(start) = self.loading.iter().position(|p| *p == path) {
+            let chain: Vec<String> = self.loading[start..]
+                .iter()
+                .chain(std::iter::once(&path))
+                .map(|p| file_name(p))
+                .collect();
+            return Err(LispError::ImportCycle {
+                chain: chain.join(" → "),
+            });
+        }
+        if let Some(module) = self.cache.borrow().get(&path) {
+            return Ok(module.clone());
+        }
+
+        let failed = |reason: String| LispError::ImportFailed {
+            path: written.to_string(),
+            reason,
+        };
+        let source = self.read(&path).map_err(failed)?;
+        let forms = forms(&path, &source).map_err(failed)?;
+
+        let mut loading = self.loading.clone();
+        loading.push(path.clone());
+        let modules = Self {
+            files: Rc::clone(&self.files),
+            dir: path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            loading,
+            cache: Rc::clone(&self.cache),
+        };
+        let base = Environment::new().with_native().with_prelude();
+        let mut interp = Interpreter::new(BTreeMap::new(), base.clone()).with_modules(modules);
+        for form in forms {
+            interp
+                .expand(form)
+                .and_then(|form| interp.eval(form))
+                .map_err(|e| match e {
+                    LispError::ImportFailed { .. } | LispError::ImportCycle { .. } => e,
+                    other => failed(other.to_string()),
+                })?;
+        }
+
+        let env = interp.env();
+        let module = Module {
+            name: path
+                .file_stem()
+                .map_or_else(|| written.to_string(), |s| s.to_string_lossy().into_owned()),
+            bindings: env
+                .bindings
+                .iter()
+                .filter(|(name, value)| base.get(name) != Some(value))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect(),
+            macros: env
+                .macros
+                .iter()
+                .map(|(n, m)| (n.clone(), m.clone()))
+                .collect(),
+            units: env.units.clone(),
+        };
+        self.cache.borrow_mut().insert(path, module.clone());
+        Ok(module)
+    }
+}
+
+impl Interpreter {
+    /// Use `modules` to resolve imports instead of the working directory.
+    #[must_use]
+    pub fn with_modules(mut self, modules: Modules) -> Self {
+        self.modules = modules;
+        self
+    }
+
+    /// `(import "path" [:as ns] [:only (names...)])`, or `(require "path")`
+    /// for unqualified names. Returns the namespace symbol.
+    pub(crate) fn eval_import(
+        &mut self,
+        args: &[LispAST],
+        qualified: bool,
+    ) -> Result<LispAST, LispError> {
+        let form = if qualified { "import" } else { "require" };
+        let Some((path, options)) = args.split_first() else {
+            return Err(LispError::Arity {
+                form: form.to_string(),
+                expected: "at least 1".to_string(),
+                got: 0,
+            });
+        };
+        let path = match self.eval(path.clone())? {
+            LispAST::String(path) => path,
+            other => {
+                return Err(LispError::TypeMismatch {
+                    form: form.to_string(),
+                    expected: "String (a path)".to_string(),
+                    got: other.type_name().to_string(),
+                });
+            }
+        };
+
+        let mut alias = None;
+        let mut only = None;
+        for pair in options.chunks(2) {
+            match pair {
+                [
+                    LispAST::Keyword(k),
+                    LispAST::Symbol(name) | LispAST::String(name),
+                ] if k == "as" => {
+                    alias = Some(name.clone());
+                }
+                [LispAST::Keyword(k), LispAST::List(names)] if k == "only" => {
+                    let names = match names.split_first() {
+                        Some((LispAST::Symbol(head), rest)) if head == "vec" => rest,
+                        _ => names,
+                    };
+                    only = Some(
+                        names
+                            .iter()
+                            .map(|n| match n {
+                                LispAST::Symbol(s) => Ok(s.clone()),
+                                other => Err(LispError::TypeMismatch {
+                                    form: format!("{form} :only"),
+                                    expected: "Symbol".to_string(),
+                                    got: other.type_name().to_string(),
+                                }),
+                            })
+                            .collect::<Result<Vec<_>, _>>()?,
+                    );
+                }
+                other => {
+                    return Err(LispError::TypeMismatch {
+                        form: form.to_string(),
+                        expected: ":as name or :only (names...)".to_string(),
+                        got: other
+                            .iter()
+                            .map(LispAST::type_name)
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                    });
+                }
+            }
+        }
+
+        let module = self.modules.load(&path)?;
+        let namespace = alias.unwrap_or_else(|| module.name.clone());
+        let prefix = if qualified || only.is_some() {
+            format!("{namespace}/")
+        } else {
+            String::new()
+        };
+        let names: BTreeSet<String> = module
+            .bindings
+            .iter()
+            .chain(&module.macros)
+            .map(|(name, _)| name.clone())
+            .collect();
+
+        for (name, value) in &module.bindings {
+            self.env
+                .set(format!("{prefix}{name}"), qualify(value, &names, &prefix));
+        }
+        for (name, value) in &module.macros {
+            self.env
+                .set_macro(format!("{prefix}{name}"), qualify(value, &names, &prefix));
+        }
+        for (name, unit) in module.units.iter() {
+            self.env.set_unit(name.clone(), unit.clone());
+        }
+
+        for name in only.unwrap_or_default() {
+            let binding = module.bindings.iter().find(|(n, _)| *n == name);
+            let macro_ = module.macros.iter().find(|(n, _)| *n == name);
+            match (binding, macro_) {
+                (None, None) => {
+                    return Err(LispError::ImportFailed {
+                        path,
+                        reason: format!("it does not define `{name}`"),
+                    });
+                }
+                (binding, macro_) => {
+                    if let Some((_, value)) = binding {
+                        self.env.set(name.clone(), qualify(value, &names, &prefix));
+                    }
+                    if let Some((_, value)) = macro_ {
+                        self.env.set_macro(name.clone(), qualify(value, &names, &prefix));
+                    }
+                }
+            }
+        }
+
+        Ok(LispAST::Symbol(namespace))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(files: &[(&str, &str)], src: &str) -> Result<LispAST, LispError> {
+        let files = files
+            .iter()
+            .map(|(p, s)| ((*p).to_string(), (*s).to_string()));
+        let env = Environment::new().with_native().with_prelude();
+        let mut interp = Interpreter::new(BTreeMap::new(), env)
+            .with_modules(Modules::in_memory(files, "notes/main.zr"));
+        let program = Parser::new(src).parse()?;
+        interp.eval(program)
+    }
+
+    #[test]
+    fn qualified_functions_find_their_helpers() {
+        let lib = (
+            "notes/lib/sq.mzl",
+            "(defunc sq (x) (* x x)) (defunc quad (x) (sq (sq x)))",
+        );
+        assert_eq!(
+            run(&[lib], r#"(import "lib/sq.mzl" :as m) (m/quad 2)"#),
+            Ok(LispAST::Number(16.into()))
+        );
+        assert!(matches!(
+            run(&[lib], r#"(import "lib/sq.mzl" :only (quad)) (sq 2)"#),
+            Err(LispError::UnboundSymbol { .. } | LispError::UnboundSymbolDidYouMean { .. })
+        ));
+    }
+
+    #[test]
+    fn cycles_are_reported() {
+        let a = ("notes/a.zr", r#"(eval (import "b.zr"))"#);
+        let b = ("notes/b.zr", r#"(eval (import "./a.zr"))"#);
+        assert_eq!(
+            run(&[a, b], r#"(import "a.zr")"#),
+            Err(LispError::ImportCycle {
+                chain: "a.zr → b.zr → a.zr".to_string()
+            })
+        );
+    }
+}
@@ -1,5 +1,13 @@
+//! The reader: Lisp source text to [`LispAST`].
+//!
+//! Beyond parens, numbers, strings and symbols it understands `;` line
+//! comments, nestable `#| ... |#` block comments, the prefixes `'x`, `` `x ``,
+//! `,x` and `,@x`, `[a b c]` vector literals (read as `(vec a b c)`) and
+//! `:keyword`s. Syntax errors carry the line and column they occurred at.
+
 use fastnum::{D512, decimal::Context};
 use mazer_types::{LispAST, LispError};
+use miette::SourceSpan;
 
 pub enum LispToken {
     Symbol(String),
@@ -7,14 +15,38 @@ pub enum LispToken {
     /// Text that looked numeric but failed to parse; surfaced as an error by the
     /// parser rather than panicking in the tokenizer.
     BadNumber(String),
+    /// A double-quoted string literal, escapes resolved.
+    Str(String),
+    /// A string literal still open at the end of input.
+    UnterminatedString,
+    /// A `#|` block comment still open at the end of input.
+    UnterminatedComment,
+    /// `:name`, without the colon.
+    Keyword(String),
+    /// A reader prefix that wraps the next datum: `'x` is `(quote x)`, and
+    /// likewise `` ` `` quasiquote, `,` unquote and `,@` unquote-splicing.
+    Prefix(&'static str),
     OpenParen,
     CloseParen,
+    OpenBracket,
+    CloseBracket,
+}
+
+/// A token and the byte range of source it was read from.
+pub struct Spanned {
+    pub token: LispToken,
+    pub span: SourceSpan,
 }
 
 pub struct Tokenizer {
     src: String,
 }
 
+/// Characters that end a symbol, number or keyword.
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '(' | ')' | '[' | ']' | ',' | ';' | '"')
+}
+
 impl Tokenizer {
     pub fn new(src: &str) -> Self {
         Tokenizer {
@@ -22,77 +54,158 @@ impl Tokenizer {
         }
     }
 
-    pub fn tokenize(&self) -> Vec<LispToken> {
+    pub fn tokenize(&self) -> Vec<Spanned> {
         let mut tokens = Vec::new();
-        let chars: Vec<char> = self.src.chars().collect();
+        let chars: Vec<(usize, char)> = self.src.char_indices().collect();
+        let at = |i: usize| chars.get(i).map(|(_, c)| *c);
+        let offset = |i: usize| chars.get(i).map_or(self.src.len(), |(o, _)| *o);
         let mut i = 0;
 
         while i < chars.len() {
-            match chars[i] {
+            let start = i;
+            let token = match chars[i].1 {
+                c if c.is_whitespace() => {
+                    i += 1;
+                    continue;
+                }
+                ';' => {
+                    while at(i).is_some_and(|c| c != '\n') {
+                        i += 1;
+                    }
+                    continue;
+                }
+                '#' if at(i + 1) == Some('|') => {
+                    i += 2;
+                    let mut depth = 1;
+                    while depth > 0 && i < chars.len() {
+                        match (chars[i].1, at(i + 1)) {
+                            ('|', Some('#')) => {
+                                depth -= 1;
+                                i += 2;
+                            }
+                            ('#', Some('|')) => {
+                                depth += 1;
+                                i += 2;
+                            }
+                            _ => i += 1,
+                        }
+                    }
+                    if depth == 0 {
+                        continue;
+                    }
+                    LispToken::UnterminatedComment
+                }
                 '(' => {
-                    tokens.push(LispToken::OpenParen);
                     i += 1;
+                    LispToken::OpenParen
                 }
                 ')' => {
-                    tokens.push(LispToken::CloseParen);
                     i += 1;
+                    LispToken::CloseParen
                 }
-                c if c.is_whitespace() => {
+                '[' => {
+                    i += 1;
+                    LispToken::OpenBracket
+                }
+                ']' => {
                     i += 1;
+                    LispToken::CloseBracket
+                }
+                '\'' => {
+                    i += 1;
+                    LispToken::Prefix("quote")
+                }
+                '`' => {
+                    i += 1;
+                    LispToken::Prefix("quasiquote")
+                }
+                ',' if at(i + 1) == Some('@') => {
+                    i += 2;
+                    LispToken::Prefix("unquote-splicing")
+                }
+                ',' => {
+                    i += 1;
+                    LispToken::Prefix("unquote")
+                }
+                '"' => {
+                    i += 1;
+                    let mut text = String::new();
+                    let mut closed = false;
+                    while i < chars.len() {
+                        match chars[i].1 {
+                            '"' => {
+                                closed = true;
+                                i += 1;
+                                break;
+                            }
+                            '\\' if i + 1 < chars.len() => {
+                                text.push(match chars[i + 1].1 {
+                                    'n' => '\n',
+                                    't' => '\t',
+                                    other => other,
+                                });
+                                i += 2;
+                            }
+                            c => {
+                                text.push(c);
+                                i += 1;
+                            }
+                        }
+                    }
+                    if closed {
+                        LispToken::Str(text)
+                    } else {
+                        LispToken::UnterminatedString
+                    }
                 }
                 // Handle numbers: must start with digit, or minus followed by digit
-                c if c.is_numeric()
-                    || (c == '-' && i + 1 < chars.len() && chars[i + 1].is_numeric()) =>
-                {
-                    let start = i;
+                c if c.is_numeric() || (c == '-' && at(i + 1).is_some_and(char::is_numeric)) => {
                     // Handle optional leading minus
-                    if chars[i] == '-' {
+                    if c == '-' {
                         i += 1;
                     }
                     // Parse digits before decimal point
-                    while i < chars.len() && chars[i].is_numeric() {
+                    while at(i).is_some_and(char::is_numeric) {
                         i += 1;
                     }
                     // Handle decimal point and digits after
-                    if i < chars.len()
-                        && chars[i] == '.'
-                        && i + 1 < chars.len()
-                        && chars[i + 1].is_numeric()
-                    {
+                    if at(i) == Some('.') && at(i + 1).is_some_and(char::is_numeric) {
                         i += 1; // consume '.'
-                        while i < chars.len() && chars[i].is_numeric() {
+                        while at(i).is_some_and(char::is_numeric) {
                             i += 1;
                         }
                     }
                     // Handle scientific notation
-                    if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                    if matches!(at(i), Some('e' | 'E')) {
                         i += 1;
-                        if i < chars.len() && (chars[i] == '+' || chars[i] == '-') {
+                        if matches!(at(i), Some('+' | '-')) {
                             i += 1;
                         }
-                        while i < chars.len() && chars[i].is_numeric() {
+                        while at(i).is_some_and(char::is_numeric) {
                             i += 1;
                         }
                     }
-                    let num_str: String = chars[start..i].iter().collect();
-                    match D512::from_str(&num_str, Context::default()) {
-                        Ok(number) => tokens.push(LispToken::Number(number)),
-                        Err(_) => tokens.push(LispToken::BadNumber(num_str)),
+                    let num_str = &self.src[offset(start)..offset(i)];
+                    match D512::from_str(num_str, Context::default()) {
+                        Ok(number) => LispToken::Number(number),
+                        Err(_) => LispToken::BadNumber(num_str.to_string()),
                     }
                 }
                 _ => {
-                    let start = i;
-                    while i < chars.len()
-                        && !chars[i].is_whitespace()
-                        && chars[i] != '('
-                        && chars[i] != ')'
-                    {
+                    while at(i).is_some_and(|c| !is_delimiter(c)) {
                         i += 1;
                     }
-                    let sym_str: String = chars[start..i].iter().collect();
-                    tokens.push(LispToken::Symbol(sym_str));
+                    let text = &self.src[offset(start)..offset(i)];
+                    match text.strip_prefix(':') {
+                        Some(name) if !name.is_empty() => LispToken::Keyword(name.to_string()),
+                        _ => LispToken::Symbol(text.to_string()),
+                    }
                 }
-            }
+            };
+            tokens.push(Spanned {
+                token,
+                span: (offset(start)..offset(i)).into(),
+            });
         }
 
         tokens
@@ -100,28 +213,39 @@ impl Tokenizer {
 }
 
 pub struct Parser {
-    tokens: Vec<LispToken>,
+    src: String,
+    tokens: Vec<Spanned>,
     pos: usize,
 }
 
 impl Parser {
     pub fn new(src: &str) -> Self {
         Parser {
+            src: src.to_string(),
             tokens: Tokenizer::new(src).tokenize(),
             pos: 0,
         }
     }
 
     fn peek(&self) -> Option<&LispToken> {
-        self.tokens.get(self.pos)
+        self.tokens.get(self.pos).map(|t| &t.token)
     }
 
-    fn advance(&mut self) -> Option<&LispToken> {
+    fn advance(&mut self) -> Option<&Spanned> {
         let token = self.tokens.get(self.pos);
         self.pos += 1;
         token
     }
 
+    /// One-based line and column of a byte offset into the source. Only
+    /// errors need it, so it is worked out when one is built.
+    fn position(&self, span: SourceSpan) -> (usize, usize) {
+        let before = &self.src[..span.offset().min(self.src.len())];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        (line, before[line_start..].chars().count() + 1)
+    }
+
     pub fn parse(&mut self) -> Result<LispAST, LispError> {
         let mut exprs = Vec::new();
 
@@ -138,29 +262,204 @@ impl Parser {
         } else {
             let mut begin_list = vec![LispAST::Symbol("begin".to_string())];
             begin_list.extend(exprs);
-            Ok(LispAST::List(begin_list))
+            Ok(LispAST::List(begin_list.into()))
         }
     }
 
+    /// Read one datum. Callers make sure a token is left.
     fn parse_one(&mut self) -> Result<LispAST, LispError> {
-        match self.advance() {
-            Some(LispToken::Number(n)) => Ok(LispAST::Number(*n)),
-            Some(LispToken::BadNumber(text)) => Err(LispError::BadNumber { text: text.clone() }),
-            Some(LispToken::Symbol(s)) => match s.as_str() {
+        let Some(span) = self.tokens.get(self.pos).map(|t| t.span) else {
+            return Err(LispError::EmptyProgram);
+        };
+        let token = &self.tokens[self.pos].token;
+        self.pos += 1;
+        match token {
+            LispToken::Number(n) => Ok(LispAST::Number(*n)),
+            LispToken::BadNumber(text) => {
+                let (line, column) = self.position(span);
+                Err(LispError::BadNumber {
+                    text: text.clone(),
+                    line,
+                    column,
+                    span,
+                })
+            }
+            LispToken::Str(text) => Ok(LispAST::String(text.clone())),
+            LispToken::Keyword(name) => Ok(LispAST::Keyword(name.clone())),
+            LispToken::UnterminatedString => {
+                let (line, column) = self.position(span);
+                Err(LispError::UnterminatedString { line, column, span })
+            }
+            LispToken::UnterminatedComment => {
+                let (line, column) = self.position(span);
+                Err(LispError::UnterminatedComment { line, column, span })
+            }
+            LispToken::Symbol(s) => match s.as_str() {
                 "true" => Ok(LispAST::Bool(true)),
                 "false" => Ok(LispAST::Bool(false)),
                 _ => Ok(LispAST::Symbol(s.clone())),
             },
-            Some(LispToken::OpenParen) => {
-                let mut list = Vec::new();
-                while !matches!(self.peek(), Some(LispToken::CloseParen) | None) {
-                    list.push(self.parse_one()?);
+            LispToken::Prefix(form) => {
+                let form = LispAST::Symbol((*form).to_string());
+                if self.peek().is_none() {
+                    let (line, column) = self.position(span);
+                    return Err(LispError::DanglingPrefix {
+                        prefix: self.src[span.offset()..span.offset() + span.len()].to_string(),
+                        line,
+                        column,
+                        span,
+                    });
                 }
-                self.advance(); // consume CloseParen
-                Ok(LispAST::List(list))
+                Ok(LispAST::List(vec![form, self.parse_one()?].into()))
+            }
+            LispToken::OpenParen => Ok(LispAST::List(self.parse_until('(', span)?.into())),
+            LispToken::OpenBracket => {
+                let mut items = vec![LispAST::Symbol("vec".to_string())];
+                items.extend(self.parse_until('[', span)?);
+                Ok(LispAST::List(items.into()))
+            }
+            LispToken::CloseParen | LispToken::CloseBracket => {
+                let found = if matches!(token, LispToken::CloseParen) { ')' } else { ']' };
+                let (line, column) = self.position(span);
+                Err(LispError::UnexpectedCloseParen {
+                    found,
+                    line,
+                    column,
+                    span,
+                })
             }
-            Some(LispToken::CloseParen) => Err(LispError::UnexpectedCloseParen),
-            None => Err(LispError::UnexpectedEof),
         }
     }
+
+    /// Read data up to the delimiter closing `opened` (read at `span`),
+    /// consuming it.
+    fn parse_until(&mut self, opened: char, span: SourceSpan) -> Result<Vec<LispAST>, LispError> {
+        let close = if opened == '(' { ')' } else { ']' };
+        let mut items = Vec::new();
+        loop {
+            let found = match self.peek() {
+                None => {
+                    let (line, column) = self.position(span);
+                    return Err(LispError::UnexpectedEof {
+                        opened,
+                        line,
+                        column,
+                        span,
+                    });
+                }
+                Some(LispToken::CloseParen) => ')',
+                Some(LispToken::CloseBracket) => ']',
+                Some(_) => {
+                    items.push(self.parse_one()?);
+                    continue;
+                }
+            };
+            self.advance();
+            if found != close {
+                let (line, column) = self.position(span);
+                return Err(LispError::MismatchedDelimiter {
+                    opened,
+                    found,
+                    line,
+                    column,
+                    span,
+                });
+            }
+            return Ok(items);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(src: &str) -> Result<LispAST, LispError> {
+        Parser::new(src).parse()
+    }
+
+    fn sym(s: &str) -> LispAST {
+        LispAST::Symbol(s.to_string())
+    }
+
+    #[test]
+    fn comments_are_skipped() {
+        let src = "; leading\n(a #| block #| nested |# |# b) ; trailing";
+        assert_eq!(read(src), Ok(LispAST::List(vec![sym("a"), sym("b")].into())));
+    }
+
+    #[test]
+    fn vectors_keywords_and_quote() {
+        assert_eq!(
+            read("(f [x y] :style 'z)"),
+            Ok(LispAST::List(vec![
+                sym("f"),
+                LispAST::List(vec![sym("vec"), sym("x"), sym("y")].into()),
+                LispAST::Keyword("style".to_string()),
+                LispAST::List(vec![sym("quote"), sym("z")].into()),
+            ].into()))
+        );
+        assert_eq!(read("f'"), Ok(sym("f'")));
+    }
+
+    #[test]
+    fn written_source_reads_back() {
+        let src = r#"(defunc greet (name &optional (greeting "hello\t\"you\"")) "Greets."
+                      (concat greeting ", " name `(a ,b ,@c) 'd :e [1 2.5 -3]))"#;
+        let expr = read(src).unwrap();
+        assert_eq!(read(&expr.to_string()), Ok(expr.clone()));
+        let pretty = expr.pretty(30);
+        assert!(pretty.lines().count() > 1);
+        assert_eq!(read(&pretty), Ok(expr));
+    }
+
+    #[test]
+    fn errors_carry_positions() {
+        let Err(LispError::UnexpectedEof { line, column, .. }) = read("(a\n  (b c)") else {
+            panic!("expected an end-of-input error");
+        };
+        assert_eq!((line, column), (1, 1));
+        let Err(LispError::MismatchedDelimiter { found, line, column, .. }) = read("(a\n [b c))")
+        else {
+            panic!("expected a mismatched delimiter");
+        };
+        assert_eq!((found, line, column), (')', 2, 2));
+        assert!(matches!(
+            read("x\n  #| open"),
+            Err(LispError::UnterminatedComment { line: 2, column: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn block_comments_nest() {
+        assert_eq!(read("#| a #| b |# c |# x"), Ok(sym("x")));
+        assert_eq!(
+            read("(a #| ) ( |# b)"),
+            Ok(LispAST::List(vec![sym("a"), sym("b")].into()))
+        );
+        // `|#` inside an inner comment closes only that one.
+        assert_eq!(read("#| #| |# ) |# y"), Ok(sym("y")));
+        assert_eq!(read("#||# z"), Ok(sym("z")));
+    }
+
+    #[test]
+    fn unterminated_block_comments_are_reported() {
+        assert!(matches!(
+            read("(a)\n#| open #| nested |#"),
+            Err(LispError::UnterminatedComment { line: 2, column: 1, .. })
+        ));
+        assert!(matches!(
+            read("(a #| never closed)"),
+            Err(LispError::UnterminatedComment { line: 1, column: 4, .. })
+        ));
+        assert_eq!(read("#| only a comment |#"), Err(LispError::EmptyProgram));
+    }
+
+    #[test]
+    fn positions_count_characters_not_bytes() {
+        assert!(matches!(
+            read("(α β)\n  (γ ]"),
+            Err(LispError::MismatchedDelimiter { found: ']', line: 2, column: 3, .. })
+        ));
+    }
 }
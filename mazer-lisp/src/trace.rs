@@ -0,0 +1,347 @@
+//! Execution tracing for `--trace`: a [`Trace`] records the calls, returns,
+//! definitions and errors of a traced [`Interpreter`] with their depth and
+//! time, and renders them as a call tree, Chrome trace-event JSON or a
+//! per-function profile.
+//!
+//! Every function application is a call, natives included; special forms are
+//! not. Tracing watches the tree-walker, so a traced interpreter evaluates with
+//! it whichever [`Engine`] was selected.
+//!
+//! [`Interpreter`]: crate::interpreter::Interpreter
+//! [`Engine`]: crate::interpreter::Engine
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use mazer_types::{LispAST, LispError};
+use serde_json::{Value, json};
+
+/// Written values longer than this many characters are cut short in the call
+/// tree.
+const MAX_WIDTH: usize = 60;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    /// A function applied to its evaluated arguments, as written.
+    Call { name: String, args: Vec<String> },
+    /// The innermost open call returned.
+    Return { value: String },
+    /// The innermost open call failed.
+    Error { message: String },
+    /// `define` or `defunc` bound a name.
+    Define { name: String, value: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub kind: EventKind,
+    /// Calls open when the event happened; a return or error has its call's
+    /// depth.
+    pub depth: usize,
+    /// Time since tracing started.
+    pub at: Duration,
+}
+
+/// One function's line in [`Trace::profile`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub calls: usize,
+    /// Time inside the function, counting a recursive call's time once.
+    pub total: Duration,
+    /// Time inside the function but not in the calls it made.
+    pub own: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct Trace {
+    start: Instant,
+    depth: usize,
+    events: Vec<Event>,
+}
+
+impl Default for Trace {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Trace {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            depth: 0,
+            events: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    fn push(&mut self, kind: EventKind) {
+        self.events.push(Event {
+            kind,
+            depth: self.depth,
+            at: self.start.elapsed(),
+        });
+    }
+
+    pub(crate) fn call(&mut self, name: &str, args: &[LispAST]) {
+        self.push(EventKind::Call {
+            name: name.to_string(),
+            args: args.iter().map(LispAST::to_string).collect(),
+        });
+        self.depth += 1;
+    }
+
+    pub(crate) fn finish(&mut self, result: &Result<LispAST, LispError>) {
+        self.depth = self.depth.saturating_sub(1);
+        self.push(match result {
+            Ok(value) => EventKind::Return {
+                value: value.to_string(),
+            },
+            Err(e) => EventKind::Error {
+                message: e.to_string(),
+            },
+        });
+    }
+
+    pub(crate) fn define(&mut self, name: &str, value: &LispAST) {
+        self.push(EventKind::Define {
+            name: name.to_string(),
+            value: value.to_string(),
+        });
+    }
+
+    /// Each call as `(name args...)`, indented by depth, with what it returned
+    /// and how long it took below its nested calls.
+    #[must_use]
+    pub fn call_tree(&self) -> String {
+        let mut out = String::new();
+        let mut open: Vec<Duration> = Vec::new();
+        for event in &self.events {
+            let indent = "  ".repeat(event.depth);
+            let _ = match &event.kind {
+                EventKind::Call { name, args } => {
+                    open.push(event.at);
+                    let call = std::iter::once(name.as_str())
+                        .chain(args.iter().map(String::as_str))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    writeln!(out, "{indent}({})", shorten(&call))
+                }
+                EventKind::Return { value } => {
+                    let took = event.at - open.pop().unwrap_or(event.at);
+                    writeln!(out, "{indent}=> {}  [{took:.1?}]", shorten(value))
+                }
+                EventKind::Error { message } => {
+                    let took = event.at - open.pop().unwrap_or(event.at);
+                    writeln!(out, "{indent}!! {message}  [{took:.1?}]")
+                }
+                EventKind::Define { name, value } => {
+                    writeln!(out, "{indent}define {name} = {}", shorten(value))
+                }
+            };
+        }
+        out
+    }
+
+    /// The events in Chrome's trace-event format, for `chrome://tracing` or
+    /// Perfetto: calls as begin/end pairs, definitions as instants.
+    #[must_use]
+    pub fn chrome_json(&self) -> String {
+        let mut open: Vec<&str> = Vec::new();
+        let events: Vec<Value> = self
+            .events
+            .iter()
+            .map(|event| {
+                let entry = |name: &str, phase: &str, args: Value| {
+                    json!({
+                        "name": name,
+                        "ph": phase,
+                        "ts": event.at.as_secs_f64() * 1e6,
+                        "pid": 1,
+                        "tid": 1,
+                        "args": args,
+                    })
+                };
+                match &event.kind {
+                    EventKind::Call { name, args } => {
+                        open.push(name);
+                        entry(name, "B", json!({ "args": args }))
+                    }
+                    EventKind::Return { value } => entry(
+                        open.pop().unwrap_or_default(),
+                        "E",
+                        json!({ "value": value }),
+                    ),
+                    EventKind::Error { message } => entry(
+                        open.pop().unwrap_or_default(),
+                        "E",
+                        json!({ "error": message }),
+                    ),
+                    EventKind::Define { name, value } => {
+                        let mut instant =
+                            entry(&format!("define {name}"), "i", json!({ "value": value }));
+                        instant["s"] = json!("t");
+                        instant
+                    }
+                }
+            })
+            .collect();
+        json!({ "traceEvents": events }).to_string()
+    }
+
+    /// Calls and time per function, most total time first.
+    #[must_use]
+    pub fn profile(&self) -> Vec<Profile> {
+        // (name, start, time in nested calls) for each open call.
+        let mut open: Vec<(&str, Duration, Duration)> = Vec::new();
+        let mut by_name: HashMap<&str, Profile> = HashMap::new();
+        for event in &self.events {
+            match &event.kind {
+                EventKind::Call { name, .. } => {
+                    open.push((name, event.at, Duration::ZERO));
+                    by_name
+                        .entry(name)
+                        .or_insert_with(|| Profile {
+                            name: name.clone(),
+                            calls: 0,
+                            total: Duration::ZERO,
+                            own: Duration::ZERO,
+                        })
+                        .calls += 1;
+                }
+                EventKind::Return { .. } | EventKind::Error { .. } => {
+                    let Some((name, start, nested)) = open.pop() else {
+                        continue;
+                    };
+                    let took = event.at - start;
+                    let recursive = open.iter().any(|(outer, ..)| *outer == name);
+                    if let Some(entry) = by_name.get_mut(name) {
+                        if !recursive {
+                            entry.total += took;
+                        }
+                        entry.own += took.saturating_sub(nested);
+                    }
+                    if let Some((_, _, parent_nested)) = open.last_mut() {
+                        *parent_nested += took;
+                    }
+                }
+                EventKind::Define { .. } => {}
+            }
+        }
+        let mut profile: Vec<Profile> = by_name.into_values().collect();
+        profile.sort_by(|a, b| b.total.cmp(&a.total).then_with(|| a.name.cmp(&b.name)));
+        profile
+    }
+
+    /// [`Trace::profile`] as an aligned table.
+    #[must_use]
+    pub fn profile_table(&self) -> String {
+        let profile = self.profile();
+        let width = profile
+            .iter()
+            .map(|p| p.name.chars().count())
+            .chain(std::iter::once("function".len()))
+            .max()
+            .unwrap_or_default();
+        let mut out = format!(
+            "{:width$}  {:>7}  {:>12}  {:>12}\n",
+            "function", "calls", "total", "self"
+        );
+        for p in &profile {
+            let _ = writeln!(
+                out,
+                "{:width$}  {:>7}  {:>12}  {:>12}",
+                p.name,
+                p.calls,
+                format!("{:.1?}", p.total),
+                format!("{:.1?}", p.own),
+            );
+        }
+        out
+    }
+}
+
+fn shorten(text: &str) -> String {
+    if text.chars().count() <= MAX_WIDTH {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(MAX_WIDTH - 1).collect();
+    format!("{cut}…")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use mazer_types::Environment;
+
+    use crate::{environment::EnvironmentExt, interpreter::Interpreter, parser::Parser};
+
+    use super::*;
+
+    fn traced(src: &str) -> Trace {
+        let env = Environment::new().with_native().with_prelude();
+        let mut interp = Interpreter::new(BTreeMap::new(), env).with_trace();
+        let _ = Parser::new(src)
+            .parse()
+            .and_then(|expr| interp.evaluate(expr));
+        interp.trace().cloned().expect("tracing was on")
+    }
+
+    #[test]
+    fn calls_nest_and_return() {
+        let trace = traced("(defunc sq (x) (* x x)) (sq (+ 1 2))");
+        let tree = trace.call_tree();
+        let lines: Vec<&str> = tree
+            .lines()
+            .map(|l| l.split("  [").next().unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "define sq = ⟨UserFunc⟩",
+                "(+ 1 2)",
+                "=> 3",
+                "(sq 3)",
+                "  (* 3 3)",
+                "  => 9",
+                "=> 9",
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_close_their_calls() {
+        let trace = traced("(defunc f (x) (/ x 0)) (f 1)");
+        let kinds: Vec<_> = trace.events().iter().map(|e| (&e.kind, e.depth)).collect();
+        assert!(matches!(
+            kinds[..],
+            [
+                (EventKind::Define { .. }, 0),
+                (EventKind::Call { .. }, 0),
+                (EventKind::Call { .. }, 1),
+                (EventKind::Error { .. }, 1),
+                (EventKind::Error { .. }, 0),
+            ]
+        ));
+        let json: Value = serde_json::from_str(&trace.chrome_json()).unwrap();
+        assert_eq!(json["traceEvents"].as_array().map(Vec::len), Some(5));
+    }
+
+    #[test]
+    fn profile_counts_recursive_calls_once_in_total() {
+        let trace = traced("(defunc fact (n) (if (= n 0) 1 (* n (fact (- n 1))))) (fact 5)");
+        let profile = trace.profile();
+        let fact = profile.iter().find(|p| p.name == "fact").unwrap();
+        assert_eq!(fact.calls, 6);
+        assert_eq!(profile[0].name, "fact");
+        assert!(fact.own <= fact.total);
+    }
+}
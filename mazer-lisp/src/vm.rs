@@ -0,0 +1,565 @@
+//! The bytecode engine: a stack machine running [`Code`] from
+//! [`crate::bytecode`], selected with [`Engine::Bytecode`].
+//!
+//! Scoping is the tree-walker's: a call binds its parameters in the one global
+//! environment, saving any values they shadow and restoring those on return.
+//! Variables live in slots while code runs; the environment is brought up to
+//! date before anything else can look at it (a form handed to the tree-walker,
+//! an unbound-symbol suggestion, the end of the run), and slots are refilled
+//! from it lazily afterwards.
+//!
+//! Functions are compiled the first time they are called and cached by the
+//! identity of their body and parameter list, so a function called in a loop is compiled once, and user calls
+//! push a frame rather than recursing on the Rust stack.
+//!
+//! [`Engine::Bytecode`]: crate::interpreter::Engine::Bytecode
+
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use mazer_types::{LispAST, LispError};
+
+use crate::bytecode::{Code, Compiler, Names, Op};
+use crate::interpreter::{Interpreter, Signature};
+
+/// A compiled [`LispAST::UserFunc`].
+struct Function {
+    /// The parameters' slots, or `None` for a function with `&optional` or
+    /// `&rest` parameters, which is called through the tree-walker.
+    params: Option<Vec<usize>>,
+    code: Rc<Code>,
+    /// The function as a value, for when it leaves the VM.
+    source: LispAST,
+}
+
+// Nearly every value is an `Ast`; boxing those to shrink the enum would cost
+// an allocation each.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone)]
+enum Value {
+    Ast(LispAST),
+    /// A user function, compiled the first time it was loaded from a
+    /// variable so calls do not copy its body.
+    Func(Rc<Function>),
+}
+
+impl Value {
+    fn into_ast(self) -> LispAST {
+        match self {
+            Value::Ast(value) => value,
+            Value::Func(f) => f.source.clone(),
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Value::Ast(value) => value.type_name(),
+            Value::Func(f) => f.source.type_name(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Slot {
+    value: Option<Value>,
+    /// Whether `value` reflects the environment (or a newer assignment).
+    loaded: bool,
+    /// Whether `value` is newer than the environment.
+    dirty: bool,
+}
+
+struct Frame {
+    code: Rc<Code>,
+    ip: usize,
+    /// Values the parameters shadowed, put back when the frame is left.
+    saved: Vec<(usize, Value)>,
+    /// The calling code and its [`Op::Call`]'s `first_arg`.
+    call_site: Option<(Rc<Code>, usize)>,
+}
+
+/// Where a user function's body and parameter list live; copies of one
+/// function value share them.
+type FunctionKey = (*const LispAST, *const [String]);
+
+/// Cached functions kept before the cache starts over. A `lambda` evaluated in
+/// a loop makes a new function each time, and each entry keeps its source alive.
+const MAX_FUNCTIONS: usize = 1024;
+
+/// Per-interpreter state for the bytecode engine.
+#[derive(Default)]
+pub(crate) struct Vm {
+    names: Names,
+    slots: Vec<Slot>,
+    /// Compiled functions by the addresses of their body and parameter list.
+    /// Each entry holds its function's source, so an address cannot be reused
+    /// while it is a key.
+    functions: HashMap<FunctionKey, Rc<Function>>,
+    /// How many macros existed when `functions` were compiled; a new macro
+    /// can turn a compiled call into a macro call.
+    macro_count: usize,
+}
+
+/// Name an assertion that failed without naming itself after the call's first
+/// argument, as the tree-walker does.
+fn name_assertion(error: LispError, code: &Code, first_arg: Option<usize>) -> LispError {
+    match (error, first_arg) {
+        (
+            LispError::AssertionFailed {
+                expr,
+                expected,
+                actual,
+            },
+            Some(i),
+        ) if expr.is_empty() => LispError::AssertionFailed {
+            expr: code.consts[i].to_string(),
+            expected,
+            actual,
+        },
+        (error, _) => error,
+    }
+}
+
+impl Vm {
+    fn get(&mut self, interp: &Interpreter, slot: usize) -> Option<&mut Value> {
+        let entry = &mut self.slots[slot];
+        if !entry.loaded {
+            entry.value = interp
+                .env
+                .get(self.names.name(slot))
+                .cloned()
+                .map(Value::Ast);
+            entry.loaded = true;
+            entry.dirty = false;
+        }
+        entry.value.as_mut()
+    }
+
+    fn set(&mut self, slot: usize, value: Value) {
+        self.slots[slot] = Slot {
+            value: Some(value),
+            loaded: true,
+            dirty: true,
+        };
+    }
+
+    /// Write assignments back to the environment.
+    fn flush(&mut self, interp: &mut Interpreter) {
+        for (slot, entry) in self.slots.iter_mut().enumerate() {
+            if entry.dirty {
+                if let Some(value) = &entry.value {
+                    interp
+                        .env
+                        .set(self.names.name(slot).to_string(), value.clone().into_ast());
+                }
+                entry.dirty = false;
+            }
+        }
+    }
+
+    /// Drop every slot's value, to be reloaded from the environment on next
+    /// use. Flush first.
+    fn forget(&mut self) {
+        for entry in &mut self.slots {
+            *entry = Slot::default();
+        }
+    }
+
+    fn compile(&mut self, interp: &Interpreter, expr: &LispAST) -> Code {
+        let code = Compiler::compile(expr, &mut self.names, &interp.env.macros);
+        self.slots.resize_with(self.names.len(), Slot::default);
+        code
+    }
+
+    /// The compiled form of a user function value.
+    fn function(&mut self, interp: &Interpreter, source: LispAST) -> Rc<Function> {
+        let LispAST::UserFunc { params, body, .. } = &source else {
+            unreachable!("only user functions are compiled");
+        };
+        if interp.env.macros.len() != self.macro_count {
+            self.functions.clear();
+            self.macro_count = interp.env.macros.len();
+        }
+        let key = (Arc::as_ptr(body), Arc::as_ptr(params));
+        if let Some(f) = self.functions.get(&key)
+            && f.source == source
+        {
+            return Rc::clone(f);
+        }
+        let (params, code) = if Signature::of(params).is_plain() {
+            let code = self.compile(interp, body);
+            let params = params.iter().map(|p| self.names.slot(p)).collect();
+            self.slots.resize_with(self.names.len(), Slot::default);
+            (Some(params), Rc::new(code))
+        } else {
+            (None, Rc::new(Code::default()))
+        };
+        let f = Rc::new(Function {
+            params,
+            code,
+            source,
+        });
+        if self.functions.len() >= MAX_FUNCTIONS {
+            self.functions.clear();
+        }
+        self.functions.insert(key, Rc::clone(&f));
+        f
+    }
+
+    /// Compile and run `expr`, leaving the environment up to date.
+    pub(crate) fn execute(
+        &mut self,
+        interp: &mut Interpreter,
+        expr: &LispAST,
+    ) -> Result<LispAST, LispError> {
+        let code = Rc::new(self.compile(interp, expr));
+        let mut frames = vec![Frame {
+            code,
+            ip: 0,
+            saved: Vec::new(),
+            call_site: None,
+        }];
+        let mut stack = Vec::new();
+        let result = self.run(interp, &mut frames, &mut stack);
+        let result = result.map_err(|e| self.unwind(&mut frames, e));
+        self.flush(interp);
+        self.forget();
+        result
+    }
+
+    fn run(
+        &mut self,
+        interp: &mut Interpreter,
+        frames: &mut Vec<Frame>,
+        stack: &mut Vec<Value>,
+    ) -> Result<LispAST, LispError> {
+        loop {
+            let frame = frames.last_mut().expect("a frame is running");
+            let op = frame.code.ops[frame.ip];
+            frame.ip += 1;
+            match op {
+                Op::Const(i) => stack.push(Value::Ast(frame.code.consts[i].clone())),
+                Op::Load(slot) => {
+                    let value = self.load(interp, slot)?;
+                    stack.push(value);
+                }
+                Op::Store(slot) => {
+                    let value = stack.last().expect("a value to store").clone();
+                    self.set(slot, value);
+                }
+                Op::Pop => {
+                    stack.pop();
+                }
+                Op::Jump(target) => frame.ip = target,
+                Op::JumpUnless(target) => match stack.pop().expect("a condition") {
+                    Value::Ast(LispAST::Bool(true)) => {}
+                    Value::Ast(LispAST::Bool(false)) => frame.ip = target,
+                    other => {
+                        return Err(LispError::TypeMismatch {
+                            form: "if condition".to_string(),
+                            expected: "Bool".to_string(),
+                            got: other.type_name().to_string(),
+                        });
+                    }
+                },
+                Op::Test { short_on, exit } => match stack.pop().expect("an operand") {
+                    Value::Ast(LispAST::Bool(b)) if b == short_on => {
+                        stack.push(Value::Ast(LispAST::Bool(b)));
+                        frame.ip = exit;
+                    }
+                    Value::Ast(LispAST::Bool(_)) => {}
+                    other => {
+                        return Err(LispError::TypeMismatch {
+                            form: if short_on { "or" } else { "and" }.to_string(),
+                            expected: "Bool".to_string(),
+                            got: other.type_name().to_string(),
+                        });
+                    }
+                },
+                Op::Call { argc, first_arg } => {
+                    let args = stack.split_off(stack.len() - argc);
+                    let func = stack.pop().expect("a function");
+                    let caller = Rc::clone(&frame.code);
+                    let func = match func {
+                        Value::Ast(source @ LispAST::UserFunc { .. }) => {
+                            Value::Func(self.function(interp, source))
+                        }
+                        other => other,
+                    };
+                    match func {
+                        Value::Ast(LispAST::NativeFunc(f)) => {
+                            let args: Vec<LispAST> =
+                                args.into_iter().map(Value::into_ast).collect();
+                            let value =
+                                f(&args).map_err(|e| name_assertion(e, &caller, first_arg))?;
+                            stack.push(Value::Ast(value));
+                        }
+                        Value::Func(f) if f.params.is_none() => {
+                            let args = args.into_iter().map(Value::into_ast).collect();
+                            self.flush(interp);
+                            let value = interp.apply(f.source.clone(), args);
+                            self.forget();
+                            let value = value.map_err(|e| name_assertion(e, &caller, first_arg))?;
+                            stack.push(Value::Ast(value));
+                        }
+                        Value::Func(f) => {
+                            let frame = self.enter(interp, &f, args, caller, first_arg)?;
+                            frames.push(frame);
+                        }
+                        other => {
+                            return Err(LispError::NotAFunction {
+                                value_type: other.type_name().to_string(),
+                            });
+                        }
+                    }
+                }
+                Op::Raise(i) => return Err(frame.code.errors[i].clone()),
+                Op::Interp(i) => {
+                    let form = frame.code.consts[i].clone();
+                    self.flush(interp);
+                    let result = interp.eval(form);
+                    self.forget();
+                    stack.push(Value::Ast(result?));
+                }
+                Op::Return => {
+                    let frame = frames.pop().expect("a frame to return from");
+                    self.restore(frame.saved);
+                    if frames.is_empty() {
+                        return Ok(stack.pop().expect("a result").into_ast());
+                    }
+                }
+            }
+        }
+    }
+
+    fn load(&mut self, interp: &mut Interpreter, slot: usize) -> Result<Value, LispError> {
+        let value = match self.get(interp, slot) {
+            Some(Value::Ast(LispAST::UserFunc { .. })) => {
+                let Some(Value::Ast(source)) = self.slots[slot].value.take() else {
+                    unreachable!("just matched a user function");
+                };
+                let f = Value::Func(self.function(interp, source));
+                self.slots[slot].value = Some(f.clone());
+                f
+            }
+            Some(value) => value.clone(),
+            None => {
+                // Suggestions come from the environment, so bring it up to date.
+                self.flush(interp);
+                return Err(interp.unbound(self.names.name(slot)));
+            }
+        };
+        Ok(value)
+    }
+
+    /// Bind `f`'s parameters to `args` and return the frame that runs its body.
+    fn enter(
+        &mut self,
+        interp: &Interpreter,
+        f: &Function,
+        args: Vec<Value>,
+        caller: Rc<Code>,
+        first_arg: Option<usize>,
+    ) -> Result<Frame, LispError> {
+        let params = f.params.as_deref().unwrap_or_default();
+        if params.len() != args.len() {
+            return Err(LispError::Arity {
+                form: "function".to_string(),
+                expected: params.len().to_string(),
+                got: args.len(),
+            });
+        }
+        let mut saved: Vec<(usize, Value)> = Vec::new();
+        for (&slot, arg) in params.iter().zip(args) {
+            if let Some(existing) = self.get(interp, slot).cloned() {
+                match saved.iter_mut().find(|(s, _)| *s == slot) {
+                    Some(entry) => entry.1 = existing,
+                    None => saved.push((slot, existing)),
+                }
+            }
+            self.set(slot, arg);
+        }
+        Ok(Frame {
+            code: Rc::clone(&f.code),
+            ip: 0,
+            saved,
+            call_site: first_arg.map(|i| (caller, i)),
+        })
+    }
+
+    fn restore(&mut self, saved: Vec<(usize, Value)>) {
+        for (slot, value) in saved {
+            self.set(slot, value);
+        }
+    }
+
+    /// Leave every frame after `error`, restoring shadowed bindings and
+    /// naming a failed assertion after the innermost call it escapes.
+    fn unwind(&mut self, frames: &mut Vec<Frame>, mut error: LispError) -> LispError {
+        while let Some(frame) = frames.pop() {
+            self.restore(frame.saved);
+            if let Some((caller, first_arg)) = frame.call_site {
+                error = name_assertion(error, &caller, Some(first_arg));
+            }
+        }
+        error
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use mazer_types::Environment;
+
+    use super::*;
+    use crate::{environment::EnvironmentExt, interpreter::Engine, parser::Parser};
+
+    /// Run `src` on a fresh interpreter, returning the result and the
+    /// bindings left behind.
+    fn run(src: &str, engine: Engine) -> (Result<LispAST, LispError>, Environment) {
+        let env = Environment::new().with_native().with_prelude();
+        let mut interp = Interpreter::new(BTreeMap::new(), env).with_engine(engine);
+        let result = Parser::new(src)
+            .parse()
+            .and_then(|expr| interp.expand(expr))
+            .and_then(|expr| interp.evaluate(expr));
+        (result, interp.env().clone())
+    }
+
+    #[test]
+    fn engines_agree() {
+        let programs = [
+            "(+ 1 (* 2 3))",
+            "(defunc fact (n) (if (= n 0) 1 (* n (fact (- n 1))))) (fact 20)",
+            // Parameters are dynamically scoped and stay bound after a call
+            // when nothing was shadowed.
+            "(define y 10) (defunc f (x) (+ x y)) (defunc g (y) (f 1)) (list (g 5) y (f 0) x)",
+            "(defunc twice (h v) (h (h v))) (defunc inc (v) (+ v 1)) (twice inc 1)",
+            "(and true (or false (= 1 1)))",
+            "(and true 3)",
+            "(if 1 2 3)",
+            "(if true 2)",
+            "(begin)",
+            "(define x)",
+            "(defunc f (1) x)",
+            "(lenght (list 1 2))",
+            "(undefined-thing 1)",
+            "((quote x) 1)",
+            "(defunc f (a b) a) (f 1)",
+            "(defunc check (v) (assert (= v 1))) (check 2)",
+            "(assert (= 1 2))",
+            "(try (/ 1 0) (catch e (define caught e) 0))",
+            "(defunc speed (d) (qty d m/s)) (convert (speed 3) km/h)",
+            "(defmacro unless (c a b) `(if ,c ,b ,a)) (unless false 1 2)",
+            "(forall (in k (set 1 2 3)) (> k 0))",
+            "(defunc f (a &optional (b (* a 2)) c) \"doc\" (define seen a) (vec a b c)) \
+             (vec (f 1) (f 1 5) (f 1 5 true) seen)",
+            "(defunc f (a &rest more) (length more)) (vec (f 1) (f 1 2 3))",
+            "(defunc f (a &optional b) a) (f)",
+            "(defunc f (a &rest) a)",
+            "(defunc f (a &rest more) more) (f 1)",
+            "(defunc f (a &optional b) a) (f 1 2 3)",
+            "(defunc f (x) \"Just a doc.\") (f 1)",
+        ];
+        for src in programs {
+            let (tree, tree_env) = run(src, Engine::Tree);
+            let (vm, vm_env) = run(src, Engine::Bytecode);
+            assert_eq!(tree, vm, "results differ for {src}");
+            assert_eq!(
+                tree_env.bindings, vm_env.bindings,
+                "bindings differ for {src}"
+            );
+        }
+    }
+
+    #[test]
+    fn optional_and_rest_parameters() {
+        let def = "(defunc f (a &optional (b (+ a 1)) &rest more) \"Sum.\" (+ a b (length more)))";
+        let (result, env) = run(&format!("{def} (f 1)"), Engine::Tree);
+        assert_eq!(result, Ok(LispAST::Number(3.into())));
+        let (result, _) = run(&format!("{def} (f 1 5 6 7)"), Engine::Tree);
+        assert_eq!(result, Ok(LispAST::Number(8.into())));
+        let f = env.bindings.get("f").unwrap();
+        assert!(matches!(f, LispAST::UserFunc { doc: Some(doc), .. } if &**doc == "Sum."));
+        assert_eq!(
+            crate::interpreter::parameter_list(f).as_deref(),
+            Some("(a &optional (b (+ a 1)) &rest more)")
+        );
+    }
+
+    #[test]
+    fn optional_and_rest_signatures_are_left_to_the_tree_walker() {
+        let env = Environment::new().with_native().with_prelude();
+        let mut interp = Interpreter::new(BTreeMap::new(), env);
+        let mut vm = Vm::default();
+        for (def, plain) in [
+            ("(defunc f (a b) a)", true),
+            ("(defunc f (a &optional b) a)", false),
+            ("(defunc f (&rest xs) xs)", false),
+        ] {
+            let f = interp.evaluate(Parser::new(def).parse().unwrap()).unwrap();
+            let compiled = vm.function(&interp, f);
+            assert_eq!(compiled.params.is_some(), plain, "{def}");
+            assert_eq!(compiled.code.ops.is_empty(), !plain, "{def}");
+        }
+    }
+
+    #[test]
+    fn functions_are_cached_by_identity() {
+        let env = Environment::new().with_native().with_prelude();
+        let mut interp = Interpreter::new(BTreeMap::new(), env);
+        let mut vm = Vm::default();
+        let mut define = || interp.evaluate(Parser::new("(defunc f (x) x)").parse().unwrap());
+        let (f, g) = (define().unwrap(), define().unwrap());
+        let first = vm.function(&interp, f.clone());
+        assert!(Rc::ptr_eq(&first, &vm.function(&interp, f)));
+        // Same text, but a separate definition.
+        assert!(!Rc::ptr_eq(&first, &vm.function(&interp, g)));
+
+        for _ in 0..2 * MAX_FUNCTIONS {
+            let f = interp.evaluate(Parser::new("(defunc f (x) x)").parse().unwrap()).unwrap();
+            vm.function(&interp, f);
+        }
+        assert!(vm.functions.len() <= MAX_FUNCTIONS);
+    }
+
+    #[test]
+    fn print_output_is_kept_per_fragment() {
+        for engine in [Engine::Tree, Engine::Bytecode] {
+            let fragments = [
+                ("0 a", "(defunc show (x) (print \"x = \" x)) (show 2) (show (vec 1 2))"),
+                ("1 b", "(defunc twice (f x) (f x) (f x)) (twice print 'hi) (reflect print)"),
+                ("2 c", "(begin (print \"before\") (/ 1 0))"),
+            ]
+            .map(|(key, src)| (key.to_string(), Parser::new(src).parse().unwrap()));
+            let env = Environment::new().with_native().with_prelude();
+            let mut interp =
+                Interpreter::new(BTreeMap::from(fragments), env).with_engine(engine);
+            assert!(interp.run().is_err());
+            let outputs: Vec<_> = interp
+                .outputs()
+                .iter()
+                .map(|(key, out)| (key.as_str(), out.as_str()))
+                .collect();
+            assert_eq!(
+                outputs,
+                [
+                    ("0 a", "x = 2\nx = (vec 1 2)\n"),
+                    ("1 b", "hi\nhi\n"),
+                    ("2 c", "before\n"),
+                ],
+                "{engine:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn deep_recursion_does_not_use_the_rust_stack() {
+        let src = "(defunc count (n) (if (= n 0) 0 (+ 1 (count (- n 1))))) (count 20000)";
+        assert_eq!(
+            run(src, Engine::Bytecode).0,
+            Ok(LispAST::Number(20000.into()))
+        );
+    }
+}
@@ -8,7 +8,9 @@ use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 use syn::{
     parse_macro_input, DeriveInput, Data, Fields, Attribute, Expr, ExprArray,
-    ExprLit, Lit, Meta, Token, Ident,
+    ExprLit, ExprPath, Lit, Token, Ident,
+    ext::IdentExt,
+    parse::{Parse, ParseStream},
     punctuated::Punctuated,
 };
 
@@ -18,6 +20,23 @@ struct FuncAttr {
     arity: AritySpec,
     doc: String,
     symbol: Option<String>,
+    /// The native implementing the function, from `impl = Native::add`.
+    native: Option<syn::Path>,
+}
+
+/// One `key = value` entry of a `#[func(...)]` attribute
+struct NameValue {
+    key: Ident,
+    value: Expr,
+}
+
+impl Parse for NameValue {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key = input.call(Ident::parse_any)?;
+        input.parse::<Token![=]>()?;
+        let value = input.parse()?;
+        Ok(Self { key, value })
+    }
 }
 
 /// Arity specification parsed from attribute
@@ -36,6 +55,7 @@ impl Default for FuncAttr {
             arity: AritySpec::Variadic,
             doc: String::new(),
             symbol: None,
+            native: None,
         }
     }
 }
@@ -55,7 +75,7 @@ impl Default for FuncAttr {
 ///     #[func(names = ["arcsin", "asin"], arity = Fixed(1), doc = "Inverse sine")]
 ///     Arcsin,
 ///
-///     #[func(names = ["+", "add"], arity = Variadic, doc = "Addition")]
+///     #[func(names = ["+", "add"], arity = AtLeast(1), doc = "Addition", impl = Native::add)]
 ///     Add,
 ///
 ///     MaybeFunc(String), // No attribute needed for fallback variant
@@ -70,6 +90,13 @@ impl Default for FuncAttr {
 /// - `symbol(&self) -> Option<&'static str>` - optional MathML symbol override
 /// - `from_name(s: &str) -> Self` - parse from string
 /// - `all_functions() -> Vec<FuncInfo>` - all functions with metadata
+///
+/// Variants naming an `impl` are also collected into an exported
+/// `show_func_natives!()` macro (named after the enum) that expands to an
+/// array of `(variant, fn)` pairs, where each `fn` checks the argument count
+/// against the variant's arity before calling the native. The `impl` paths
+/// are resolved where the macro is expanded, so the enum and the natives must
+/// be in scope there.
 #[proc_macro_derive(FuncMeta, attributes(func))]
 pub fn derive_func_meta(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
@@ -131,9 +158,11 @@ pub fn derive_func_meta(input: TokenStream) -> TokenStream {
     let from_name_impl = generate_from_name_impl(name, &variant_metas);
     let all_functions_impl = generate_all_functions_impl(name, &variant_metas);
     let func_info_struct = generate_func_info_struct();
+    let natives_macro = generate_natives_macro(name, &variant_metas);
 
     let expanded = quote! {
         #func_info_struct
+        #natives_macro
 
         impl #name {
             #names_impl
@@ -157,16 +186,18 @@ fn parse_func_attr(attrs: &[Attribute]) -> Option<FuncAttr> {
 
         let mut func_attr = FuncAttr::default();
 
+        // `impl` is a keyword, so the keys are read as any identifier rather
+        // than as `Meta` paths.
         let Ok(nested) = attr.parse_args_with(
-            Punctuated::<Meta, Token![,]>::parse_terminated
+            Punctuated::<NameValue, Token![,]>::parse_terminated
         ) else {
             continue;
         };
 
-        for meta in nested {
-            match &meta {
-                Meta::NameValue(nv) if nv.path.is_ident("names") => {
-                    if let Expr::Array(ExprArray { elems, .. }) = &nv.value {
+        for NameValue { key, value } in nested {
+            match key.to_string().as_str() {
+                "names" => {
+                    if let Expr::Array(ExprArray { elems, .. }) = &value {
                         for elem in elems {
                             if let Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) = elem {
                                 func_attr.names.push(s.value());
@@ -174,19 +205,24 @@ fn parse_func_attr(attrs: &[Attribute]) -> Option<FuncAttr> {
                         }
                     }
                 }
-                Meta::NameValue(nv) if nv.path.is_ident("arity") => {
-                    func_attr.arity = parse_arity_expr(&nv.value);
+                "arity" => {
+                    func_attr.arity = parse_arity_expr(&value);
                 }
-                Meta::NameValue(nv) if nv.path.is_ident("doc") => {
-                    if let Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) = &nv.value {
+                "doc" => {
+                    if let Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) = &value {
                         func_attr.doc = s.value();
                     }
                 }
-                Meta::NameValue(nv) if nv.path.is_ident("symbol") => {
-                    if let Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) = &nv.value {
+                "symbol" => {
+                    if let Expr::Lit(ExprLit { lit: Lit::Str(s), .. }) = &value {
                         func_attr.symbol = Some(s.value());
                     }
                 }
+                "impl" => {
+                    if let Expr::Path(ExprPath { path, .. }) = value {
+                        func_attr.native = Some(path);
+                    }
+                }
                 _ => {}
             }
         }
@@ -488,6 +524,47 @@ fn generate_all_functions_impl(_enum_name: &Ident, variants: &[(Ident, Option<Fu
     }
 }
 
+fn generate_natives_macro(enum_name: &Ident, variants: &[(Ident, Option<FuncAttr>, bool)]) -> TokenStream2 {
+    let entries: Vec<_> = variants.iter().filter_map(|(ident, attr, _)| {
+        let native = attr.as_ref()?.native.as_ref()?;
+        Some(quote! {
+            (#enum_name::#ident, {
+                fn checked(args: &[$crate::LispAST]) -> ::std::result::Result<$crate::LispAST, $crate::LispError> {
+                    let func = #enum_name::#ident;
+                    func.arity().check(func.canonical_name(), args)?;
+                    #native(args)
+                }
+                checked as fn(&[$crate::LispAST]) -> ::std::result::Result<$crate::LispAST, $crate::LispError>
+            })
+        })
+    }).collect();
+
+    if entries.is_empty() {
+        return quote! {};
+    }
+
+    let mut snake = String::new();
+    for (i, c) in enum_name.to_string().chars().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_lowercase());
+    }
+    let macro_name = Ident::new(&format!("{snake}_natives"), enum_name.span());
+
+    quote! {
+        /// Every function with a native implementation, as `(variant, fn)`
+        /// pairs. Each `fn` checks the argument count against the variant's
+        /// arity before calling the native.
+        #[macro_export]
+        macro_rules! #macro_name {
+            () => {
+                [#(#entries),*]
+            };
+        }
+    }
+}
+
 fn generate_func_info_struct() -> TokenStream2 {
     quote! {
         /// Metadata about a function, used for LSP and documentation.
@@ -1,4 +1,4 @@
-use miette::Diagnostic;
+use miette::{Diagnostic, SourceSpan};
 use thiserror::Error;
 use unicode_segmentation::UnicodeSegmentation;
 
@@ -76,12 +76,28 @@ pub enum MdAst {
     ShowBlock {
         code: String,
     },
+    /// `(calc ...)` or `(show-eval ...)`: shown like a show block, followed by
+    /// its evaluated value.
+    CalcBlock {
+        code: String,
+    },
     Text {
         content: String,
     },
     Paragraph {
         children: Vec<MdAst>,
     },
+    /// `{{expr}}` in prose: replaced by the value of `expr` once the eval
+    /// blocks have run. `span` covers the braces in the source.
+    Interpolation {
+        code: String,
+        span: SourceSpan,
+    },
+    /// `key: value` lines between `---` fences at the very start of a
+    /// document, in source order. Only known keys (`numbers`) qualify.
+    FrontMatter {
+        entries: Vec<(String, String)>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -104,25 +120,48 @@ enum Token {
     Underscore,
     Tilde,
     Newline,
+    /// `{{expr}}` on a single line, with its byte span in the source.
+    Interpolation(String, SourceSpan),
     Text(String),
     Whitespace(String),
 }
 
 struct Tokenizer<'a> {
     _input: &'a str,
+    /// Byte offset of `input` within the whole document, for spans.
+    offset: usize,
     pos: usize,
     graphemes: Vec<&'a str>,
 }
 
 impl<'a> Tokenizer<'a> {
-    fn new(input: &'a str) -> Self {
+    fn new(input: &'a str, offset: usize) -> Self {
         Self {
             _input: input,
+            offset,
             pos: 0,
             graphemes: input.graphemes(true).collect(),
         }
     }
 
+    /// Byte offset of the grapheme at `pos` within the whole document.
+    fn byte_offset(&self, pos: usize) -> usize {
+        self.offset + self.graphemes[..pos].iter().map(|g| g.len()).sum::<usize>()
+    }
+
+    /// At `{{`: the interpolation up to the matching `}}` on the same line, if
+    /// there is one.
+    fn interpolation(&mut self) -> Option<Token> {
+        let close = (self.pos + 2..self.graphemes.len())
+            .take_while(|&i| !matches!(self.graphemes[i], "\n" | "\r"))
+            .find(|&i| self.graphemes[i] == "}" && self.graphemes.get(i + 1) == Some(&"}"))?;
+        let code = self.graphemes[self.pos + 2..close].concat();
+        let start = self.byte_offset(self.pos);
+        let span = SourceSpan::new(start.into(), self.byte_offset(close + 2) - start);
+        self.pos = close + 2;
+        Some(Token::Interpolation(code, span))
+    }
+
     fn peek(&self, offset: usize) -> Option<&str> {
         self.graphemes.get(self.pos + offset).copied()
     }
@@ -323,6 +362,16 @@ impl<'a> Tokenizer<'a> {
                     self.advance();
                     tokens.push(Token::Tilde);
                 }
+                "{" => {
+                    if self.peek(1) == Some("{")
+                        && let Some(token) = self.interpolation()
+                    {
+                        tokens.push(token);
+                    } else {
+                        self.advance();
+                        tokens.push(Token::Text("{".to_string()));
+                    }
+                }
                 " " | "\t" => {
                     if let Some(ws) = self.skip_whitespace_inline() {
                         tokens.push(Token::Whitespace(ws));
@@ -345,6 +394,7 @@ impl<'a> Tokenizer<'a> {
                                 | "*"
                                 | "_"
                                 | "~"
+                                | "{"
                         )
                     });
                     if !text.is_empty() {
@@ -365,6 +415,13 @@ impl<'a> Tokenizer<'a> {
     }
 }
 
+/// The Lisp block kinds recognised inline in prose.
+enum BlockKind {
+    Eval,
+    Show,
+    Calc,
+}
+
 struct TokenParser {
     tokens: Vec<Token>,
     pos: usize,
@@ -448,6 +505,10 @@ impl TokenParser {
                     text.push('~');
                     self.advance();
                 }
+                Token::Interpolation(code, _) => {
+                    text.push_str(&format!("{{{{{code}}}}}"));
+                    self.advance();
+                }
                 _ => break,
             }
         }
@@ -504,6 +565,10 @@ impl TokenParser {
                                 code.push('\n');
                                 self.advance();
                             }
+                            Token::Interpolation(c, _) => {
+                                code.push_str(&format!("{{{{{c}}}}}"));
+                                self.advance();
+                            }
                             _ => {
                                 self.advance();
                             }
@@ -709,9 +774,23 @@ impl TokenParser {
 
                     if let Some(Token::Text(first_word)) = self.peek(0) {
                         let word_trimmed = first_word.trim();
-                        if word_trimmed == "eval" || word_trimmed == "show" {
-                            let is_eval = word_trimmed == "eval";
+                        // `show-eval` tokenizes as `show`, `-`, `eval`.
+                        let show_eval = word_trimmed == "show"
+                            && matches!(self.peek(1), Some(Token::Text(t)) if t == "-")
+                            && matches!(self.peek(2), Some(Token::Text(t)) if t.trim() == "eval");
+                        let kind = match word_trimmed {
+                            "eval" => Some(BlockKind::Eval),
+                            "show" if show_eval => Some(BlockKind::Calc),
+                            "show" => Some(BlockKind::Show),
+                            "calc" => Some(BlockKind::Calc),
+                            _ => None,
+                        };
+                        if let Some(kind) = kind {
                             self.advance();
+                            if show_eval {
+                                self.advance();
+                                self.advance();
+                            }
 
                             if matches!(self.peek(0), Some(Token::Whitespace(_))) {
                                 self.advance();
@@ -788,6 +867,10 @@ impl TokenParser {
                                         scheme_code.push_str("---");
                                         self.advance();
                                     }
+                                    Token::Interpolation(c, _) => {
+                                        scheme_code.push_str(&format!("{{{{{c}}}}}"));
+                                        self.advance();
+                                    }
                                     _ => {
                                         // Skip other special tokens (headers, bullet points, etc.)
                                         self.advance();
@@ -796,13 +879,13 @@ impl TokenParser {
                             }
 
                             flush_text(&mut text_buffer, &mut elements);
-                            if is_eval {
-                                elements.push(MdAst::EvalBlock { code: scheme_code });
-                            } else {
-                                elements.push(MdAst::ShowBlock { code: scheme_code });
-                            }
+                            elements.push(match kind {
+                                BlockKind::Eval => MdAst::EvalBlock { code: scheme_code },
+                                BlockKind::Show => MdAst::ShowBlock { code: scheme_code },
+                                BlockKind::Calc => MdAst::CalcBlock { code: scheme_code },
+                            });
 
-                            // Preserve newline after show/eval block if present
+                            // Preserve newline after the block if present
                             if matches!(self.peek(0), Some(Token::Newline)) {
                                 self.advance();
                                 elements.push(MdAst::Text {
@@ -829,6 +912,14 @@ impl TokenParser {
                     text_buffer.push_str(ws);
                     self.advance();
                 }
+                Token::Interpolation(code, span) => {
+                    flush_text(&mut text_buffer, &mut elements);
+                    elements.push(MdAst::Interpolation {
+                        code: code.clone(),
+                        span: *span,
+                    });
+                    self.advance();
+                }
                 _ => {
                     self.advance();
                 }
@@ -872,6 +963,7 @@ impl TokenParser {
                             .map(|node| match node {
                                 MdAst::Text { content } => content,
                                 MdAst::InlineCode { code } => format!("`{}`", code),
+                                MdAst::Interpolation { code, .. } => format!("{{{{{code}}}}}"),
                                 _ => String::new(),
                             })
                             .collect::<String>();
@@ -979,6 +1071,10 @@ impl TokenParser {
                                 code.push('~');
                                 self.advance();
                             }
+                            Token::Interpolation(c, _) => {
+                                code.push_str(&format!("{{{{{c}}}}}"));
+                                self.advance();
+                            }
                             _ => {
                                 self.advance();
                             }
@@ -1022,9 +1118,16 @@ impl<'a> Parser<'a> {
             return Err(ParseError::EmptyInput);
         }
 
-        let mut tokenizer = Tokenizer::new(self.input);
+        let (front_matter, body) = match split_front_matter(self.input) {
+            Some((entries, body)) => (Some(MdAst::FrontMatter { entries }), body),
+            None => (None, self.input),
+        };
+        let mut tokenizer = Tokenizer::new(body, self.input.len() - body.len());
         let tokens = tokenizer.tokenize();
-        Ok(Parser::parse_tokens(tokens))
+        Ok(front_matter
+            .into_iter()
+            .chain(Parser::parse_tokens(tokens))
+            .collect())
     }
 
     fn parse_tokens(tokens: Vec<Token>) -> Vec<MdAst> {
@@ -1033,6 +1136,39 @@ impl<'a> Parser<'a> {
     }
 }
 
+/// Keys a front matter block may set. A `key: value` line with any other key
+/// is prose between page separators, such as `Warning: read this first`.
+const FRONT_MATTER_KEYS: &[&str] = &["numbers"];
+
+/// A document that opens with `---`, then only `key: value` (or blank) lines
+/// with keys from [`FRONT_MATTER_KEYS`], then a closing `---` has front matter.
+/// Anything else, including a lone `---`, is left for the tokenizer as a page
+/// separator.
+fn split_front_matter(input: &str) -> Option<(Vec<(String, String)>, &str)> {
+    let rest = input
+        .strip_prefix("---\n")
+        .or_else(|| input.strip_prefix("---\r\n"))?;
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let line = line.trim_end();
+        if line == "---" {
+            return Some((entries, &rest[offset..]));
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (key, value) = line.split_once(':')?;
+        let key = key.trim();
+        if !FRONT_MATTER_KEYS.contains(&key) {
+            return None;
+        }
+        entries.push((key.to_string(), value.trim().to_string()));
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1148,6 +1284,58 @@ mod tests {
         assert!(matches!(ast[0], MdAst::PageSeparator));
     }
 
+    #[test]
+    fn test_front_matter() {
+        let input = "---\nnumbers: (sig 4) (sci)\n\n---\n# Header";
+        let ast = Parser::new(input).parse().unwrap();
+        assert_eq!(
+            ast[0],
+            MdAst::FrontMatter {
+                entries: vec![("numbers".to_string(), "(sig 4) (sci)".to_string())]
+            }
+        );
+        assert!(matches!(ast[1], MdAst::Header { level: 1, .. }));
+
+        // Separators around ordinary text are not front matter, even when the
+        // text reads like a key.
+        let ast = Parser::new("---\nsome text\n---").parse().unwrap();
+        assert!(matches!(ast[0], MdAst::PageSeparator));
+        let input = "---\nWarning: read the appendix first\n---\n# Title";
+        let ast = Parser::new(input).parse().unwrap();
+        assert!(matches!(ast[0], MdAst::PageSeparator));
+        assert!(matches!(ast.last(), Some(MdAst::Header { level: 1, .. })));
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let input = "---\nnumbers: (sig 3)\n---\nspeed is {{v}} m/s, {not} {{ (* 2 v) }}";
+        let ast = Parser::new(input).parse().unwrap();
+        let MdAst::Paragraph { children } = &ast[1] else {
+            panic!("expected a paragraph, got {:?}", ast[1]);
+        };
+        let interpolations: Vec<_> = children
+            .iter()
+            .filter_map(|c| match c {
+                MdAst::Interpolation { code, span } => Some((code.as_str(), *span)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(interpolations.len(), 2);
+        assert_eq!(interpolations[0].0, "v");
+        let span = interpolations[0].1;
+        assert_eq!(&input[span.offset()..span.offset() + span.len()], "{{v}}");
+        assert_eq!(interpolations[1].0, " (* 2 v) ");
+        assert!(children.iter().any(|c| matches!(c, MdAst::Text { content } if content.contains("{not}"))));
+
+        // Unclosed braces and braces in code stay as written.
+        let ast = Parser::new("a {{b\n`{{c}}`").parse().unwrap();
+        let MdAst::Paragraph { children } = &ast[0] else {
+            panic!("expected a paragraph, got {:?}", ast[0]);
+        };
+        assert!(matches!(&children[0], MdAst::Text { content } if content.starts_with("a {{b")));
+        assert!(children.iter().any(|c| matches!(c, MdAst::InlineCode { code } if code == "{{c}}")));
+    }
+
     #[test]
     fn test_eval_scheme() {
         let input = "Result: (eval (+ 1 1))";
@@ -1178,6 +1366,24 @@ mod tests {
         }));
     }
 
+    #[test]
+    fn test_calc_blocks() {
+        fn calc_code(nodes: &[MdAst]) -> Vec<String> {
+            nodes
+                .iter()
+                .flat_map(|node| match node {
+                    MdAst::Paragraph { children } => calc_code(children),
+                    MdAst::CalcBlock { code } => vec![code.clone()],
+                    _ => Vec::new(),
+                })
+                .collect()
+        }
+
+        let input = "Speed: (calc (/ d t)) and (show-eval (* 2 3) (digits 1))";
+        let ast = Parser::new(input).parse().unwrap();
+        assert_eq!(calc_code(&ast), ["(/ d t)", "(* 2 3) (digits 1)"]);
+    }
+
     #[test]
     fn test_normal_parentheses() {
         let input = "This is a (Sentence).";
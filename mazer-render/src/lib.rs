@@ -1,5 +1,6 @@
+use fastnum::D512;
 use mazer_atog::Atog;
-use mazer_types::{Environment, LispAST, implfuncs::ShowFunc};
+use mazer_types::{Environment, LispAST, Unit, implfuncs::ShowFunc};
 
 pub mod docs;
 
@@ -29,9 +30,19 @@ impl MathMLFormatter {
 
 fn format_mathml(expr: &LispAST, env: Option<&Environment>) -> String {
     match expr {
-        LispAST::Error(e) => format!("<merror><mtext>{}</mtext></merror>", escape_xml(e)),
+        LispAST::Error(e) | LispAST::ErrorValue { message: e, .. } => {
+            format!("<merror><mtext>{}</mtext></merror>", escape_xml(e))
+        }
+
+        LispAST::Number(n) => format_number(*n, env),
+
+        LispAST::Quantity { value, unit } => format!(
+            "<mrow>{}<mspace width=\"0.167em\"/>{}</mrow>",
+            format_number(*value, env),
+            format_unit(unit)
+        ),
 
-        LispAST::Number(n) => format!("<mn>{}</mn>", n),
+        LispAST::Poly { var, coeffs } => format_poly(var, coeffs, env),
 
         LispAST::Bool(b) => format!("<mtext>{}</mtext>", b),
 
@@ -39,10 +50,16 @@ fn format_mathml(expr: &LispAST, env: Option<&Environment>) -> String {
 
         LispAST::Symbol(s) => format_symbol(s),
 
+        LispAST::Keyword(k) => format!("<mtext>:{}</mtext>", escape_xml(k)),
+
         LispAST::List(exprs) if exprs.is_empty() => "<mrow></mrow>".to_string(),
 
         LispAST::List(exprs) => format_list(exprs, env),
 
+        LispAST::Set(items) => format_set(items, env),
+
+        LispAST::Dict(entries) => format_dict_table(entries, env),
+
         LispAST::Application { name, args } => {
             let mut full_list = vec![LispAST::Symbol(name.clone())];
             full_list.extend(args.clone());
@@ -55,6 +72,27 @@ fn format_mathml(expr: &LispAST, env: Option<&Environment>) -> String {
     }
 }
 
+/// A number under the environment's [`NumberFormat`], with any power of ten
+/// set as a superscript.
+fn format_number(n: D512, env: Option<&Environment>) -> String {
+    let policy = env.map(|e| e.number_format).unwrap_or_default();
+    let formatted = policy.format(n);
+    match formatted.exponent {
+        None => format!("<mn>{}</mn>", formatted.mantissa),
+        Some(e) => {
+            let exponent = if e < 0 {
+                format!("<mrow><mo>−</mo><mn>{}</mn></mrow>", -e)
+            } else {
+                format!("<mn>{}</mn>", e)
+            };
+            format!(
+                "<mrow><mn>{}</mn><mo>×</mo><msup><mn>10</mn>{}</msup></mrow>",
+                formatted.mantissa, exponent
+            )
+        }
+    }
+}
+
 fn format_list(exprs: &[LispAST], env: Option<&Environment>) -> String {
     if exprs.is_empty() {
         return "<mrow></mrow>".to_string();
@@ -68,7 +106,10 @@ fn format_list(exprs: &[LispAST], env: Option<&Environment>) -> String {
         match op_enum {
             ShowFunc::Define => return format_define(args, env),
             ShowFunc::Defunc => return format_defunc(args, env),
-            ShowFunc::Quote => return format_quote(args, env),
+            ShowFunc::Quote | ShowFunc::Quasiquote => return format_quote(args, env),
+            ShowFunc::Defmacro => return format_defunc(args, env),
+            ShowFunc::Import => return format_func_application("import", args, env),
+            ShowFunc::Require => return format_func_application("require", args, env),
             ShowFunc::String => return format_string(args, env),
 
             // Arithmetic
@@ -100,6 +141,8 @@ fn format_list(exprs: &[LispAST], env: Option<&Environment>) -> String {
             ShowFunc::Derivative => return format_derivative(args, env),
             ShowFunc::Partial => return format_partial(args, env),
             ShowFunc::Dd => return format_dd(args, env),
+            ShowFunc::Series => return format_series(args, env),
+            ShowFunc::BigO => return format_func_application("O", args, env),
 
             // Trig functions
             ShowFunc::Sin => return format_trig("sin", args, env),
@@ -124,6 +167,55 @@ fn format_list(exprs: &[LispAST], env: Option<&Environment>) -> String {
             ShowFunc::Fact => return format_factorial(args, env),
             ShowFunc::Binom => return format_binomial(args, env),
 
+            // Number theory
+            ShowFunc::Mod => return format_infix_op(args, "mod", env),
+            ShowFunc::Rem => return format_func_application("rem", args, env),
+            ShowFunc::Quotient => return format_func_application("quotient", args, env),
+            ShowFunc::Gcd => return format_func_application("gcd", args, env),
+            ShowFunc::Lcm => return format_func_application("lcm", args, env),
+            ShowFunc::IsPrime => return format_func_application("prime?", args, env),
+            ShowFunc::NextPrime => return format_func_application("next-prime", args, env),
+            ShowFunc::Factorize => return format_func_application("factorize", args, env),
+            ShowFunc::Factors => return format_factors(args, env),
+            ShowFunc::PowMod => return format_powmod(args, env),
+            ShowFunc::ModInv => return format_modinv(args, env),
+            ShowFunc::Totient => return format_func_application("φ", args, env),
+
+            // Units
+            ShowFunc::Qty => return format_qty(args, env),
+            ShowFunc::Convert => return format_convert(args, env),
+            ShowFunc::Defunit => return format_defunit(args, env),
+
+            // Formatting
+            ShowFunc::FmtNumber => return format_fmt_number(args, env),
+
+            // Checks
+            ShowFunc::Assert => return format_assert(args, env),
+            ShowFunc::Expect => return format_expect(args, env),
+
+            // Errors
+            ShowFunc::Error => return format_func_application("error", args, env),
+            ShowFunc::Try => return format_func_application("try", args, env),
+            ShowFunc::ErrorMessage => return format_func_application("error-message", args, env),
+            ShowFunc::ErrorKind => return format_func_application("error-kind", args, env),
+            ShowFunc::ErrorData => return format_func_application("error-data", args, env),
+            ShowFunc::IsError => return format_func_application("error?", args, env),
+
+            // Debugging
+            ShowFunc::Reflect => return format_func_application("reflect", args, env),
+            ShowFunc::Print => return format_func_application("print", args, env),
+            ShowFunc::Debug => return format_func_application("debug", args, env),
+
+            // Polynomials
+            ShowFunc::Poly => return format_poly_literal(args, env),
+            ShowFunc::PolyDiv => return format_func_application("poly-div", args, env),
+            ShowFunc::PolyGcd => return format_func_application("gcd", args, env),
+            ShowFunc::PolyDeriv => return format_prime(args, env),
+            ShowFunc::PolyEval => return format_funcapp(args, env),
+            ShowFunc::PolyRoots => return format_func_application("roots", args, env),
+            ShowFunc::Solve => return format_func_application("solve", args, env),
+            ShowFunc::Complex => return format_complex(args, env),
+
             // Matrices
             ShowFunc::Matrix => return format_matrix(args, env),
             ShowFunc::Vec => return format_vector(args, env),
@@ -144,6 +236,35 @@ fn format_list(exprs: &[LispAST], env: Option<&Environment>) -> String {
             ShowFunc::Union => return format_infix_op(args, "∪", env),
             ShowFunc::Intersect => return format_infix_op(args, "∩", env),
 
+            // Dicts
+            ShowFunc::Dict => {
+                let entries: Vec<_> = args
+                    .chunks(2)
+                    .map(|kv| (kv[0].clone(), kv.get(1).cloned().unwrap_or(LispAST::List([].into()))))
+                    .collect();
+                return format_dict_table(&entries, env);
+            }
+            ShowFunc::Get => return format_func_application("get", args, env),
+            ShowFunc::Assoc => return format_func_application("assoc", args, env),
+            ShowFunc::Dissoc => return format_func_application("dissoc", args, env),
+            ShowFunc::Keys => return format_func_application("keys", args, env),
+            ShowFunc::Vals => return format_func_application("vals", args, env),
+            ShowFunc::Merge => return format_func_application("merge", args, env),
+
+            // Strings
+            ShowFunc::Concat => return format_func_application("concat", args, env),
+            ShowFunc::Substring => return format_func_application("substring", args, env),
+            ShowFunc::Split => return format_func_application("split", args, env),
+            ShowFunc::Join => return format_func_application("join", args, env),
+            ShowFunc::Upper => return format_func_application("upper", args, env),
+            ShowFunc::Lower => return format_func_application("lower", args, env),
+            ShowFunc::Trim => return format_func_application("trim", args, env),
+            ShowFunc::Replace => return format_func_application("replace", args, env),
+            ShowFunc::Format => return format_func_application("format", args, env),
+            ShowFunc::NumberToString => return format_func_application("number->string", args, env),
+            ShowFunc::StringToNumber => return format_func_application("string->number", args, env),
+            ShowFunc::Length => return format_func_application("length", args, env),
+
             // Logic
             ShowFunc::And => return format_infix_op(args, "∧", env),
             ShowFunc::Or => return format_infix_op(args, "∨", env),
@@ -152,6 +273,7 @@ fn format_list(exprs: &[LispAST], env: Option<&Environment>) -> String {
             ShowFunc::Iff => return format_infix_op(args, "⟺", env),
             ShowFunc::ForAll => return format_quantifier("∀", args, env),
             ShowFunc::Exists => return format_quantifier("∃", args, env),
+            ShowFunc::TruthTable => return format_func_application("truth-table", args, env),
 
             // Grouping
             ShowFunc::Paren => return format_parenthesized(args, env),
@@ -211,7 +333,12 @@ fn format_defunc(args: &[LispAST], env: Option<&Environment>) -> String {
     }
     let name = format_mathml(&args[0], env);
     let params = format_mathml(&args[1], env);
-    let body = format_mathml(&args[2], env);
+    // Leave out a docstring; of several body forms the last gives the value.
+    let body = match &args[2..] {
+        [LispAST::String(_), rest @ ..] if !rest.is_empty() => rest,
+        body => body,
+    };
+    let body = format_mathml(&body[body.len() - 1], env);
     format!(
         "<mrow>{}<mo>(</mo>{}<mo>)</mo><mo>=</mo>{}</mrow>",
         name, params, body
@@ -470,6 +597,38 @@ fn format_derivative(args: &[LispAST], env: Option<&Environment>) -> String {
     }
 }
 
+/// `(series f x a n)` renders as the Taylor sum
+/// Σₖ₌₀ⁿ 1/k! [dᵏf/dxᵏ]ₓ₌ₐ (x − a)ᵏ, with (x − a) written as x when a is 0.
+fn format_series(args: &[LispAST], env: Option<&Environment>) -> String {
+    if args.len() != 4 {
+        return "<merror><mtext>series requires 4 arguments</mtext></merror>".to_string();
+    }
+    let k = LispAST::Symbol("k".to_string());
+    // `(series '(sin x) x 0 3)` sets the same as the unquoted form.
+    let expr = match &args[0] {
+        LispAST::List(items) => match &items[..] {
+            [LispAST::Symbol(q), quoted] if q == "quote" => quoted.clone(),
+            _ => args[0].clone(),
+        },
+        expr => expr.clone(),
+    };
+    let var = format_mathml(&args[1], env);
+    let point = format_mathml(&args[2], env);
+    let order = format_mathml(&args[3], env);
+    let derivative = format_derivative(&[expr, args[1].clone(), k.clone()], env);
+    let shifted = match &args[2] {
+        LispAST::Number(a) if a.is_zero() => var.clone(),
+        _ => format!("<mrow><mo>(</mo>{}<mo>-</mo>{}<mo>)</mo></mrow>", var, point),
+    };
+    format!(
+        "<mrow><munderover><mo>∑</mo><mrow><mi>k</mi><mo>=</mo><mn>0</mn></mrow>{}</munderover>\
+         <mfrac><mn>1</mn><mrow><mi>k</mi><mo>!</mo></mrow></mfrac>\
+         <msub><mrow><mo>[</mo>{}<mo>]</mo></mrow><mrow>{}<mo>=</mo>{}</mrow></msub>\
+         <msup>{}<mi>k</mi></msup></mrow>",
+        order, derivative, var, point, shifted
+    )
+}
+
 fn format_partial(args: &[LispAST], env: Option<&Environment>) -> String {
     if args.len() != 2 {
         return "<merror><mtext>partial requires 2 arguments</mtext></merror>".to_string();
@@ -599,6 +758,272 @@ fn format_binomial(args: &[LispAST], env: Option<&Environment>) -> String {
     )
 }
 
+/// Formats a prime factorisation as a product of powers.
+///
+/// Each argument is a `(p k)` pair, exactly as returned by `factorize`; a
+/// multiplicity of 1 drops the exponent. A bare argument is rendered as-is, and
+/// an empty product renders as `1`.
+fn format_factors(args: &[LispAST], env: Option<&Environment>) -> String {
+    if args.is_empty() {
+        return "<mn>1</mn>".to_string();
+    }
+
+    let factors: Vec<String> = args
+        .iter()
+        .map(|factor| match factor {
+            LispAST::List(pair) if pair.len() == 2 => {
+                let base = format_mathml(&pair[0], env);
+                match &pair[1] {
+                    LispAST::Number(k) if k.is_one() => base,
+                    exp => format!("<msup>{}{}</msup>", base, format_mathml(exp, env)),
+                }
+            }
+            other => format_mathml(other, env),
+        })
+        .collect();
+    format!("<mrow>{}</mrow>", factors.join("<mo>×</mo>"))
+}
+
+fn format_powmod(args: &[LispAST], env: Option<&Environment>) -> String {
+    if args.len() != 3 {
+        return "<merror><mtext>powmod requires 3 arguments</mtext></merror>".to_string();
+    }
+    let power = format_power(&args[..2], env);
+    let modulus = format_mathml(&args[2], env);
+    format!("<mrow>{}<mo>mod</mo>{}</mrow>", power, modulus)
+}
+
+fn format_modinv(args: &[LispAST], env: Option<&Environment>) -> String {
+    if args.len() != 2 {
+        return "<merror><mtext>modinv requires 2 arguments</mtext></merror>".to_string();
+    }
+    let value = format_mathml(&args[0], env);
+    let modulus = format_mathml(&args[1], env);
+    format!(
+        "<mrow><msup>{}<mrow><mo>-</mo><mn>1</mn></mrow></msup><mo>mod</mo>{}</mrow>",
+        value, modulus
+    )
+}
+
+/// `(qty value unit)` as written in a show block.
+fn format_qty(args: &[LispAST], env: Option<&Environment>) -> String {
+    if args.len() != 2 {
+        return "<merror><mtext>qty requires 2 arguments</mtext></merror>".to_string();
+    }
+    let value = format_mathml(&args[0], env);
+    let unit = format_unit_expr(&args[1], env);
+    format!("<mrow>{}<mspace width=\"0.167em\"/>{}</mrow>", value, unit)
+}
+
+/// `(convert q unit)` renders as q → unit.
+fn format_convert(args: &[LispAST], env: Option<&Environment>) -> String {
+    if args.len() != 2 {
+        return "<merror><mtext>convert requires 2 arguments</mtext></merror>".to_string();
+    }
+    let value = format_mathml(&args[0], env);
+    let unit = format_unit_expr(&args[1], env);
+    format!("<mrow>{}<mo>→</mo>{}</mrow>", value, unit)
+}
+
+/// `(defunit furlong (qty 201.168 m))` renders as 1 furlong = 201.168 m.
+fn format_defunit(args: &[LispAST], env: Option<&Environment>) -> String {
+    if args.len() != 2 {
+        return "<merror><mtext>defunit requires 2 arguments</mtext></merror>".to_string();
+    }
+    let name = match &args[0] {
+        LispAST::Symbol(s) => format!("<mi mathvariant=\"normal\">{}</mi>", escape_xml(s)),
+        other => format_mathml(other, env),
+    };
+    let value = format_mathml(&args[1], env);
+    format!(
+        "<mrow><mn>1</mn><mspace width=\"0.167em\"/>{}<mo>=</mo>{}</mrow>",
+        name, value
+    )
+}
+
+/// `(fmt-number x (sig 3) ...)` renders x under the environment's number
+/// format with the given options layered on top.
+fn format_fmt_number(args: &[LispAST], env: Option<&Environment>) -> String {
+    let Some((value, options)) = args.split_first() else {
+        return "<merror><mtext>fmt-number requires at least 1 argument</mtext></merror>"
+            .to_string();
+    };
+    let mut scoped = env.cloned().unwrap_or_else(Environment::new);
+    for option in options {
+        if let Err(e) = scoped.number_format.apply("fmt-number", option) {
+            return format!("<merror><mtext>{}</mtext></merror>", escape_xml(&e.to_string()));
+        }
+    }
+    format_mathml(value, Some(&scoped))
+}
+
+/// `(assert cond)` renders as the condition it checks.
+fn format_assert(args: &[LispAST], env: Option<&Environment>) -> String {
+    match args.first() {
+        Some(cond) => format_mathml(cond, env),
+        None => "<merror><mtext>assert requires 1 argument</mtext></merror>".to_string(),
+    }
+}
+
+/// `(expect expr value tolerance)` renders as expr = value ± tolerance.
+fn format_expect(args: &[LispAST], env: Option<&Environment>) -> String {
+    let [expr, value, tolerance @ ..] = args else {
+        return "<merror><mtext>expect requires 2 or 3 arguments</mtext></merror>".to_string();
+    };
+    let tolerance = match tolerance {
+        [tol] => format!("<mo>±</mo>{}", format_mathml(tol, env)),
+        _ => String::new(),
+    };
+    format!(
+        "<mrow>{}<mo>=</mo>{}{}</mrow>",
+        format_mathml(expr, env),
+        format_mathml(value, env),
+        tolerance
+    )
+}
+
+/// `(poly 1 -3 2)` as written: set as the polynomial itself when every
+/// coefficient is a literal, otherwise as an application.
+fn format_poly_literal(args: &[LispAST], env: Option<&Environment>) -> String {
+    let (var, rest) = match args {
+        [LispAST::Symbol(v), rest @ ..] => (v.as_str(), rest),
+        [LispAST::List(q), rest @ ..] => match &q[..] {
+            [LispAST::Symbol(quote), LispAST::Symbol(v)] if quote == "quote" => (v.as_str(), rest),
+            _ => ("x", args),
+        },
+        _ => ("x", args),
+    };
+    let coeffs: Option<Vec<_>> = rest
+        .iter()
+        .rev()
+        .map(|c| match c {
+            LispAST::Number(n) => Some(*n),
+            _ => None,
+        })
+        .collect();
+    match coeffs {
+        Some(mut coeffs) if !coeffs.is_empty() => {
+            while coeffs.last().is_some_and(|c| c.is_zero()) {
+                coeffs.pop();
+            }
+            format_poly(var, &coeffs, env)
+        }
+        _ => format_func_application("poly", args, env),
+    }
+}
+
+/// A polynomial in descending powers, e.g. x² − 3x + 2. Unit coefficients are
+/// dropped except on the constant term, and zero terms are skipped.
+fn format_poly(var: &str, coeffs: &[D512], env: Option<&Environment>) -> String {
+    let mut terms = String::new();
+    for (power, c) in coeffs.iter().enumerate().rev() {
+        if c.is_zero() {
+            continue;
+        }
+        let sign = if c.is_negative() { "-" } else { "+" };
+        if !terms.is_empty() {
+            terms.push_str(&format!("<mo>{}</mo>", sign));
+        } else if c.is_negative() {
+            terms.push_str("<mo>-</mo>");
+        }
+        let magnitude = c.abs();
+        if power == 0 || magnitude != D512::ONE {
+            terms.push_str(&format_number(magnitude, env));
+        }
+        match power {
+            0 => {}
+            1 => terms.push_str(&format_symbol(var)),
+            _ => terms.push_str(&format!(
+                "<msup>{}<mn>{}</mn></msup>",
+                format_symbol(var),
+                power
+            )),
+        }
+    }
+    if terms.is_empty() {
+        return "<mn>0</mn>".to_string();
+    }
+    format!("<mrow>{}</mrow>", terms)
+}
+
+/// `(complex re im)` renders as re ± |im|i, omitting a zero real part.
+fn format_complex(args: &[LispAST], env: Option<&Environment>) -> String {
+    if args.len() != 2 {
+        return "<merror><mtext>complex requires 2 arguments</mtext></merror>".to_string();
+    }
+    let i = "<mi>i</mi>";
+    match (&args[0], &args[1]) {
+        (LispAST::Number(re), LispAST::Number(im)) => {
+            let magnitude = if im.abs() == D512::ONE {
+                String::new()
+            } else {
+                format_number(im.abs(), env)
+            };
+            let sign = if im.is_negative() { "-" } else { "+" };
+            if re.is_zero() {
+                let sign = if im.is_negative() { "<mo>-</mo>" } else { "" };
+                format!("<mrow>{}{}{}</mrow>", sign, magnitude, i)
+            } else {
+                format!(
+                    "<mrow>{}<mo>{}</mo>{}{}</mrow>",
+                    format_number(*re, env),
+                    sign,
+                    magnitude,
+                    i
+                )
+            }
+        }
+        (re, im) => format!(
+            "<mrow>{}<mo>+</mo>{}{}</mrow>",
+            format_mathml(re, env),
+            format_mathml(im, env),
+            i
+        ),
+    }
+}
+
+/// A unit written in source, resolved against `defunit`s in `env` when there is
+/// one. A unit that does not parse is still set upright rather than rejected.
+fn format_unit_expr(expr: &LispAST, env: Option<&Environment>) -> String {
+    match expr {
+        LispAST::Symbol(s) | LispAST::String(s) => env
+            .map_or_else(|| Unit::parse(s), |e| e.unit(s))
+            .map_or_else(
+                |_| format!("<mi mathvariant=\"normal\">{}</mi>", escape_xml(s)),
+                |u| format_unit(&u),
+            ),
+        other => format_mathml(other, env),
+    }
+}
+
+/// Upright unit symbols. A single denominator term is written after a slash
+/// (`m/s²`); otherwise negative powers are kept as exponents (`kg·m⁻¹·s⁻²`).
+fn format_unit(unit: &Unit) -> String {
+    let term = |symbol: &str, exp: i32| {
+        let base = format!("<mi mathvariant=\"normal\">{}</mi>", escape_xml(symbol));
+        match exp {
+            1 => base,
+            e if e < 0 => format!(
+                "<msup>{}<mrow><mo>−</mo><mn>{}</mn></mrow></msup>",
+                base, -e
+            ),
+            e => format!("<msup>{}<mn>{}</mn></msup>", base, e),
+        }
+    };
+    let dot = "<mo>·</mo>";
+
+    let (num, den): (Vec<_>, Vec<_>) = unit.terms.iter().partition(|(_, e)| *e > 0);
+    let body = if den.len() == 1 && !num.is_empty() {
+        let num: Vec<_> = num.iter().map(|(s, e)| term(s, *e)).collect();
+        let (s, e) = den[0];
+        format!("{}<mo>/</mo>{}", num.join(dot), term(s, -e))
+    } else {
+        let all: Vec<_> = unit.terms.iter().map(|(s, e)| term(s, *e)).collect();
+        all.join(dot)
+    };
+    format!("<mrow>{}</mrow>", body)
+}
+
 fn format_matrix(args: &[LispAST], env: Option<&Environment>) -> String {
     if args.is_empty() {
         return "<mtable></mtable>".to_string();
@@ -681,8 +1106,24 @@ fn format_table(args: &[LispAST], env: Option<&Environment>) -> String {
     let mut has_header = false;
     let mut rows_html: Vec<String> = Vec::new();
 
-    for arg in args {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
         match arg {
+            // `:style clean` and `:align [l c r]` spell `(style clean)` and
+            // `(align l c r)` as keyword arguments.
+            LispAST::Keyword(option) => match (option.as_str(), args.next()) {
+                ("style", Some(LispAST::Symbol(s))) => style = table_style(s),
+                ("align", Some(LispAST::List(items)))
+                    if matches!(items.first(), Some(LispAST::Symbol(v)) if v == "vec") =>
+                {
+                    let aligns: Vec<&str> = items[1..].iter().map(map_align).collect();
+                    if !aligns.is_empty() {
+                        col_align = Some(aligns.join(" "));
+                    }
+                }
+                ("align", Some(a)) => col_align = Some(map_align(a).to_string()),
+                _ => {}
+            },
             LispAST::List(items) if !items.is_empty() => {
                 if let LispAST::Symbol(tag) = &items[0] {
                     match tag.as_str() {
@@ -695,11 +1136,7 @@ fn format_table(args: &[LispAST], env: Option<&Environment>) -> String {
                         }
                         "style" => {
                             if let Some(LispAST::Symbol(s)) = items.get(1) {
-                                style = match s.as_str() {
-                                    "clean" => "clean",
-                                    "plain" => "plain",
-                                    _ => "grid",
-                                };
+                                style = table_style(s);
                             }
                             continue;
                         }
@@ -747,6 +1184,32 @@ fn format_table(args: &[LispAST], env: Option<&Environment>) -> String {
     format!("<mtable{}>{}</mtable>", attrs, rows_html.join(""))
 }
 
+/// A dict as a two-column table, one `(row key value)` per entry. Keyword
+/// keys are written without their colon.
+fn format_dict_table(entries: &[(LispAST, LispAST)], env: Option<&Environment>) -> String {
+    let rows: Vec<LispAST> = std::iter::once(LispAST::List(vec![
+        LispAST::Symbol("style".to_string()),
+        LispAST::Symbol("clean".to_string()),
+    ].into()))
+    .chain(entries.iter().map(|(key, value)| {
+        let key = match key {
+            LispAST::Keyword(k) => LispAST::String(k.clone()),
+            other => other.clone(),
+        };
+        LispAST::List(vec![LispAST::Symbol("row".to_string()), key, value.clone()].into())
+    }))
+    .collect();
+    format_table(&rows, env)
+}
+
+fn table_style(name: &str) -> &'static str {
+    match name {
+        "clean" => "clean",
+        "plain" => "plain",
+        _ => "grid",
+    }
+}
+
 /// Formats one table row. Header cells are wrapped in a bold `<mstyle>`.
 fn format_table_row(cells: &[LispAST], is_header: bool, env: Option<&Environment>) -> String {
     let tds: Vec<String> = cells
@@ -865,7 +1328,7 @@ fn format_text(args: &[LispAST], _env: Option<&Environment>) -> String {
         .map(|arg| match arg {
             LispAST::String(s) => escape_xml(s),
             LispAST::Symbol(s) => s.clone(),
-            _ => format!("{:?}", arg),
+            other => escape_xml(&other.to_string()),
         })
         .collect();
     format!("<mtext>{}</mtext>", text_parts.join(" "))
@@ -1094,7 +1557,7 @@ mod table_tests {
     }
 
     fn list(items: Vec<LispAST>) -> LispAST {
-        LispAST::List(items)
+        LispAST::List(items.into())
     }
 
     /// A header + a couple of data rows, default (grid) style.
@@ -1206,3 +1669,50 @@ mod table_tests {
         assert!(out.contains("<mfrac>"));
     }
 }
+
+#[cfg(test)]
+mod number_tests {
+    use super::*;
+
+    #[test]
+    fn policy_applies_to_rendered_numbers() {
+        let mut env = Environment::new();
+        let n = LispAST::Number(D512::from(-123456));
+        assert_eq!(format_mathml(&n, Some(&env)), "<mn>-123456</mn>");
+
+        env.number_format.notation = mazer_types::Notation::Scientific;
+        env.number_format.precision = mazer_types::Precision::Significant(2);
+        assert_eq!(
+            format_mathml(&n, Some(&env)),
+            "<mrow><mn>-1.2</mn><mo>×</mo><msup><mn>10</mn><mn>5</mn></msup></mrow>"
+        );
+    }
+}
+
+#[cfg(test)]
+mod poly_tests {
+    use super::*;
+
+    fn coeffs(cs: &[i64]) -> Vec<D512> {
+        cs.iter().map(|&c| D512::from(c)).collect()
+    }
+
+    #[test]
+    fn descending_terms_with_signs() {
+        // 2 - 3x + x² in ascending order.
+        let out = format_poly("x", &coeffs(&[2, -3, 1]), None);
+        assert_eq!(
+            out,
+            "<mrow><msup><mi>x</mi><mn>2</mn></msup><mo>-</mo><mn>3</mn><mi>x</mi><mo>+</mo><mn>2</mn></mrow>"
+        );
+    }
+
+    #[test]
+    fn zero_polynomial_and_unit_coefficients() {
+        assert_eq!(format_poly("x", &[], None), "<mn>0</mn>");
+        // -x³ + 1 keeps the leading minus and drops the 1 on x³.
+        let out = format_poly("t", &coeffs(&[1, 0, 0, -1]), None);
+        assert!(out.starts_with("<mrow><mo>-</mo><msup><mi>t</mi>"));
+        assert!(out.ends_with("<mo>+</mo><mn>1</mn></mrow>"));
+    }
+}
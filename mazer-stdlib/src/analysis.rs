@@ -0,0 +1,469 @@
+//! Elementary functions on numbers, symbolic differentiation of quoted
+//! expressions, and truncated Taylor series.
+//!
+//! `exp`, `ln`, `sin`, `cos`, `tan` and `sqrt` evaluate in D512 and reject
+//! arguments outside their domain instead of producing NaN. [`differentiate`]
+//! understands those functions and `+ - * / ^`; `series` falls back to finite
+//! differences for anything else, such as calls to user functions.
+
+use fastnum::D512;
+use mazer_types::{LispAST, LispError};
+
+use crate::{Native, poly};
+
+/// Highest order `series` will expand to; symbolic derivatives grow quickly.
+const MAX_ORDER: u32 = 20;
+
+/// Decimal places kept for coefficients found by finite differences.
+const NUMERIC_DIGITS: i16 = 20;
+
+/// `exp` overflows D512 well past this; refuse rather than trap.
+const MAX_EXP_ARGUMENT: i64 = 100_000_000;
+
+fn number(form: &str, args: &[LispAST]) -> Result<D512, LispError> {
+    match args {
+        [LispAST::Number(n)] => Ok(*n),
+        [other] => Err(LispError::TypeMismatch {
+            form: form.to_string(),
+            expected: "Number".to_string(),
+            got: other.type_name().to_string(),
+        }),
+        _ => Err(LispError::Arity {
+            form: form.to_string(),
+            expected: "1".to_string(),
+            got: args.len(),
+        }),
+    }
+}
+
+fn undefined(form: &str, x: D512) -> LispError {
+    LispError::Domain {
+        form: form.to_string(),
+        reason: format!("{form} is undefined at {x}"),
+    }
+}
+
+impl Native {
+    pub fn exp(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let x = number("exp", args)?;
+        if x > D512::from(MAX_EXP_ARGUMENT) {
+            return Err(LispError::Domain {
+                form: "exp".to_string(),
+                reason: format!("exp({x}) is too large to represent"),
+            });
+        }
+        Ok(LispAST::Number(x.exp()))
+    }
+
+    pub fn ln(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let x = number("ln", args)?;
+        if x.is_negative() || x.is_zero() {
+            return Err(undefined("ln", x));
+        }
+        Ok(LispAST::Number(x.ln()))
+    }
+
+    pub fn sin(args: &[LispAST]) -> Result<LispAST, LispError> {
+        Ok(LispAST::Number(number("sin", args)?.sin()))
+    }
+
+    pub fn cos(args: &[LispAST]) -> Result<LispAST, LispError> {
+        Ok(LispAST::Number(number("cos", args)?.cos()))
+    }
+
+    pub fn tan(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let x = number("tan", args)?;
+        let (sin, cos) = (x.sin(), x.cos());
+        if cos.is_zero() {
+            return Err(undefined("tan", x));
+        }
+        Ok(LispAST::Number(sin / cos))
+    }
+
+    pub fn sqrt(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let x = number("sqrt", args)?;
+        if x.is_negative() {
+            return Err(undefined("sqrt", x));
+        }
+        Ok(LispAST::Number(x.sqrt()))
+    }
+}
+
+// Builders for derivative expressions. They fold numeric constants and drop
+// identities as they go, so repeated differentiation stays readable.
+
+fn num(n: i64) -> LispAST {
+    LispAST::Number(D512::from(n))
+}
+
+fn call(op: &str, args: Vec<LispAST>) -> LispAST {
+    let mut items = vec![LispAST::Symbol(op.to_string())];
+    items.extend(args);
+    LispAST::List(items.into())
+}
+
+fn is_number(expr: &LispAST, n: i64) -> bool {
+    matches!(expr, LispAST::Number(x) if *x == D512::from(n))
+}
+
+/// The operands of `expr` if it is a call to `op`, else `expr` alone, so that
+/// nested sums and products flatten.
+fn operands(op: &str, expr: LispAST) -> Vec<LispAST> {
+    match expr {
+        LispAST::List(items) if matches!(items.first(), Some(LispAST::Symbol(s)) if s == op) => {
+            items[1..].to_vec()
+        }
+        other => vec![other],
+    }
+}
+
+fn sum(terms: Vec<LispAST>) -> LispAST {
+    let mut constant = D512::ZERO;
+    let mut rest = Vec::new();
+    for term in terms.into_iter().flat_map(|t| operands("+", t)) {
+        match term {
+            LispAST::Number(n) => constant += n,
+            other => rest.push(other),
+        }
+    }
+    if !constant.is_zero() || rest.is_empty() {
+        rest.push(LispAST::Number(constant));
+    }
+    if rest.len() == 1 {
+        rest.remove(0)
+    } else {
+        call("+", rest)
+    }
+}
+
+fn product(factors: Vec<LispAST>) -> LispAST {
+    let mut constant = D512::ONE;
+    let mut rest = Vec::new();
+    for factor in factors.into_iter().flat_map(|f| operands("*", f)) {
+        match factor {
+            LispAST::Number(n) => constant *= n,
+            other => rest.push(other),
+        }
+    }
+    if constant.is_zero() {
+        return num(0);
+    }
+    if constant != D512::ONE || rest.is_empty() {
+        rest.insert(0, LispAST::Number(constant));
+    }
+    if rest.len() == 1 {
+        rest.remove(0)
+    } else {
+        call("*", rest)
+    }
+}
+
+fn negate(expr: LispAST) -> LispAST {
+    product(vec![num(-1), expr])
+}
+
+fn quotient(numerator: LispAST, denominator: LispAST) -> LispAST {
+    match (&numerator, &denominator) {
+        _ if is_number(&numerator, 0) => num(0),
+        _ if is_number(&denominator, 1) => numerator,
+        (LispAST::Number(a), LispAST::Number(b)) if !b.is_zero() => LispAST::Number(*a / *b),
+        _ => call("/", vec![numerator, denominator]),
+    }
+}
+
+fn power(base: LispAST, exponent: LispAST) -> LispAST {
+    if is_number(&exponent, 0) {
+        num(1)
+    } else if is_number(&exponent, 1) {
+        base
+    } else {
+        call("^", vec![base, exponent])
+    }
+}
+
+fn mentions(expr: &LispAST, var: &str) -> bool {
+    match expr {
+        LispAST::Symbol(s) => s == var,
+        LispAST::List(items) => items.iter().any(|e| mentions(e, var)),
+        _ => false,
+    }
+}
+
+/// The derivative of a quoted expression with respect to `var`, or `None` when
+/// it calls something without a known derivative (a user function, say).
+/// Anything not mentioning `var` is a constant.
+pub fn differentiate(expr: &LispAST, var: &str) -> Option<LispAST> {
+    if !mentions(expr, var) {
+        return Some(num(0));
+    }
+    let LispAST::List(items) = expr else {
+        return Some(num(1));
+    };
+    let LispAST::Symbol(op) = items.first()? else {
+        return None;
+    };
+    let args = &items[1..];
+    let d = |e: &LispAST| differentiate(e, var);
+
+    let derivative = match (op.as_str(), args) {
+        ("+" | "add", _) => sum(args.iter().map(d).collect::<Option<_>>()?),
+        ("-" | "sub", [u]) => negate(d(u)?),
+        ("-" | "sub", [u, rest @ ..]) => {
+            let mut terms = vec![d(u)?];
+            for r in rest {
+                terms.push(negate(d(r)?));
+            }
+            sum(terms)
+        }
+        // Product rule over every factor.
+        ("*" | "mul", _) => {
+            let mut terms = Vec::with_capacity(args.len());
+            for i in 0..args.len() {
+                let mut factors = args.to_vec();
+                factors[i] = d(&args[i])?;
+                terms.push(product(factors));
+            }
+            sum(terms)
+        }
+        ("/" | "div", [v]) => quotient(negate(d(v)?), power(v.clone(), num(2))),
+        ("/" | "div", [u, v]) => quotient(
+            sum(vec![
+                product(vec![d(u)?, v.clone()]),
+                negate(product(vec![u.clone(), d(v)?])),
+            ]),
+            power(v.clone(), num(2)),
+        ),
+        ("/" | "div", [u, v, rest @ ..]) => {
+            let mut nested = vec![call("/", vec![u.clone(), v.clone()])];
+            nested.extend(rest.iter().cloned());
+            return differentiate(&call("/", nested), var);
+        }
+        ("^" | "pow", [u, n]) if !mentions(n, var) => product(vec![
+            n.clone(),
+            power(u.clone(), sum(vec![n.clone(), num(-1)])),
+            d(u)?,
+        ]),
+        ("^" | "pow", [a, v]) if !mentions(a, var) => {
+            product(vec![expr.clone(), call("ln", vec![a.clone()]), d(v)?])
+        }
+        ("^" | "pow", [u, v]) => product(vec![
+            expr.clone(),
+            sum(vec![
+                product(vec![d(v)?, call("ln", vec![u.clone()])]),
+                quotient(product(vec![v.clone(), d(u)?]), u.clone()),
+            ]),
+        ]),
+        ("exp", [u]) => product(vec![expr.clone(), d(u)?]),
+        ("ln", [u]) => quotient(d(u)?, u.clone()),
+        ("sin", [u]) => product(vec![call("cos", vec![u.clone()]), d(u)?]),
+        ("cos", [u]) => negate(product(vec![call("sin", vec![u.clone()]), d(u)?])),
+        ("tan", [u]) => quotient(d(u)?, power(call("cos", vec![u.clone()]), num(2))),
+        ("sqrt", [u]) => quotient(d(u)?, product(vec![num(2), expr.clone()])),
+        _ => return None,
+    };
+    Some(derivative)
+}
+
+/// Evaluates an expression with the series variable bound to a number.
+pub type Evaluator<'a> = dyn FnMut(&LispAST, D512) -> Result<LispAST, LispError> + 'a;
+
+fn sample(at: &mut Evaluator, expr: &LispAST, x: D512) -> Result<D512, LispError> {
+    match at(expr, x)? {
+        LispAST::Number(n) => Ok(n),
+        other => Err(LispError::TypeMismatch {
+            form: "series".to_string(),
+            expected: "an expression that evaluates to a Number".to_string(),
+            got: other.type_name().to_string(),
+        }),
+    }
+}
+
+/// The `j`th derivative of `expr` at `a` by a central difference. D512 carries
+/// ~150 digits, so a tiny step still leaves ~20 good ones after cancellation.
+fn finite_difference(
+    at: &mut Evaluator,
+    expr: &LispAST,
+    j: u32,
+    a: D512,
+) -> Result<D512, LispError> {
+    let order = i32::try_from(j).unwrap_or(i32::MAX);
+    let h = D512::from(10).powi(-(100 / (order + 2)));
+    let mut total = D512::ZERO;
+    let mut binomial = D512::ONE;
+    for i in 0..=j {
+        let offset = (D512::from(j) / D512::from(2) - D512::from(i)) * h;
+        let term = binomial * sample(at, expr, a + offset)?;
+        total = if i % 2 == 0 {
+            total + term
+        } else {
+            total - term
+        };
+        binomial = binomial * D512::from(j - i) / D512::from(i + 1);
+    }
+    Ok(total / h.powi(order))
+}
+
+impl Native {
+    /// `(series f x a n)`: the Taylor polynomial of `f` about `x = a` through
+    /// `(x - a)ⁿ`, as `(+ p (O (^ (- x a) n+1)))` with `p` expanded in powers
+    /// of `x`. The interpreter supplies `at`, since `f` is taken unevaluated.
+    pub fn series(
+        expr: &LispAST,
+        var: &str,
+        point: &LispAST,
+        order: &LispAST,
+        at: &mut Evaluator,
+    ) -> Result<LispAST, LispError> {
+        let LispAST::Number(a) = *point else {
+            return Err(LispError::TypeMismatch {
+                form: "series".to_string(),
+                expected: "Number".to_string(),
+                got: point.type_name().to_string(),
+            });
+        };
+        let n = match order {
+            LispAST::Number(n) if n.is_integral() => n.to_u32().ok().filter(|n| *n <= MAX_ORDER),
+            _ => None,
+        }
+        .ok_or_else(|| LispError::NotAnInteger {
+            form: "series".to_string(),
+            expected: format!("an order between 0 and {MAX_ORDER}"),
+            value: match order {
+                LispAST::Number(n) => n.to_string(),
+                other => other.type_name().to_string(),
+            },
+        })?;
+
+        // Differentiate symbolically for as long as possible, then take
+        // finite differences of the last symbolic derivative.
+        let mut coefficients = Vec::with_capacity(n as usize + 1);
+        let mut symbolic = Some(expr.clone());
+        let mut last = (0, expr.clone());
+        let mut factorial = D512::ONE;
+        for k in 0..=n {
+            if k > 0 {
+                factorial *= D512::from(k);
+            }
+            let coefficient = if let Some(e) = symbolic.take() {
+                let value = sample(at, &e, a)? / factorial;
+                symbolic = differentiate(&e, var);
+                last = (k, e);
+                value
+            } else {
+                let value = finite_difference(at, &last.1, k - last.0, a)? / factorial;
+                value.round(NUMERIC_DIGITS).reduce()
+            };
+            coefficients.push(coefficient);
+        }
+
+        // Horner's rule in (x - a) to expand into powers of x.
+        let shift = [-a, D512::ONE];
+        let expanded = coefficients.iter().rev().fold(Vec::new(), |acc, c| {
+            poly::add(&poly::mul(&acc, &shift), &[*c])
+        });
+        let expanded = expanded
+            .into_iter()
+            .map(|c| if c.is_zero() { D512::ZERO } else { c.reduce() })
+            .collect();
+
+        let base = if a.is_zero() {
+            LispAST::Symbol(var.to_string())
+        } else {
+            call(
+                "-",
+                vec![LispAST::Symbol(var.to_string()), LispAST::Number(a)],
+            )
+        };
+        let remainder = call(
+            "O",
+            vec![call("^", vec![base, LispAST::Number(D512::from(n + 1))])],
+        );
+        Ok(call("+", vec![poly::value(var, expanded), remainder]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sym(s: &str) -> LispAST {
+        LispAST::Symbol(s.to_string())
+    }
+
+    /// A stand-in for the interpreter: evaluates natives with `x` bound.
+    fn evaluate(expr: &LispAST, x: D512) -> Result<LispAST, LispError> {
+        match expr {
+            LispAST::Number(_) => Ok(expr.clone()),
+            LispAST::Symbol(s) if s == "x" => Ok(LispAST::Number(x)),
+            LispAST::List(items) => {
+                let LispAST::Symbol(op) = &items[0] else {
+                    unreachable!()
+                };
+                let args = items[1..]
+                    .iter()
+                    .map(|e| evaluate(e, x))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match op.as_str() {
+                    "+" => Native::add(&args),
+                    "-" => Native::sub(&args),
+                    "*" => Native::mul(&args),
+                    "/" => Native::div(&args),
+                    "^" => Native::pow(&args),
+                    "exp" => Native::exp(&args),
+                    "sin" => Native::sin(&args),
+                    "cos" => Native::cos(&args),
+                    "ln" => Native::ln(&args),
+                    // An opaque function: differentiate cannot see inside it.
+                    "cube" => Native::mul(&[args[0].clone(), args[0].clone(), args[0].clone()]),
+                    other => Err(LispError::UnboundSymbol {
+                        name: other.to_string(),
+                    }),
+                }
+            }
+            other => Ok(other.clone()),
+        }
+    }
+
+    fn coefficients(result: &LispAST) -> Vec<D512> {
+        let LispAST::List(items) = result else {
+            panic!("expected (+ poly O)");
+        };
+        let LispAST::Poly { coeffs, .. } = &items[1] else {
+            panic!("expected a polynomial");
+        };
+        coeffs.clone()
+    }
+
+    #[test]
+    fn derivatives_simplify() {
+        // d/dx (* 3 (^ x 2)) = (* 6 x)
+        let expr = call("*", vec![num(3), call("^", vec![sym("x"), num(2)])]);
+        assert_eq!(
+            differentiate(&expr, "x"),
+            Some(call("*", vec![num(6), sym("x")]))
+        );
+        assert_eq!(differentiate(&call("f", vec![sym("x")]), "x"), None);
+        assert_eq!(differentiate(&call("f", vec![sym("y")]), "x"), Some(num(0)));
+    }
+
+    #[test]
+    fn exp_series_about_zero() {
+        let expr = call("exp", vec![sym("x")]);
+        let result = Native::series(&expr, "x", &num(0), &num(3), &mut evaluate).unwrap();
+        let c = coefficients(&result);
+        assert_eq!(c[..3], [D512::ONE, D512::ONE, D512::ONE / D512::from(2)]);
+        assert_eq!(c[3], D512::ONE / D512::from(6));
+        let LispAST::List(items) = &result else {
+            unreachable!()
+        };
+        assert_eq!(items[2], call("O", vec![call("^", vec![sym("x"), num(4)])]));
+    }
+
+    #[test]
+    fn opaque_functions_use_finite_differences() {
+        // cube(x) about 1 is exactly x³ once expanded.
+        let expr = call("cube", vec![sym("x")]);
+        let result = Native::series(&expr, "x", &num(1), &num(3), &mut evaluate).unwrap();
+        assert_eq!(coefficients(&result), [0, 0, 0, 1].map(D512::from));
+    }
+}
@@ -0,0 +1,136 @@
+//! Self-checks for notes: `(assert cond)` and `(expect expr value tolerance)`.
+//! A failure is a [`LispError::AssertionFailed`], so a stale worked answer
+//! stops the build instead of rendering quietly.
+
+use fastnum::D512;
+use mazer_types::{LispAST, LispError};
+
+use crate::Native;
+
+/// The magnitude of `actual - expected`, in `expected`'s unit for quantities.
+fn difference(actual: &LispAST, expected: &LispAST) -> Result<D512, LispError> {
+    match (actual, expected) {
+        (LispAST::Number(a), LispAST::Number(e)) => Ok((*a - *e).abs()),
+        (LispAST::Quantity { .. }, LispAST::Quantity { value, unit }) => {
+            match Native::convert(actual, unit.clone())? {
+                LispAST::Quantity { value: a, .. } => Ok((a - *value).abs()),
+                _ => unreachable!("convert returns a quantity"),
+            }
+        }
+        _ => Err(LispError::TypeMismatch {
+            form: "expect".to_string(),
+            expected: "Number or Quantity when a tolerance is given".to_string(),
+            got: format!("{} and {}", actual.type_name(), expected.type_name()),
+        }),
+    }
+}
+
+impl Native {
+    /// `(assert cond)` or `(assert cond "message")`: `true`, or an assertion
+    /// failure. The expression is left blank here for the interpreter to fill
+    /// in from the call site, unless a message stands in for it.
+    pub fn assert(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let (cond, message) = match args {
+            [cond] => (cond, String::new()),
+            [cond, LispAST::String(message)] => (cond, message.clone()),
+            [_, other] => {
+                return Err(LispError::TypeMismatch {
+                    form: "assert".to_string(),
+                    expected: "String".to_string(),
+                    got: other.type_name().to_string(),
+                });
+            }
+            _ => {
+                return Err(LispError::Arity {
+                    form: "assert".to_string(),
+                    expected: "1 or 2".to_string(),
+                    got: args.len(),
+                });
+            }
+        };
+        match cond {
+            LispAST::Bool(true) => Ok(LispAST::Bool(true)),
+            LispAST::Bool(false) => Err(LispError::AssertionFailed {
+                expr: message,
+                expected: "true".to_string(),
+                actual: "false".to_string(),
+            }),
+            other => Err(LispError::TypeMismatch {
+                form: "assert".to_string(),
+                expected: "Bool".to_string(),
+                got: other.type_name().to_string(),
+            }),
+        }
+    }
+
+    /// `(expect expr value tolerance)` once the interpreter has evaluated the
+    /// parts: `actual` must equal `expected`, or be within `tolerance` of it
+    /// (in `expected`'s unit for quantities). `expr` is the source text.
+    pub fn expect(
+        expr: &LispAST,
+        actual: &LispAST,
+        expected: &LispAST,
+        tolerance: Option<&LispAST>,
+    ) -> Result<LispAST, LispError> {
+        let passed = match tolerance {
+            None => actual == expected,
+            Some(LispAST::Number(tol)) if !tol.is_negative() => {
+                difference(actual, expected)? <= *tol
+            }
+            Some(other) => {
+                return Err(LispError::TypeMismatch {
+                    form: "expect".to_string(),
+                    expected: "a non-negative Number tolerance".to_string(),
+                    got: other.type_name().to_string(),
+                });
+            }
+        };
+        if passed {
+            return Ok(LispAST::Bool(true));
+        }
+        let expected = match tolerance {
+            Some(tol) => format!("{expected} ± {tol}"),
+            None => expected.to_string(),
+        };
+        Err(LispError::AssertionFailed {
+            expr: expr.to_string(),
+            expected,
+            actual: actual.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn num(n: i64) -> LispAST {
+        LispAST::Number(D512::from(n))
+    }
+
+    #[test]
+    fn expect_with_and_without_tolerance() {
+        let expr = LispAST::Symbol("x".to_string());
+        assert_eq!(Native::expect(&expr, &num(4), &num(4), None), Ok(LispAST::Bool(true)));
+        assert!(Native::expect(&expr, &num(4), &num(5), Some(&num(1))).is_ok());
+        assert_eq!(
+            Native::expect(&expr, &num(3), &num(5), Some(&num(1))),
+            Err(LispError::AssertionFailed {
+                expr: "x".to_string(),
+                expected: "5 ± 1".to_string(),
+                actual: "3".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn assert_leaves_the_expression_to_the_caller() {
+        assert_eq!(Native::assert(&[LispAST::Bool(true)]), Ok(LispAST::Bool(true)));
+        let Err(LispError::AssertionFailed { expr, .. }) = Native::assert(&[LispAST::Bool(false)])
+        else {
+            panic!("expected an assertion failure");
+        };
+        assert!(expr.is_empty());
+        assert!(Native::assert(&[num(1)]).is_err());
+    }
+}
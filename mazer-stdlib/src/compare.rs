@@ -0,0 +1,90 @@
+//! Comparison natives. `=` and `!=` compare any values structurally (numbers
+//! by value, quantities by SI magnitude); the orderings take numbers, or
+//! quantities of one dimension. All chain: `(< a b c)` is a < b and b < c.
+
+use std::cmp::Ordering;
+
+use mazer_types::{LispAST, LispError};
+
+use crate::Native;
+
+fn at_least_two(form: &str, args: &[LispAST]) -> Result<(), LispError> {
+    if args.len() < 2 {
+        return Err(LispError::Arity {
+            form: form.to_string(),
+            expected: "at least 2".to_string(),
+            got: args.len(),
+        });
+    }
+    Ok(())
+}
+
+fn order(form: &str, a: &LispAST, b: &LispAST) -> Result<Ordering, LispError> {
+    match (a, b) {
+        (LispAST::Number(x), LispAST::Number(y)) => Ok(x.cmp(y)),
+        (LispAST::Quantity { unit: ua, .. }, LispAST::Quantity { unit: ub, .. })
+            if ua.dim == ub.dim =>
+        {
+            Ok(a.canonical_cmp(b))
+        }
+        (LispAST::Quantity { unit: ua, .. }, LispAST::Quantity { unit: ub, .. }) => {
+            Err(LispError::DimensionMismatch {
+                form: form.to_string(),
+                left: ua.dim.to_string(),
+                right: ub.dim.to_string(),
+            })
+        }
+        _ => Err(LispError::TypeMismatch {
+            form: form.to_string(),
+            expected: "Number or Quantity".to_string(),
+            got: if matches!(a, LispAST::Number(_) | LispAST::Quantity { .. }) {
+                b.type_name().to_string()
+            } else {
+                a.type_name().to_string()
+            },
+        }),
+    }
+}
+
+/// Whether every adjacent pair satisfies `holds`.
+fn chain(
+    form: &str,
+    args: &[LispAST],
+    holds: impl Fn(Ordering) -> bool,
+) -> Result<LispAST, LispError> {
+    at_least_two(form, args)?;
+    for pair in args.windows(2) {
+        if !holds(order(form, &pair[0], &pair[1])?) {
+            return Ok(LispAST::Bool(false));
+        }
+    }
+    Ok(LispAST::Bool(true))
+}
+
+impl Native {
+    pub fn eq(args: &[LispAST]) -> Result<LispAST, LispError> {
+        at_least_two("=", args)?;
+        Ok(LispAST::Bool(args.windows(2).all(|p| p[0] == p[1])))
+    }
+
+    /// `(!= a b)`: true when the two values differ.
+    pub fn ne(args: &[LispAST]) -> Result<LispAST, LispError> {
+        Ok(LispAST::Bool(args[0] != args[1]))
+    }
+
+    pub fn lt(args: &[LispAST]) -> Result<LispAST, LispError> {
+        chain("<", args, Ordering::is_lt)
+    }
+
+    pub fn gt(args: &[LispAST]) -> Result<LispAST, LispError> {
+        chain(">", args, Ordering::is_gt)
+    }
+
+    pub fn le(args: &[LispAST]) -> Result<LispAST, LispError> {
+        chain("<=", args, Ordering::is_le)
+    }
+
+    pub fn ge(args: &[LispAST]) -> Result<LispAST, LispError> {
+        chain(">=", args, Ordering::is_ge)
+    }
+}
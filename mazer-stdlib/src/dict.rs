@@ -0,0 +1,168 @@
+//! Dict natives. Dicts are immutable [`LispAST::Dict`] values with unique keys
+//! in canonical order; `assoc`, `dissoc` and `merge` return new dicts.
+//! Keys are usually keywords: `(dict :h 6.626e-34 :c 299792458)`.
+
+use mazer_types::{LispAST, LispError};
+
+use crate::Native;
+
+fn entries<'a>(form: &str, arg: &'a LispAST) -> Result<&'a [(LispAST, LispAST)], LispError> {
+    match arg {
+        LispAST::Dict(entries) => Ok(entries),
+        other => Err(LispError::TypeMismatch {
+            form: form.to_string(),
+            expected: "Dict".to_string(),
+            got: other.type_name().to_string(),
+        }),
+    }
+}
+
+/// Split `key value key value ...` into pairs.
+fn pairs(form: &str, args: &[LispAST]) -> Result<Vec<(LispAST, LispAST)>, LispError> {
+    if !args.len().is_multiple_of(2) {
+        return Err(LispError::Arity {
+            form: form.to_string(),
+            expected: "keys and values in pairs".to_string(),
+            got: args.len(),
+        });
+    }
+    Ok(args
+        .chunks(2)
+        .map(|kv| (kv[0].clone(), kv[1].clone()))
+        .collect())
+}
+
+/// Where `key` is, or would go, in entries kept in canonical order. Every
+/// native finds keys through this, so they agree on which keys are the same.
+fn position(entries: &[(LispAST, LispAST)], key: &LispAST) -> Result<usize, usize> {
+    entries.binary_search_by(|(k, _)| k.canonical_cmp(key))
+}
+
+fn lookup<'a>(entries: &'a [(LispAST, LispAST)], key: &LispAST) -> Option<&'a LispAST> {
+    position(entries, key).ok().map(|i| &entries[i].1)
+}
+
+impl Native {
+    /// `(dict k1 v1 k2 v2 ...)`: a dict of the pairs; a repeated key keeps its
+    /// last value.
+    pub fn dict(args: &[LispAST]) -> Result<LispAST, LispError> {
+        Ok(LispAST::dict_from(pairs("dict", args)?))
+    }
+
+    /// `(get d key)` or `(get d key default)`.
+    pub fn get(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let key = &args[1];
+        match (lookup(entries("get", &args[0])?, key), args.get(2)) {
+            (Some(value), _) | (None, Some(value)) => Ok(value.clone()),
+            (None, None) => Err(LispError::MissingKey {
+                form: "get".to_string(),
+                key: key.to_string(),
+            }),
+        }
+    }
+
+    /// `(assoc d k1 v1 ...)`: `d` with the pairs added or replaced.
+    pub fn assoc(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let existing = entries("assoc", &args[0])?.iter().cloned();
+        Ok(LispAST::dict_from(
+            existing.chain(pairs("assoc", &args[1..])?),
+        ))
+    }
+
+    /// `(dissoc d k1 k2 ...)`: `d` without the given keys.
+    pub fn dissoc(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let mut entries = entries("dissoc", &args[0])?.to_vec();
+        for key in &args[1..] {
+            if let Ok(i) = position(&entries, key) {
+                entries.remove(i);
+            }
+        }
+        Ok(LispAST::Dict(entries.into()))
+    }
+
+    /// `(keys d)`: the keys as a list, in order.
+    pub fn keys(args: &[LispAST]) -> Result<LispAST, LispError> {
+        Ok(LispAST::List(
+            entries("keys", &args[0])?
+                .iter()
+                .map(|(k, _)| k.clone())
+                .collect(),
+        ))
+    }
+
+    /// `(vals d)`: the values as a list, in key order.
+    pub fn vals(args: &[LispAST]) -> Result<LispAST, LispError> {
+        Ok(LispAST::List(
+            entries("vals", &args[0])?
+                .iter()
+                .map(|(_, v)| v.clone())
+                .collect(),
+        ))
+    }
+
+    /// `(merge d1 d2 ...)`: all the pairs; later dicts win on shared keys.
+    pub fn merge(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let mut all = Vec::new();
+        for dict in args {
+            all.extend_from_slice(entries("merge", dict)?);
+        }
+        Ok(LispAST::dict_from(all))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastnum::D512;
+
+    fn kw(k: &str) -> LispAST {
+        LispAST::Keyword(k.to_string())
+    }
+
+    fn num(n: i64) -> LispAST {
+        LispAST::Number(D512::from(n))
+    }
+
+    #[test]
+    fn later_keys_win() {
+        let d = Native::dict(&[kw("b"), num(1), kw("a"), num(2), kw("b"), num(3)]).unwrap();
+        assert_eq!(
+            Native::keys(std::slice::from_ref(&d)),
+            Ok(LispAST::List(vec![kw("a"), kw("b")].into()))
+        );
+        assert_eq!(Native::get(&[d.clone(), kw("b")]), Ok(num(3)));
+        let merged = Native::merge(&[d, Native::dict(&[kw("a"), num(9)]).unwrap()]).unwrap();
+        assert_eq!(
+            Native::vals(&[merged]),
+            Ok(LispAST::List(vec![num(9), num(3)].into()))
+        );
+    }
+
+    #[test]
+    fn missing_keys() {
+        let d = Native::dict(&[kw("a"), num(1)]).unwrap();
+        assert_eq!(Native::get(&[d.clone(), kw("z"), num(0)]), Ok(num(0)));
+        assert_eq!(
+            Native::get(&[d.clone(), kw("z")]),
+            Err(LispError::MissingKey {
+                form: "get".to_string(),
+                key: ":z".to_string(),
+            })
+        );
+        assert_eq!(Native::dissoc(&[d, kw("a")]), Ok(LispAST::Dict(Vec::new().into())));
+        assert!(Native::dict(&[kw("a")]).is_err());
+    }
+
+    #[test]
+    fn keys_match_the_same_way_everywhere() {
+        let qty = |value: i64, unit: &str| {
+            Native::quantity(&num(value), mazer_types::Unit::parse(unit).unwrap()).unwrap()
+        };
+        let d = Native::dict(&[qty(1, "km"), num(1), kw("a"), num(2)]).unwrap();
+        assert_eq!(Native::get(&[d.clone(), qty(1000, "m")]), Ok(num(1)));
+        assert_eq!(
+            Native::dissoc(&[d, qty(1000, "m")]),
+            Native::dict(&[kw("a"), num(2)])
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! Raising and inspecting errors from user code. `(error "msg" data)` raises;
+//! the interpreter's `try` form catches and binds an [`LispAST::ErrorValue`],
+//! which these accessors take apart.
+
+use mazer_types::{LispAST, LispError};
+
+use crate::Native;
+
+fn caught<'a>(
+    form: &str,
+    args: &'a [LispAST],
+) -> Result<(&'a str, &'a str, Option<&'a LispAST>), LispError> {
+    match args {
+        [
+            LispAST::ErrorValue {
+                code,
+                message,
+                data,
+            },
+        ] => Ok((code, message, data.as_deref())),
+        [other] => Err(LispError::TypeMismatch {
+            form: form.to_string(),
+            expected: "Error".to_string(),
+            got: other.type_name().to_string(),
+        }),
+        _ => Err(LispError::Arity {
+            form: form.to_string(),
+            expected: "1".to_string(),
+            got: args.len(),
+        }),
+    }
+}
+
+impl Native {
+    /// `(error "message")` or `(error "message" data)` raises; `(error e)`
+    /// re-raises a caught error.
+    pub fn raise(args: &[LispAST]) -> Result<LispAST, LispError> {
+        match args {
+            [LispAST::String(message)] => Err(LispError::Raised {
+                message: message.clone(),
+                data: None,
+            }),
+            [LispAST::String(message), data] => Err(LispError::Raised {
+                message: message.clone(),
+                data: Some(Box::new(data.clone())),
+            }),
+            [LispAST::ErrorValue { message, data, .. }] => Err(LispError::Raised {
+                message: message.clone(),
+                data: data.clone(),
+            }),
+            [other, ..] if args.len() <= 2 => Err(LispError::TypeMismatch {
+                form: "error".to_string(),
+                expected: "String".to_string(),
+                got: other.type_name().to_string(),
+            }),
+            _ => Err(LispError::Arity {
+                form: "error".to_string(),
+                expected: "1 or 2".to_string(),
+                got: args.len(),
+            }),
+        }
+    }
+
+    /// `(error-message e)`: the message, as it would have been reported.
+    pub fn error_message(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let (_, message, _) = caught("error-message", args)?;
+        Ok(LispAST::String(message.to_string()))
+    }
+
+    /// `(error-kind e)`: the diagnostic code, e.g. `"mazer::lisp::division_by_zero"`,
+    /// or `"mazer::lisp::raised"` for errors raised with `error`.
+    pub fn error_kind(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let (code, _, _) = caught("error-kind", args)?;
+        Ok(LispAST::String(code.to_string()))
+    }
+
+    /// `(error-data e)`: the data given to `error`, or an empty list.
+    pub fn error_data(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let (_, _, data) = caught("error-data", args)?;
+        Ok(data.cloned().unwrap_or_else(|| LispAST::List(Vec::new().into())))
+    }
+
+    /// `(error? x)`: whether `x` is a caught error.
+    pub fn is_error(args: &[LispAST]) -> Result<LispAST, LispError> {
+        Ok(LispAST::Bool(matches!(args[0], LispAST::ErrorValue { .. })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raised_errors_round_trip_through_values() {
+        let raised = Native::raise(&[
+            LispAST::String("negative input".to_string()),
+            LispAST::Bool(false),
+        ])
+        .unwrap_err();
+        let value = raised.into_value();
+        assert_eq!(
+            Native::error_kind(std::slice::from_ref(&value)),
+            Ok(LispAST::String("mazer::lisp::raised".to_string()))
+        );
+        assert_eq!(
+            Native::error_data(std::slice::from_ref(&value)),
+            Ok(LispAST::Bool(false))
+        );
+
+        let builtin = LispError::DivisionByZero.into_value();
+        assert_eq!(
+            Native::error_kind(&[builtin]),
+            Ok(LispAST::String("mazer::lisp::division_by_zero".to_string()))
+        );
+    }
+}
@@ -1,11 +1,25 @@
-use std::{
-    collections::HashMap,
-    io::{Write, stderr},
-};
+use std::collections::HashMap;
 
 use fastnum::D512;
+use mazer_types::pretty::DEFAULT_WIDTH;
 use mazer_types::{LispAST, LispError};
 
+mod analysis;
+mod check;
+mod compare;
+mod dict;
+mod errors;
+mod logic;
+mod number_theory;
+pub mod output;
+mod poly;
+mod quantity;
+mod sets;
+mod solve;
+mod strings;
+
+pub use analysis::{Evaluator, differentiate};
+
 // prlude functions are functions that are valid lisp code that is parsed
 // and added to the environment at startup
 pub struct Prelude;
@@ -15,8 +29,6 @@ impl Prelude {
         let mut prelude = HashMap::new();
 
         prelude.insert("not".into(), Self::not());
-        prelude.insert("or".into(), Self::or());
-        prelude.insert("and".into(), Self::and());
         prelude.insert("xor".into(), Self::xor());
 
         prelude
@@ -32,26 +44,6 @@ impl Prelude {
         "#
     }
 
-    #[inline]
-    fn or() -> &'static str {
-        r#"(defunc or (a b)
-                (if a
-                    true
-                    b)
-            )
-        "#
-    }
-
-    #[inline]
-    fn and() -> &'static str {
-        r#"(defunc and (a b)
-                (if a
-                    b
-                    false)
-            )
-        "#
-    }
-
     #[inline]
     fn xor() -> &'static str {
         r#"(defunc xor (a b)
@@ -83,51 +75,47 @@ pub struct Native;
 impl Native {
     // type infer runtime
     pub fn reflect(args: &[LispAST]) -> Result<LispAST, LispError> {
-        if args.len() != 1 {
-            return Err(LispError::Arity {
-                form: "reflect".to_string(),
-                expected: "1".to_string(),
-                got: args.len(),
-            });
-        }
-
         Ok(LispAST::Symbol(args[0].type_name().to_string()))
     }
 
-    pub fn print(args: &[LispAST]) -> Result<LispAST, LispError> {
-        for arg in args {
-            match arg {
-                LispAST::Number(n) => eprint!("{}", n),
-                LispAST::Bool(b) => eprint!("{}", b),
-                LispAST::String(s) => eprint!("{}", s),
-                LispAST::Symbol(s) => eprint!("{}", s),
-                LispAST::List(_) => eprint!("{:?}", arg),
-                _ => eprint!("{:?}", arg),
-            }
-        }
-        eprintln!();
-
-        stderr().flush().ok();
+    /// `(vec a b c)`, also read from `[a b c]`: the elements as a list headed
+    /// by `vec`, which renders as a column vector.
+    pub fn vector(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let mut items = vec![LispAST::Symbol("vec".to_string())];
+        items.extend_from_slice(args);
+        Ok(LispAST::List(items.into()))
+    }
 
+    /// `(print a b ...)`: one line of output, each argument as `concat` would
+    /// splice it in and symbols as they are. See [`output`].
+    pub fn print(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let mut line: String = args
+            .iter()
+            .map(|arg| match arg {
+                LispAST::Symbol(s) => s.clone(),
+                other => strings::text(other),
+            })
+            .collect();
+        line.push('\n');
+        output::write(&line);
         Ok(LispAST::Bool(true))
     }
 
+    /// `(debug a b ...)`: each argument as source, laid out to
+    /// [`DEFAULT_WIDTH`], with its type.
     pub fn debug(args: &[LispAST]) -> Result<LispAST, LispError> {
         for arg in args {
-            eprintln!("{:?}", arg);
+            output::write(&format!("{} ; {}\n", arg.pretty(DEFAULT_WIDTH), arg.type_name()));
         }
-        stderr().flush().ok();
-
         Ok(LispAST::Bool(true))
     }
 
     pub fn add(args: &[LispAST]) -> Result<LispAST, LispError> {
-        if args.is_empty() {
-            return Err(LispError::Arity {
-                form: "add".to_string(),
-                expected: "at least 1".to_string(),
-                got: 0,
-            });
+        if quantity::involved(args) {
+            return quantity::sum("add", args, false);
+        }
+        if poly::involved(args) {
+            return poly::sum("add", args, false);
         }
         require_all_numbers("add", args)?;
 
@@ -143,12 +131,11 @@ impl Native {
     }
 
     pub fn sub(args: &[LispAST]) -> Result<LispAST, LispError> {
-        if args.is_empty() {
-            return Err(LispError::Arity {
-                form: "sub".to_string(),
-                expected: "at least 1".to_string(),
-                got: 0,
-            });
+        if quantity::involved(args) {
+            return quantity::sum("sub", args, true);
+        }
+        if poly::involved(args) {
+            return poly::sum("sub", args, true);
         }
         require_all_numbers("sub", args)?;
 
@@ -182,12 +169,11 @@ impl Native {
     }
 
     pub fn mul(args: &[LispAST]) -> Result<LispAST, LispError> {
-        if args.is_empty() {
-            return Err(LispError::Arity {
-                form: "mul".to_string(),
-                expected: "at least 1".to_string(),
-                got: 0,
-            });
+        if quantity::involved(args) {
+            return quantity::product("mul", args, false);
+        }
+        if poly::involved(args) {
+            return poly::product("mul", args);
         }
         require_all_numbers("mul", args)?;
 
@@ -203,12 +189,11 @@ impl Native {
     }
 
     pub fn div(args: &[LispAST]) -> Result<LispAST, LispError> {
-        if args.is_empty() {
-            return Err(LispError::Arity {
-                form: "div".to_string(),
-                expected: "at least 1".to_string(),
-                got: 0,
-            });
+        if quantity::involved(args) {
+            return quantity::product("div", args, true);
+        }
+        if poly::involved(args) {
+            return poly::quotient("div", args);
         }
         require_all_numbers("div", args)?;
 
@@ -246,4 +231,36 @@ impl Native {
 
         Ok(LispAST::Number(result))
     }
+
+    pub fn pow(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let LispAST::Number(exponent) = args[1] else {
+            return Err(LispError::TypeMismatch {
+                form: "pow".to_string(),
+                expected: "Number".to_string(),
+                got: args[1].type_name().to_string(),
+            });
+        };
+        let base = match &args[0] {
+            LispAST::Number(n) => *n,
+            quantity @ LispAST::Quantity { .. } => return quantity::power(quantity, exponent),
+            poly @ LispAST::Poly { .. } => return poly::power(poly, exponent),
+            other => {
+                return Err(LispError::TypeMismatch {
+                    form: "pow".to_string(),
+                    expected: "Number".to_string(),
+                    got: other.type_name().to_string(),
+                });
+            }
+        };
+
+        if base.is_zero() && exponent.is_negative() {
+            return Err(LispError::DivisionByZero);
+        }
+        // Whole exponents stay exact; anything else goes through exp/ln.
+        let result = match exponent.to_i32() {
+            Ok(n) if exponent.is_integral() => base.powi(n),
+            _ => base.pow(exponent),
+        };
+        Ok(LispAST::Number(result))
+    }
 }
@@ -0,0 +1,185 @@
+//! Propositional-logic natives: `implies`, `iff` and `truth-table`.
+//!
+//! `and`/`or` are short-circuiting special forms in the interpreter; the
+//! connectives here take already-evaluated `Bool`s.
+
+use mazer_types::{LispAST, LispError};
+
+use crate::Native;
+
+/// Rows double with every variable; past this a table is unreadable anyway.
+const MAX_TABLE_VARS: usize = 10;
+
+fn boolean(form: &str, arg: &LispAST) -> Result<bool, LispError> {
+    match arg {
+        LispAST::Bool(b) => Ok(*b),
+        other => Err(LispError::TypeMismatch {
+            form: form.to_string(),
+            expected: "Bool".to_string(),
+            got: other.type_name().to_string(),
+        }),
+    }
+}
+
+fn two_bools(form: &str, args: &[LispAST]) -> Result<(bool, bool), LispError> {
+    Ok((boolean(form, &args[0])?, boolean(form, &args[1])?))
+}
+
+/// Collect the propositional variables of `expr` (every symbol outside operator
+/// position), in order of first appearance.
+fn variables(expr: &LispAST, out: &mut Vec<String>) {
+    match expr {
+        LispAST::Symbol(s) if !out.contains(s) => out.push(s.clone()),
+        LispAST::List(items) => items.iter().skip(1).for_each(|e| variables(e, out)),
+        _ => {}
+    }
+}
+
+/// Evaluate a propositional formula under `assignment` (parallel to `vars`).
+fn truth(expr: &LispAST, vars: &[String], assignment: &[bool]) -> Result<bool, LispError> {
+    match expr {
+        LispAST::Bool(b) => Ok(*b),
+        LispAST::Symbol(s) => Ok(vars
+            .iter()
+            .position(|v| v == s)
+            .map(|i| assignment[i])
+            .unwrap_or_default()),
+        LispAST::List(items) if !items.is_empty() => {
+            let args = items[1..]
+                .iter()
+                .map(|e| truth(e, vars, assignment))
+                .collect::<Result<Vec<_>, _>>()?;
+            let op = match &items[0] {
+                LispAST::Symbol(op) => op.as_str(),
+                other => {
+                    return Err(LispError::TypeMismatch {
+                        form: "truth-table".to_string(),
+                        expected: "a connective".to_string(),
+                        got: other.type_name().to_string(),
+                    });
+                }
+            };
+            let binary = |f: fn(bool, bool) -> bool| match args[..] {
+                [a, b] => Ok(f(a, b)),
+                _ => Err(LispError::Arity {
+                    form: op.to_string(),
+                    expected: "2".to_string(),
+                    got: args.len(),
+                }),
+            };
+            match op {
+                "and" => Ok(args.iter().all(|&b| b)),
+                "or" => Ok(args.iter().any(|&b| b)),
+                "not" => match args[..] {
+                    [a] => Ok(!a),
+                    _ => Err(LispError::Arity {
+                        form: "not".to_string(),
+                        expected: "1".to_string(),
+                        got: args.len(),
+                    }),
+                },
+                "implies" => binary(|a, b| !a || b),
+                "iff" => binary(|a, b| a == b),
+                "xor" => binary(|a, b| a != b),
+                _ => Err(LispError::TypeMismatch {
+                    form: "truth-table".to_string(),
+                    expected: "one of and, or, not, implies, iff, xor".to_string(),
+                    got: format!("'{op}'"),
+                }),
+            }
+        }
+        other => Err(LispError::TypeMismatch {
+            form: "truth-table".to_string(),
+            expected: "a propositional formula".to_string(),
+            got: other.type_name().to_string(),
+        }),
+    }
+}
+
+impl Native {
+    /// `(implies a b)`: material implication, `¬a ∨ b`.
+    pub fn implies(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let (a, b) = two_bools("implies", args)?;
+        Ok(LispAST::Bool(!a || b))
+    }
+
+    /// `(iff a b)`: true when both sides agree.
+    pub fn iff(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let (a, b) = two_bools("iff", args)?;
+        Ok(LispAST::Bool(a == b))
+    }
+
+    /// `(truth-table '(implies p q))`: enumerate every assignment of the formula's
+    /// variables and return a `(table (header p q formula) (row ...) ...)` list,
+    /// which the renderer draws as a table. Rows start from all-true.
+    pub fn truth_table(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let formula = &args[0];
+
+        let mut vars = Vec::new();
+        variables(formula, &mut vars);
+        if vars.len() > MAX_TABLE_VARS {
+            return Err(LispError::Domain {
+                form: "truth-table".to_string(),
+                reason: format!(
+                    "{} variables is too many (at most {MAX_TABLE_VARS})",
+                    vars.len()
+                ),
+            });
+        }
+
+        let mut header = vec![LispAST::Symbol("header".to_string())];
+        header.extend(vars.iter().cloned().map(LispAST::Symbol));
+        header.push(formula.clone());
+
+        let mut table = vec![LispAST::Symbol("table".to_string()), LispAST::List(header.into())];
+        for i in 0..1usize << vars.len() {
+            // Bit set means false, so counting up walks TT.. → FF..
+            let assignment: Vec<bool> = (0..vars.len())
+                .map(|v| i >> (vars.len() - 1 - v) & 1 == 0)
+                .collect();
+            let value = truth(formula, &vars, &assignment)?;
+
+            let mut row = vec![LispAST::Symbol("row".to_string())];
+            row.extend(assignment.into_iter().map(LispAST::Bool));
+            row.push(LispAST::Bool(value));
+            table.push(LispAST::List(row.into()));
+        }
+        Ok(LispAST::List(table.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sym(s: &str) -> LispAST {
+        LispAST::Symbol(s.to_string())
+    }
+
+    #[test]
+    fn truth_table_of_implication() {
+        let formula = LispAST::List(vec![sym("implies"), sym("p"), sym("q")].into());
+        let LispAST::List(table) = Native::truth_table(&[formula]).unwrap() else {
+            panic!("expected a list");
+        };
+        assert_eq!(table.len(), 2 + 4);
+        let last_column: Vec<_> = table[2..]
+            .iter()
+            .map(|row| match row {
+                LispAST::List(cells) => cells.last().cloned().unwrap(),
+                _ => unreachable!(),
+            })
+            .collect();
+        let expected = [true, false, true, true].map(LispAST::Bool);
+        assert_eq!(last_column, expected);
+    }
+
+    #[test]
+    fn truth_table_rejects_unknown_connective() {
+        let formula = LispAST::List(vec![sym("nand"), sym("p"), sym("q")].into());
+        assert!(matches!(
+            Native::truth_table(&[formula]),
+            Err(LispError::TypeMismatch { .. })
+        ));
+    }
+}
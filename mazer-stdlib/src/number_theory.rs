@@ -0,0 +1,424 @@
+//! Integer natives for discrete-math notes: division with remainder, gcd/lcm,
+//! primality, factorisation and modular arithmetic.
+//!
+//! Every argument must be an integral `D512` that fits in an `i128`; anything
+//! else is rejected with [`LispError::NotAnInteger`] rather than silently
+//! truncated.
+
+use fastnum::D512;
+use mazer_types::{LispAST, LispError};
+
+use crate::Native;
+
+/// Bases for Miller–Rabin. Deterministic for every `n < 3.3 × 10²⁴`, and a
+/// vanishingly small error probability beyond that.
+const WITNESSES: [u128; 13] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41];
+
+/// Extract an `i128` from a `Number`, rejecting fractions and huge values.
+fn integer(form: &str, arg: &LispAST) -> Result<i128, LispError> {
+    let LispAST::Number(n) = arg else {
+        return Err(LispError::TypeMismatch {
+            form: form.to_string(),
+            expected: "Number".to_string(),
+            got: arg.type_name().to_string(),
+        });
+    };
+
+    let not_integer = |expected: &str| LispError::NotAnInteger {
+        form: form.to_string(),
+        expected: expected.to_string(),
+        value: n.to_string(),
+    };
+
+    if !n.is_integral() {
+        return Err(not_integer("an integer"));
+    }
+    n.to_i128()
+        .map_err(|_| not_integer("an integer of at most 38 digits"))
+}
+
+/// Like [`integer`], but additionally requires `n ≥ min`.
+fn integer_at_least(
+    form: &str,
+    arg: &LispAST,
+    min: i128,
+    expected: &str,
+) -> Result<i128, LispError> {
+    let n = integer(form, arg)?;
+    if n < min {
+        return Err(LispError::NotAnInteger {
+            form: form.to_string(),
+            expected: expected.to_string(),
+            value: n.to_string(),
+        });
+    }
+    Ok(n)
+}
+
+fn number(n: i128) -> LispAST {
+    LispAST::Number(D512::try_from(n).expect("every i128 fits in a D512"))
+}
+
+fn gcd(mut a: u128, mut b: u128) -> u128 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+/// `a · b mod m` without overflowing: small operands stay in `u128`, large
+/// ones go through `D512`, which holds the full 256-bit product exactly.
+fn mul_mod(a: u128, b: u128, m: u128) -> u128 {
+    if a <= u128::from(u64::MAX) && b <= u128::from(u64::MAX) {
+        return (a * b) % m;
+    }
+    let wide = |x: u128| D512::try_from(x).expect("every u128 fits in a D512");
+    let r = (wide(a) * wide(b)) % wide(m);
+    r.to_u128()
+        .expect("a remainder modulo a u128 fits in a u128")
+}
+
+fn pow_mod(mut base: u128, mut exp: u128, m: u128) -> u128 {
+    if m == 1 {
+        return 0;
+    }
+    let mut acc = 1;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            acc = mul_mod(acc, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    acc
+}
+
+fn is_prime(n: u128) -> bool {
+    if n < 2 {
+        return false;
+    }
+    for p in WITNESSES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+
+    let s = (n - 1).trailing_zeros();
+    let d = (n - 1) >> s;
+    'witness: for a in WITNESSES {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// Find a non-trivial factor of the odd composite `n` (Pollard's rho, Brent's
+/// variant). Retries with a new constant if a cycle collapses to `n`.
+fn pollard_rho(n: u128) -> u128 {
+    let mut c = 1;
+    loop {
+        let f = |x: u128| (mul_mod(x, x, n) + c) % n;
+        let (mut x, mut y, mut d) = (2, 2, 1);
+        while d == 1 {
+            x = f(x);
+            y = f(f(y));
+            d = gcd(x.abs_diff(y), n);
+        }
+        if d != n {
+            return d;
+        }
+        c += 1;
+    }
+}
+
+/// Prime factorisation as ascending `(prime, multiplicity)` pairs.
+fn factor(mut n: u128) -> Vec<(u128, u32)> {
+    let mut primes = Vec::new();
+
+    for p in [2, 3, 5] {
+        while n.is_multiple_of(p) {
+            primes.push(p);
+            n /= p;
+        }
+    }
+
+    let mut stack = vec![n];
+    while let Some(m) = stack.pop() {
+        if m == 1 {
+            continue;
+        }
+        if is_prime(m) {
+            primes.push(m);
+            continue;
+        }
+        let d = pollard_rho(m);
+        stack.push(d);
+        stack.push(m / d);
+    }
+
+    primes.sort_unstable();
+    let mut pairs: Vec<(u128, u32)> = Vec::new();
+    for p in primes {
+        match pairs.last_mut() {
+            Some((q, k)) if *q == p => *k += 1,
+            _ => pairs.push((p, 1)),
+        }
+    }
+    pairs
+}
+
+/// Extended Euclid: returns `(g, x)` with `a·x ≡ g (mod m)`.
+fn ext_gcd(a: i128, m: i128) -> (i128, i128) {
+    let (mut old_r, mut r) = (a, m);
+    let (mut old_s, mut s) = (1_i128, 0_i128);
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_s, s) = (s, old_s - q * s);
+    }
+    (old_r, old_s)
+}
+
+/// `a⁻¹ mod m` for `m > 0`, or `None` when `gcd(a, m) ≠ 1`.
+fn mod_inverse(a: i128, m: i128) -> Option<i128> {
+    let (g, x) = ext_gcd(a.rem_euclid(m), m);
+    (g == 1).then(|| x.rem_euclid(m))
+}
+
+impl Native {
+    /// `(mod a n)`: the remainder of floored division, taking the sign of `n`.
+    pub fn modulo(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let a = integer("mod", &args[0])?;
+        let n = integer("mod", &args[1])?;
+        if n == 0 {
+            return Err(LispError::DivisionByZero);
+        }
+        let mut r = a.wrapping_rem(n);
+        if r != 0 && (r < 0) != (n < 0) {
+            r += n;
+        }
+        Ok(number(r))
+    }
+
+    /// `(rem a n)`: the remainder of truncated division, taking the sign of `a`.
+    pub fn rem(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let a = integer("rem", &args[0])?;
+        let n = integer("rem", &args[1])?;
+        if n == 0 {
+            return Err(LispError::DivisionByZero);
+        }
+        Ok(number(a.wrapping_rem(n)))
+    }
+
+    /// `(quotient a n)`: truncated integer division.
+    pub fn quotient(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let a = integer("quotient", &args[0])?;
+        let n = integer("quotient", &args[1])?;
+        if n == 0 {
+            return Err(LispError::DivisionByZero);
+        }
+        // Only i128::MIN / -1 overflows; its quotient still fits a D512.
+        Ok(a.checked_div(n).map_or_else(
+            || LispAST::Number(-D512::try_from(a).expect("every i128 fits in a D512")),
+            number,
+        ))
+    }
+
+    pub fn gcd(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let mut g = 0;
+        for a in args {
+            g = gcd(g, integer("gcd", a)?.unsigned_abs());
+        }
+        Ok(LispAST::Number(
+            D512::try_from(g).expect("every u128 fits in a D512"),
+        ))
+    }
+
+    /// `(lcm a b ...)`. Computed in `D512`, so the result may exceed `i128`.
+    pub fn lcm(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let mut acc = D512::from(1);
+        for a in args {
+            let n = integer("lcm", a)?.unsigned_abs();
+            if n == 0 {
+                return Ok(LispAST::Number(D512::from(0)));
+            }
+            let wide = |x: u128| D512::try_from(x).expect("every u128 fits in a D512");
+            // gcd(acc, n) only needs n's range: reduce acc modulo n first.
+            let acc_mod_n = (acc % wide(n))
+                .to_u128()
+                .expect("a remainder modulo a u128 fits in a u128");
+            let g = gcd(n, acc_mod_n);
+            acc *= wide(n / g);
+        }
+        Ok(LispAST::Number(acc))
+    }
+
+    /// `(prime? n)`: Miller–Rabin primality test.
+    pub fn is_prime(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let n = integer("prime?", &args[0])?;
+        Ok(LispAST::Bool(n > 1 && is_prime(n.unsigned_abs())))
+    }
+
+    /// `(next-prime n)`: the smallest prime strictly greater than `n`.
+    pub fn next_prime(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let n = integer("next-prime", &args[0])?;
+        if n < 2 {
+            return Ok(number(2));
+        }
+        let overflow = || LispError::Domain {
+            form: "next-prime".to_string(),
+            reason: format!("there is no prime above {n} within 38 digits"),
+        };
+        let mut candidate = n.checked_add(1).ok_or_else(overflow)?;
+        while !is_prime(candidate.unsigned_abs()) {
+            candidate = candidate.checked_add(1).ok_or_else(overflow)?;
+        }
+        Ok(number(candidate))
+    }
+
+    /// `(factorize n)`: the prime factorisation of `n ≥ 1` as a list of
+    /// `(p k)` pairs in ascending order of `p`, e.g. `360 → ((2 3) (3 2) (5 1))`.
+    /// Render it with `(factors ...)` to get `2³ × 3² × 5`.
+    pub fn factorize(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let n = integer_at_least("factorize", &args[0], 1, "a positive integer")?;
+
+        let pairs = factor(n.unsigned_abs())
+            .into_iter()
+            .map(|(p, k)| {
+                LispAST::List(vec![
+                    LispAST::Number(D512::try_from(p).expect("every u128 fits in a D512")),
+                    LispAST::Number(D512::from(k)),
+                ].into())
+            })
+            .collect();
+        Ok(LispAST::List(pairs))
+    }
+
+    /// `(powmod b e m)`: `bᵉ mod m`. A negative exponent raises the modular
+    /// inverse of `b` instead.
+    pub fn powmod(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let b = integer("powmod", &args[0])?;
+        let e = integer("powmod", &args[1])?;
+        let m = integer("powmod", &args[2])?;
+        if m == 0 {
+            return Err(LispError::DivisionByZero);
+        }
+        if m < 0 {
+            return Err(LispError::NotAnInteger {
+                form: "powmod".to_string(),
+                expected: "a positive modulus".to_string(),
+                value: m.to_string(),
+            });
+        }
+
+        let base = if e < 0 {
+            mod_inverse(b, m).ok_or_else(|| LispError::NoModularInverse {
+                value: b.to_string(),
+                modulus: m.to_string(),
+            })?
+        } else {
+            b.rem_euclid(m)
+        };
+        let r = pow_mod(base.unsigned_abs(), e.unsigned_abs(), m.unsigned_abs());
+        Ok(LispAST::Number(
+            D512::try_from(r).expect("every u128 fits in a D512"),
+        ))
+    }
+
+    /// `(modinv a m)`: the `x` in `[0, m)` with `a·x ≡ 1 (mod m)`.
+    pub fn modinv(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let a = integer("modinv", &args[0])?;
+        let m = integer_at_least("modinv", &args[1], 1, "a positive modulus")?;
+        mod_inverse(a, m)
+            .map(number)
+            .ok_or_else(|| LispError::NoModularInverse {
+                value: a.to_string(),
+                modulus: m.to_string(),
+            })
+    }
+
+    /// `(totient n)`: Euler's φ, the count of `1 ≤ k ≤ n` coprime to `n`.
+    pub fn totient(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let n = integer_at_least("totient", &args[0], 1, "a positive integer")?;
+
+        let mut phi = n.unsigned_abs();
+        for (p, _) in factor(phi) {
+            phi = phi / p * (p - 1);
+        }
+        Ok(LispAST::Number(
+            D512::try_from(phi).expect("every u128 fits in a D512"),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn num(n: i64) -> LispAST {
+        LispAST::Number(D512::from(n))
+    }
+
+    #[test]
+    fn mod_follows_divisor_and_rem_follows_dividend() {
+        assert!(
+            matches!(Native::modulo(&[num(-7), num(3)]), Ok(LispAST::Number(n)) if n == D512::from(2))
+        );
+        assert!(
+            matches!(Native::rem(&[num(-7), num(3)]), Ok(LispAST::Number(n)) if n == D512::from(-1))
+        );
+    }
+
+    #[test]
+    fn factorisation_is_sorted_with_multiplicities() {
+        assert_eq!(factor(360), vec![(2, 3), (3, 2), (5, 1)]);
+        assert_eq!(
+            factor(600_851_475_143),
+            vec![(71, 1), (839, 1), (1471, 1), (6857, 1)]
+        );
+        assert!(factor(1).is_empty());
+    }
+
+    #[test]
+    fn primality_handles_carmichael_and_large_primes() {
+        assert!(!is_prime(561));
+        assert!(is_prime(1_000_000_007));
+        assert!(is_prime((1 << 127) - 1));
+    }
+
+    #[test]
+    fn modular_inverse_exists_only_for_coprime_values() {
+        assert_eq!(mod_inverse(3, 7), Some(5));
+        assert_eq!(mod_inverse(2, 4), None);
+        assert_eq!(pow_mod(2, 100, 1_000_000_007), 976_371_285);
+    }
+
+    #[test]
+    fn fractions_are_rejected_with_a_typed_error() {
+        let half = LispAST::Number(D512::from(1) / D512::from(2));
+        assert!(matches!(
+            Native::gcd(&[half, num(3)]),
+            Err(LispError::NotAnInteger { .. })
+        ));
+    }
+
+    #[test]
+    fn next_prime_stops_at_the_largest_integer() {
+        // 2¹²⁷ − 1, the largest i128, is a Mersenne prime.
+        assert_eq!(Native::next_prime(&[number(i128::MAX - 1)]), Ok(number(i128::MAX)));
+        assert!(matches!(
+            Native::next_prime(&[number(i128::MAX)]),
+            Err(LispError::Domain { form, .. }) if form == "next-prime"
+        ));
+    }
+}
@@ -0,0 +1,69 @@
+//! Where `print` and `debug` write. An interpreter evaluates inside
+//! [`capture`], which collects what the natives write so it can be shown
+//! with the block that printed it; outside of one, output goes to stderr.
+
+use std::cell::RefCell;
+
+struct Sink {
+    text: String,
+    echo: bool,
+}
+
+thread_local! {
+    static SINK: RefCell<Option<Sink>> = const { RefCell::new(None) };
+}
+
+/// Run `f`, returning what `print` and `debug` wrote meanwhile alongside its
+/// result; with `echo` it is written to stderr as well, as it happens. A
+/// capture opened while another is running leaves the output to the outer
+/// one, so it stays in order, and returns nothing.
+pub fn capture<R>(echo: bool, f: impl FnOnce() -> R) -> (R, String) {
+    let opened = SINK.with_borrow_mut(|sink| {
+        sink.is_none()
+            && sink
+                .replace(Sink {
+                    text: String::new(),
+                    echo,
+                })
+                .is_none()
+    });
+    let result = f();
+    let text = if opened {
+        SINK.with_borrow_mut(Option::take)
+            .map(|sink| sink.text)
+            .unwrap_or_default()
+    } else {
+        String::new()
+    };
+    (result, text)
+}
+
+/// Send `text` to the open capture, or to stderr.
+pub(crate) fn write(text: &str) {
+    SINK.with_borrow_mut(|sink| match sink {
+        Some(sink) => {
+            if sink.echo {
+                eprint!("{text}");
+            }
+            sink.text.push_str(text);
+        }
+        None => eprint!("{text}"),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_captures_keep_output_in_order() {
+        let ((), outer) = capture(false, || {
+            write("a\n");
+            let ((), inner) = capture(false, || write("b\n"));
+            assert_eq!(inner, "");
+            write("c\n");
+        });
+        assert_eq!(outer, "a\nb\nc\n");
+        assert_eq!(capture(false, || ()).1, "");
+    }
+}
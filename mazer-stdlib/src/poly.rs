@@ -0,0 +1,501 @@
+//! Polynomials in one variable: `(poly 1 -3 2)` is x² − 3x + 2.
+//!
+//! Values are [`LispAST::Poly`] with coefficients in ascending order and no
+//! trailing zeros. `+`, `-` and `*` accept polynomials mixed with numbers;
+//! division, gcd, derivative, evaluation and roots are `poly-*` natives.
+
+use fastnum::{D512, decimal::Context};
+use mazer_types::{LispAST, LispError};
+
+use crate::Native;
+
+/// Durand–Kerner iteration cap; convergence is usually reached in well under 100.
+const MAX_ITERATIONS: usize = 1000;
+
+/// Decimal places kept for polished real roots, and for complex parts (which
+/// are only as precise as the `f64` iteration that found them).
+const REAL_DIGITS: i16 = 30;
+const COMPLEX_DIGITS: i16 = 12;
+
+pub(crate) fn involved(args: &[LispAST]) -> bool {
+    args.iter().any(|a| matches!(a, LispAST::Poly { .. }))
+}
+
+pub(crate) fn trim(mut coeffs: Vec<D512>) -> Vec<D512> {
+    while coeffs.last().is_some_and(D512::is_zero) {
+        coeffs.pop();
+    }
+    coeffs
+}
+
+/// Like [`trim`], but also drops leading coefficients that are rounding noise
+/// relative to `scale` (the largest coefficient of the dividend). Division and
+/// gcd cannot be exact in decimal (dividing by 3 never terminates), so their
+/// remainders need this.
+fn trim_approx(mut coeffs: Vec<D512>, scale: D512) -> Vec<D512> {
+    let noise = scale * decimal("1e-100");
+    while coeffs.last().is_some_and(|c| c.abs() <= noise) {
+        coeffs.pop();
+    }
+    coeffs
+}
+
+fn largest(coeffs: &[D512]) -> D512 {
+    coeffs.iter().map(|c| c.abs()).max().unwrap_or_default()
+}
+
+fn decimal(text: &str) -> D512 {
+    D512::from_str(text, Context::default()).unwrap_or_default()
+}
+
+pub(crate) fn value(var: &str, coeffs: Vec<D512>) -> LispAST {
+    LispAST::Poly {
+        var: var.to_string(),
+        coeffs: trim(coeffs),
+    }
+}
+
+/// The coefficients of an operand, checking that every polynomial in one call
+/// shares a variable. Numbers are constant polynomials.
+fn coefficients(
+    form: &str,
+    arg: &LispAST,
+    var: &mut Option<String>,
+) -> Result<Vec<D512>, LispError> {
+    match arg {
+        LispAST::Number(n) => Ok(trim(vec![*n])),
+        LispAST::Poly { var: v, coeffs } => {
+            match var {
+                Some(existing) if existing != v => {
+                    return Err(LispError::TypeMismatch {
+                        form: form.to_string(),
+                        expected: format!("a polynomial in {existing}"),
+                        got: format!("a polynomial in {v}"),
+                    });
+                }
+                _ => *var = Some(v.clone()),
+            }
+            Ok(coeffs.clone())
+        }
+        other => Err(LispError::TypeMismatch {
+            form: form.to_string(),
+            expected: "Poly or Number".to_string(),
+            got: other.type_name().to_string(),
+        }),
+    }
+}
+
+pub(crate) fn add(a: &[D512], b: &[D512]) -> Vec<D512> {
+    (0..a.len().max(b.len()))
+        .map(|i| a.get(i).copied().unwrap_or_default() + b.get(i).copied().unwrap_or_default())
+        .collect()
+}
+
+pub(crate) fn mul(a: &[D512], b: &[D512]) -> Vec<D512> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let mut out = vec![D512::ZERO; a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            out[i + j] += *x * *y;
+        }
+    }
+    out
+}
+
+/// Long division: `a = q·b + r` with `deg r < deg b`. `b` must be nonzero.
+pub(crate) fn divmod(a: &[D512], b: &[D512]) -> (Vec<D512>, Vec<D512>) {
+    let db = b.len() - 1;
+    if a.len() <= db {
+        return (Vec::new(), a.to_vec());
+    }
+    let mut r = a.to_vec();
+    let mut q = vec![D512::ZERO; a.len() - db];
+    for i in (0..q.len()).rev() {
+        let c = r[i + db] / b[db];
+        q[i] = c;
+        for (j, y) in b.iter().enumerate() {
+            r[i + j] -= c * *y;
+        }
+    }
+    r.truncate(db);
+    (trim(q), trim_approx(r, largest(a)))
+}
+
+fn derivative(c: &[D512]) -> Vec<D512> {
+    c.iter()
+        .enumerate()
+        .skip(1)
+        .map(|(k, x)| *x * D512::from(k as u64))
+        .collect()
+}
+
+pub(crate) fn eval(c: &[D512], x: D512) -> D512 {
+    c.iter().rev().fold(D512::ZERO, |acc, k| acc * x + *k)
+}
+
+pub(crate) fn gcd(a: &[D512], b: &[D512]) -> Vec<D512> {
+    let (mut a, mut b) = (trim(a.to_vec()), trim(b.to_vec()));
+    while !b.is_empty() {
+        let (_, r) = divmod(&a, &b);
+        a = b;
+        b = r;
+    }
+    // Monic, so the answer does not depend on how the inputs were scaled.
+    match a.last().copied() {
+        Some(lead) => a.into_iter().map(|c| c / lead).collect(),
+        None => a,
+    }
+}
+
+// Complex helpers for Durand–Kerner, on (re, im) pairs.
+type Complex = (f64, f64);
+
+fn c_mul(a: Complex, b: Complex) -> Complex {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+fn c_sub(a: Complex, b: Complex) -> Complex {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn c_div(a: Complex, b: Complex) -> Complex {
+    let d = b.0 * b.0 + b.1 * b.1;
+    ((a.0 * b.0 + a.1 * b.1) / d, (a.1 * b.0 - a.0 * b.1) / d)
+}
+
+/// All complex roots (with multiplicity) of a polynomial of degree ≥ 1.
+///
+/// Durand–Kerner scatters a repeated root into a ring of nearby complex
+/// values, so the polynomial is first split into square-free parts:
+/// `p / gcd(p, p′)` has each distinct root of `p` once, and the gcd keeps the
+/// rest with every multiplicity one lower.
+fn roots(c: &[D512]) -> Vec<LispAST> {
+    let mut real = Vec::new();
+    let mut complex = Vec::new();
+    let mut rest = c.to_vec();
+    while rest.len() > 1 {
+        let g = gcd(&rest, &derivative(&rest));
+        let (free, _) = divmod(&rest, &g);
+        let (r, z) = simple_roots(&free);
+        real.extend(r);
+        complex.extend(z);
+        rest = g;
+    }
+    real.sort();
+    complex.sort();
+    real.into_iter()
+        .map(LispAST::Number)
+        .chain(complex.into_iter().map(|(re, im)| complex_value(re, im)))
+        .collect()
+}
+
+/// The roots of a square-free polynomial of degree ≥ 1, real and complex, via
+/// Durand–Kerner in `f64`. Real roots are then polished by Newton's method in
+/// D512 so that e.g. the roots of x² − 2 come out to full displayed precision.
+fn simple_roots(c: &[D512]) -> (Vec<D512>, Vec<(D512, D512)>) {
+    let n = c.len() - 1;
+    let lead = c[n].to_f64();
+    let monic: Vec<f64> = c.iter().map(|k| k.to_f64() / lead).collect();
+    let bound = 1.0 + monic[..n].iter().fold(0.0_f64, |m, k| m.max(k.abs()));
+
+    let mut z: Vec<Complex> = Vec::with_capacity(n);
+    let mut seed = (bound, 0.0);
+    for _ in 0..n {
+        seed = c_mul(seed, (0.4, 0.9));
+        z.push(seed);
+    }
+
+    for _ in 0..MAX_ITERATIONS {
+        let mut largest_step = 0.0_f64;
+        for i in 0..n {
+            let value = monic.iter().rev().fold((0.0, 0.0), |acc, k| {
+                let (re, im) = c_mul(acc, z[i]);
+                (re + k, im)
+            });
+            let denom = (0..n)
+                .filter(|&j| j != i)
+                .fold((1.0, 0.0), |acc, j| c_mul(acc, c_sub(z[i], z[j])));
+            let step = c_div(value, denom);
+            z[i] = c_sub(z[i], step);
+            largest_step = largest_step.max(step.0.hypot(step.1));
+        }
+        if largest_step < 1e-15 {
+            break;
+        }
+    }
+
+    let dc = derivative(c);
+    let mut real = Vec::new();
+    let mut complex = Vec::new();
+    for (re, im) in z {
+        if im.abs() <= 1e-7 * re.abs().max(1.0) {
+            real.push(polish(c, &dc, decimal(&format!("{re:e}"))));
+        } else {
+            complex.push((re, im));
+        }
+    }
+
+    let part = |x: f64| rounded(decimal(&format!("{x:e}")), COMPLEX_DIGITS);
+    let complex = complex
+        .into_iter()
+        .map(|(re, im)| (part(re), part(im)))
+        .collect();
+    (real, complex)
+}
+
+/// A non-real root, written `(complex re im)`.
+pub(crate) fn complex_value(re: D512, im: D512) -> LispAST {
+    LispAST::List(vec![
+        LispAST::Symbol("complex".to_string()),
+        LispAST::Number(re),
+        LispAST::Number(im),
+    ].into())
+}
+
+/// Newton's method in D512 from an `f64` estimate. Roots reaching here are
+/// simple, so convergence is quadratic; the cap is only a safety net.
+fn polish(c: &[D512], dc: &[D512], mut x: D512) -> D512 {
+    let tiny = decimal("1e-120");
+    for _ in 0..400 {
+        let slope = eval(dc, x);
+        if slope.is_zero() {
+            break;
+        }
+        let step = eval(c, x) / slope;
+        x -= step;
+        if step.abs() <= tiny {
+            break;
+        }
+    }
+    rounded(x, REAL_DIGITS)
+}
+
+/// Round to `digits` places, without the trailing zeros or the `-0` that
+/// rounding a tiny negative part leaves.
+fn rounded(x: D512, digits: i16) -> D512 {
+    let x = x.round(digits).reduce();
+    if x.is_zero() { D512::ZERO } else { x }
+}
+
+/// `+` and `-` with at least one polynomial operand.
+pub(crate) fn sum(form: &str, args: &[LispAST], subtract: bool) -> Result<LispAST, LispError> {
+    let mut var = None;
+    let mut total = coefficients(form, &args[0], &mut var)?;
+    if subtract && args.len() == 1 {
+        total = total.into_iter().map(|c| -c).collect();
+    }
+    for arg in &args[1..] {
+        let mut c = coefficients(form, arg, &mut var)?;
+        if subtract {
+            c = c.into_iter().map(|x| -x).collect();
+        }
+        total = add(&total, &c);
+    }
+    Ok(value(var.as_deref().unwrap_or("x"), total))
+}
+
+/// `*` with at least one polynomial operand.
+pub(crate) fn product(form: &str, args: &[LispAST]) -> Result<LispAST, LispError> {
+    let mut var = None;
+    let mut total = vec![D512::ONE];
+    for arg in args {
+        total = mul(&total, &coefficients(form, arg, &mut var)?);
+    }
+    Ok(value(var.as_deref().unwrap_or("x"), total))
+}
+
+/// `/` with a polynomial dividend: only division by numbers is exact enough to
+/// be an operator; polynomial divisors go through `poly-div`.
+pub(crate) fn quotient(form: &str, args: &[LispAST]) -> Result<LispAST, LispError> {
+    let mut var = None;
+    let mut total = coefficients(form, &args[0], &mut var)?;
+    for arg in &args[1..] {
+        let LispAST::Number(n) = arg else {
+            return Err(LispError::TypeMismatch {
+                form: form.to_string(),
+                expected: "Number (use poly-div to divide by a polynomial)".to_string(),
+                got: arg.type_name().to_string(),
+            });
+        };
+        if n.is_zero() {
+            return Err(LispError::DivisionByZero);
+        }
+        total = total.into_iter().map(|c| c / *n).collect();
+    }
+    Ok(value(var.as_deref().unwrap_or("x"), total))
+}
+
+/// `(pow p n)` for a whole, non-negative `n`.
+pub(crate) fn power(base: &LispAST, exponent: D512) -> Result<LispAST, LispError> {
+    let mut var = None;
+    let c = coefficients("pow", base, &mut var)?;
+    let n = exponent
+        .is_integral()
+        .then(|| exponent.to_u32().ok())
+        .flatten()
+        .ok_or_else(|| LispError::NotAnInteger {
+            form: "pow".to_string(),
+            expected: "a non-negative whole exponent for a polynomial".to_string(),
+            value: exponent.to_string(),
+        })?;
+    let result = (0..n).fold(vec![D512::ONE], |acc, _| mul(&acc, &c));
+    Ok(value(var.as_deref().unwrap_or("x"), result))
+}
+
+impl Native {
+    /// `(poly 1 -3 2)` is x² − 3x + 2; a leading quoted symbol picks the
+    /// variable: `(poly (quote t) 1 0 -1)` is t² − 1.
+    pub fn poly(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let (var, coeffs) = match args.split_first() {
+            Some((LispAST::Symbol(v), rest)) => (v.as_str(), rest),
+            _ => ("x", args),
+        };
+        if coeffs.is_empty() {
+            return Err(LispError::Arity {
+                form: "poly".to_string(),
+                expected: "at least 1 coefficient".to_string(),
+                got: 0,
+            });
+        }
+        let coeffs = coeffs
+            .iter()
+            .rev()
+            .map(|c| match c {
+                LispAST::Number(n) => Ok(*n),
+                other => Err(LispError::TypeMismatch {
+                    form: "poly".to_string(),
+                    expected: "Number".to_string(),
+                    got: other.type_name().to_string(),
+                }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(value(var, coeffs))
+    }
+
+    /// `(poly-div a b)`: the list `(quotient remainder)`.
+    pub fn poly_div(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let mut var = None;
+        let a = coefficients("poly-div", &args[0], &mut var)?;
+        let b = coefficients("poly-div", &args[1], &mut var)?;
+        if b.is_empty() {
+            return Err(LispError::DivisionByZero);
+        }
+        let (q, r) = divmod(&a, &b);
+        let var = var.unwrap_or_else(|| "x".to_string());
+        Ok(LispAST::List(vec![value(&var, q), value(&var, r)].into()))
+    }
+
+    /// `(poly-gcd a b)`: the monic greatest common divisor.
+    pub fn poly_gcd(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let mut var = None;
+        let a = coefficients("poly-gcd", &args[0], &mut var)?;
+        let b = coefficients("poly-gcd", &args[1], &mut var)?;
+        Ok(value(var.as_deref().unwrap_or("x"), gcd(&a, &b)))
+    }
+
+    /// `(poly-deriv p)`: the derivative with respect to the polynomial's variable.
+    pub fn poly_deriv(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let mut var = None;
+        let c = coefficients("poly-deriv", &args[0], &mut var)?;
+        Ok(value(var.as_deref().unwrap_or("x"), derivative(&c)))
+    }
+
+    /// `(poly-eval p x)`: the value of `p` at the number `x`.
+    pub fn poly_eval(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let c = coefficients("poly-eval", &args[0], &mut None)?;
+        let LispAST::Number(x) = args[1] else {
+            return Err(LispError::TypeMismatch {
+                form: "poly-eval".to_string(),
+                expected: "Number".to_string(),
+                got: args[1].type_name().to_string(),
+            });
+        };
+        Ok(LispAST::Number(eval(&c, x)))
+    }
+
+    /// `(poly-roots p)`: every root with multiplicity, real ones first in
+    /// ascending order, then complex ones as `(complex re im)`.
+    pub fn poly_roots(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let c = coefficients("poly-roots", &args[0], &mut None)?;
+        match c.len() {
+            0 => Err(LispError::TypeMismatch {
+                form: "poly-roots".to_string(),
+                expected: "a nonzero polynomial".to_string(),
+                got: "the zero polynomial".to_string(),
+            }),
+            1 => Ok(LispAST::List(Vec::new().into())),
+            _ => Ok(LispAST::List(roots(&c).into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p(coeffs: &[i64]) -> LispAST {
+        let args: Vec<_> = coeffs
+            .iter()
+            .map(|&c| LispAST::Number(D512::from(c)))
+            .collect();
+        Native::poly(&args).unwrap()
+    }
+
+    fn n(x: i64) -> LispAST {
+        LispAST::Number(D512::from(x))
+    }
+
+    #[test]
+    fn arithmetic_and_division() {
+        // (x - 1)(x - 2) = x² - 3x + 2
+        let product = Native::mul(&[p(&[1, -1]), p(&[1, -2])]).unwrap();
+        assert_eq!(product, p(&[1, -3, 2]));
+        assert_eq!(Native::sub(&[product.clone(), product]).unwrap(), p(&[0]));
+
+        // x³ - 1 = (x - 1)(x² + x + 1) + 0
+        let quotient = Native::poly_div(&[p(&[1, 0, 0, -1]), p(&[1, -1])]).unwrap();
+        assert_eq!(quotient, LispAST::List(vec![p(&[1, 1, 1]), p(&[0])].into()));
+    }
+
+    #[test]
+    fn gcd_is_monic() {
+        // gcd(2x² - 2, 3x - 3) = x - 1
+        let g = Native::poly_gcd(&[p(&[2, 0, -2]), p(&[3, -3])]).unwrap();
+        assert_eq!(g, p(&[1, -1]));
+    }
+
+    #[test]
+    fn roots_real_and_complex() {
+        assert_eq!(
+            Native::poly_roots(&[p(&[1, -3, 2])]).unwrap(),
+            LispAST::List(vec![n(1), n(2)].into())
+        );
+        // x² + 1 has roots ±i.
+        let complex =
+            |im| LispAST::List(vec![LispAST::Symbol("complex".to_string()), n(0), n(im)].into());
+        assert_eq!(
+            Native::poly_roots(&[p(&[1, 0, 1])]).unwrap(),
+            LispAST::List(vec![complex(-1), complex(1)].into())
+        );
+    }
+
+    #[test]
+    fn repeated_roots_keep_their_multiplicity() {
+        // (x - 1)³
+        let cube = Native::pow(&[p(&[1, -1]), n(3)]).unwrap();
+        assert_eq!(
+            Native::poly_roots(&[cube]).unwrap(),
+            LispAST::List(vec![n(1), n(1), n(1)].into())
+        );
+        // (x + 2)²(x² + 1)
+        let mixed = Native::mul(&[p(&[1, 4, 4]), p(&[1, 0, 1])]).unwrap();
+        let complex =
+            |im| LispAST::List(vec![LispAST::Symbol("complex".to_string()), n(0), n(im)].into());
+        assert_eq!(
+            Native::poly_roots(&[mixed]).unwrap(),
+            LispAST::List(vec![n(-2), n(-2), complex(-1), complex(1)].into())
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Dimensioned quantities: `(qty 9.81 m/s^2)` and the arithmetic that keeps
+//! dimensions consistent. `+`/`-` demand matching dimensions and answer in the
+//! first operand's unit (later affine operands such as `°C` count as
+//! differences); `*`/`/`/`pow` combine units term by term.
+
+use fastnum::D512;
+use mazer_types::{LispAST, LispError, Unit};
+
+use crate::Native;
+
+/// Whether arithmetic over `args` has to take the dimensioned path.
+pub(crate) fn involved(args: &[LispAST]) -> bool {
+    args.iter().any(|a| matches!(a, LispAST::Quantity { .. }))
+}
+
+/// Split an operand into value and unit; plain numbers are dimensionless.
+fn parts(form: &str, arg: &LispAST) -> Result<(D512, Unit), LispError> {
+    match arg {
+        LispAST::Number(n) => Ok((*n, Unit::one())),
+        LispAST::Quantity { value, unit } => Ok((*value, unit.clone())),
+        other => Err(LispError::TypeMismatch {
+            form: form.to_string(),
+            expected: "Number or Quantity".to_string(),
+            got: other.type_name().to_string(),
+        }),
+    }
+}
+
+/// Wrap a result, collapsing dimensionless ones (`km/m`, `Hz*s`) to plain numbers.
+fn quantity(value: D512, unit: Unit) -> LispAST {
+    if unit.is_dimensionless() {
+        LispAST::Number(value * unit.scale)
+    } else {
+        LispAST::Quantity { value, unit }
+    }
+}
+
+fn describe(unit: &Unit) -> String {
+    if unit.is_dimensionless() {
+        "a plain number".to_string()
+    } else {
+        format!("{unit} ({})", unit.dim)
+    }
+}
+
+/// `+` and `-` over quantities: every operand is converted into the first one's unit.
+pub(crate) fn sum(form: &str, args: &[LispAST], subtract: bool) -> Result<LispAST, LispError> {
+    let (mut total, unit) = parts(form, &args[0])?;
+    if subtract && args.len() == 1 {
+        return Ok(quantity(-total, unit));
+    }
+
+    for arg in &args[1..] {
+        let (value, other) = parts(form, arg)?;
+        if other.dim != unit.dim {
+            return Err(LispError::DimensionMismatch {
+                form: form.to_string(),
+                left: describe(&unit),
+                right: describe(&other),
+            });
+        }
+        let value = value * other.scale / unit.scale;
+        total = if subtract {
+            total - value
+        } else {
+            total + value
+        };
+    }
+    Ok(quantity(total, unit))
+}
+
+/// `*` and `/` over quantities: values and units multiply (or divide) together.
+pub(crate) fn product(form: &str, args: &[LispAST], divide: bool) -> Result<LispAST, LispError> {
+    let (mut value, mut unit) = parts(form, &args[0])?;
+    if divide && args.len() == 1 {
+        if value.is_zero() {
+            return Err(LispError::DivisionByZero);
+        }
+        return Ok(quantity(D512::from(1) / value, unit.powi(-1)));
+    }
+
+    for arg in &args[1..] {
+        let (v, u) = parts(form, arg)?;
+        if divide {
+            if v.is_zero() {
+                return Err(LispError::DivisionByZero);
+            }
+            value /= v;
+            unit = unit / u;
+        } else {
+            value *= v;
+            unit = unit * u;
+        }
+    }
+    Ok(quantity(value, unit))
+}
+
+/// `pow` with a quantity base; only whole exponents keep the unit meaningful.
+pub(crate) fn power(base: &LispAST, exponent: D512) -> Result<LispAST, LispError> {
+    let (value, unit) = parts("pow", base)?;
+    let n = exponent
+        .is_integral()
+        .then(|| exponent.to_i32().ok())
+        .flatten()
+        .ok_or_else(|| LispError::NotAnInteger {
+            form: "pow".to_string(),
+            expected: "a whole exponent for a quantity".to_string(),
+            value: exponent.to_string(),
+        })?;
+    if n < 0 && value.is_zero() {
+        return Err(LispError::DivisionByZero);
+    }
+    Ok(quantity(value.powi(n), unit.powi(n)))
+}
+
+impl Native {
+    /// `(qty value unit)`, once the interpreter has resolved the unit expression
+    /// (which is written unevaluated, e.g. `m/s^2`).
+    pub fn quantity(value: &LispAST, unit: Unit) -> Result<LispAST, LispError> {
+        match value {
+            LispAST::Number(n) => Ok(quantity(*n, unit)),
+            other => Err(LispError::TypeMismatch {
+                form: "qty".to_string(),
+                expected: "Number".to_string(),
+                got: other.type_name().to_string(),
+            }),
+        }
+    }
+
+    /// `(convert q unit)`: re-express `q` in `target`, honouring affine offsets.
+    pub fn convert(value: &LispAST, target: Unit) -> Result<LispAST, LispError> {
+        let (v, unit) = parts("convert", value)?;
+        if unit.dim != target.dim {
+            return Err(LispError::DimensionMismatch {
+                form: "convert".to_string(),
+                left: describe(&unit),
+                right: describe(&target),
+            });
+        }
+        // Strip the trailing zeros that dividing by the target scale leaves.
+        Ok(quantity(target.from_si(unit.to_si(v)).reduce(), target))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn qty(value: i32, unit: &str) -> LispAST {
+        Native::quantity(
+            &LispAST::Number(D512::from(value)),
+            Unit::parse(unit).unwrap(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn addition_converts_into_first_unit() {
+        let total = Native::add(&[qty(1, "km"), qty(500, "m")]).unwrap();
+        assert_eq!(total, qty(1500, "m"));
+        let LispAST::Quantity { value, unit } = total else {
+            panic!("expected a quantity");
+        };
+        assert_eq!(unit.to_string(), "km");
+        assert_eq!(value, D512::from(3) / D512::from(2));
+    }
+
+    #[test]
+    fn addition_rejects_mismatched_dimensions() {
+        assert!(matches!(
+            Native::add(&[qty(1, "m"), qty(1, "s")]),
+            Err(LispError::DimensionMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn products_combine_units() {
+        let force = Native::mul(&[qty(2, "kg"), qty(3, "m/s^2")]).unwrap();
+        assert_eq!(force, qty(6, "N"));
+        assert_eq!(
+            Native::div(&[qty(3, "km"), qty(3, "m")]).unwrap(),
+            LispAST::Number(D512::from(1000))
+        );
+    }
+
+    #[test]
+    fn conversion_handles_affine_scales() {
+        let boiling = Native::convert(&qty(212, "°F"), Unit::parse("°C").unwrap()).unwrap();
+        assert_eq!(boiling, qty(100, "°C"));
+        assert!(matches!(
+            Native::convert(&qty(1, "mi"), Unit::parse("kg").unwrap()),
+            Err(LispError::DimensionMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,134 @@
+//! Finite-set natives. Sets are [`LispAST::Set`] values whose elements are kept
+//! unique and sorted, so membership and equality are plain comparisons.
+
+use mazer_types::{LispAST, LispError};
+
+use crate::Native;
+
+fn elements<'a>(form: &str, arg: &'a LispAST) -> Result<&'a [LispAST], LispError> {
+    match arg {
+        LispAST::Set(items) => Ok(items),
+        other => Err(LispError::TypeMismatch {
+            form: form.to_string(),
+            expected: "Set".to_string(),
+            got: other.type_name().to_string(),
+        }),
+    }
+}
+
+fn contains(set: &[LispAST], x: &LispAST) -> bool {
+    set.binary_search_by(|e| e.canonical_cmp(x)).is_ok()
+}
+
+impl Native {
+    /// `(set a b c ...)`: a finite set of the (evaluated) arguments.
+    pub fn set(args: &[LispAST]) -> Result<LispAST, LispError> {
+        Ok(LispAST::set_from(args.iter().cloned()))
+    }
+
+    pub fn union(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let mut all = Vec::new();
+        for a in args {
+            all.extend_from_slice(elements("union", a)?);
+        }
+        Ok(LispAST::set_from(all))
+    }
+
+    pub fn intersect(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let Some((first, rest)) = args.split_first() else {
+            return Err(LispError::Arity {
+                form: "intersect".to_string(),
+                expected: "at least 1".to_string(),
+                got: 0,
+            });
+        };
+        let rest = rest
+            .iter()
+            .map(|a| elements("intersect", a))
+            .collect::<Result<Vec<_>, _>>()?;
+        let common = elements("intersect", first)?
+            .iter()
+            .filter(|x| rest.iter().all(|s| contains(s, x)))
+            .cloned();
+        Ok(LispAST::set_from(common))
+    }
+
+    /// `(in x S)`: whether `x ∈ S`.
+    pub fn member(args: &[LispAST]) -> Result<LispAST, LispError> {
+        Ok(LispAST::Bool(contains(elements("in", &args[1])?, &args[0])))
+    }
+
+    /// `(notin x S)`: whether `x ∉ S`.
+    pub fn not_member(args: &[LispAST]) -> Result<LispAST, LispError> {
+        Ok(LispAST::Bool(!contains(
+            elements("notin", &args[1])?,
+            &args[0],
+        )))
+    }
+
+    /// `(subset A B)`: whether `A ⊆ B`.
+    pub fn subset(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let a = elements("subset", &args[0])?;
+        let b = elements("subset", &args[1])?;
+        Ok(LispAST::Bool(a.iter().all(|x| contains(b, x))))
+    }
+
+    /// `(superset A B)`: whether `A ⊇ B`.
+    pub fn superset(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let a = elements("superset", &args[0])?;
+        let b = elements("superset", &args[1])?;
+        Ok(LispAST::Bool(b.iter().all(|x| contains(a, x))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    use fastnum::decimal::Context;
+
+    use super::*;
+
+    fn hash(value: &LispAST) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn distinct_functions_stay_distinct_members() {
+        let set_fn = LispAST::NativeFunc(Native::set);
+        let union_fn = LispAST::NativeFunc(Native::union);
+        let call = |name: &str| LispAST::Application {
+            name: name.to_string(),
+            args: vec![LispAST::Number(1.into())],
+        };
+        let s = Native::set(&[set_fn.clone(), call("f")]).unwrap();
+        let LispAST::Set(items) = &s else {
+            panic!("expected a set");
+        };
+        assert_eq!(items.len(), 2);
+        assert_eq!(Native::member(&[set_fn, s.clone()]), Ok(LispAST::Bool(true)));
+        assert_eq!(Native::member(&[union_fn, s.clone()]), Ok(LispAST::Bool(false)));
+        assert_eq!(Native::member(&[call("g"), s]), Ok(LispAST::Bool(false)));
+    }
+
+    #[test]
+    fn equal_values_hash_alike() {
+        let one = LispAST::Number(1.into());
+        let one_point_zero =
+            LispAST::Number(fastnum::D512::from_str("1.0", Context::default()).unwrap());
+        assert_eq!(one, one_point_zero);
+        assert_eq!(hash(&one), hash(&one_point_zero));
+    }
+
+    #[test]
+    fn nan_and_negative_zero_are_single_members() {
+        let number = |s| LispAST::Number(fastnum::D512::from_str(s, Context::default()).unwrap());
+        let s = Native::set(&[number("NaN"), number("0"), number("NaN"), number("-0")]).unwrap();
+        assert_eq!(s, LispAST::Set(vec![number("0"), number("NaN")].into()));
+        assert_eq!(number("NaN"), number("NaN"));
+        assert_eq!(number("0").canonical_cmp(&number("-0")), std::cmp::Ordering::Equal);
+    }
+}
@@ -0,0 +1,453 @@
+//! `solve`: exact-as-decimal solutions of quoted equations.
+//!
+//! A single equation is brought to the form N(x)/D(x) = 0 with polynomial N
+//! and D, common factors are cancelled, and N is solved when it is at most
+//! quadratic; roots of D are dropped as extraneous. A list of equations in a
+//! list of unknowns is solved as a linear system by Gaussian elimination.
+
+use fastnum::D512;
+use mazer_types::{LispAST, LispError};
+
+use crate::{Native, poly};
+
+/// Below this a pivot counts as zero; elimination in D512 leaves residue far
+/// smaller than any coefficient a note would write.
+const PIVOT_EPSILON: &str = "1e-100";
+
+fn unsolvable(reason: impl Into<String>) -> LispError {
+    LispError::Unsolvable {
+        reason: reason.into(),
+    }
+}
+
+fn epsilon() -> D512 {
+    D512::from_str(PIVOT_EPSILON, fastnum::decimal::Context::default()).unwrap_or_default()
+}
+
+/// `lhs - rhs` for `(= lhs rhs)`; anything else is taken as `expr = 0`.
+fn difference(equation: &LispAST) -> LispAST {
+    match equation {
+        LispAST::List(items) => match &items[..] {
+            [LispAST::Symbol(eq), lhs, rhs] if eq == "=" => LispAST::List(vec![
+                LispAST::Symbol("-".to_string()),
+                lhs.clone(),
+                rhs.clone(),
+            ].into()),
+            _ => equation.clone(),
+        },
+        other => other.clone(),
+    }
+}
+
+fn describe(expr: &LispAST) -> String {
+    match expr {
+        LispAST::List(items) => match items.first() {
+            Some(LispAST::Symbol(op)) => format!("a call to '{op}'"),
+            _ => "a list".to_string(),
+        },
+        LispAST::Symbol(s) => format!("'{s}'"),
+        other => format!("a {}", other.type_name()),
+    }
+}
+
+/// A rational function as (numerator, denominator) coefficients, ascending.
+type Rational = (Vec<D512>, Vec<D512>);
+
+fn constant(n: D512) -> Rational {
+    (poly::trim(vec![n]), vec![D512::ONE])
+}
+
+fn r_add(a: &Rational, b: &Rational) -> Rational {
+    (
+        poly::trim(poly::add(&poly::mul(&a.0, &b.1), &poly::mul(&b.0, &a.1))),
+        poly::mul(&a.1, &b.1),
+    )
+}
+
+fn r_neg(a: Rational) -> Rational {
+    (a.0.into_iter().map(|c| -c).collect(), a.1)
+}
+
+fn r_mul(a: &Rational, b: &Rational) -> Rational {
+    (poly::mul(&a.0, &b.0), poly::mul(&a.1, &b.1))
+}
+
+fn r_recip(a: Rational) -> Result<Rational, LispError> {
+    if a.0.is_empty() {
+        return Err(LispError::DivisionByZero);
+    }
+    Ok((a.1, a.0))
+}
+
+/// Read `expr` as a rational function of `var`.
+fn rational(expr: &LispAST, var: &str) -> Result<Rational, LispError> {
+    match expr {
+        LispAST::Number(n) => Ok(constant(*n)),
+        LispAST::Symbol(s) if s == var => Ok((vec![D512::ZERO, D512::ONE], vec![D512::ONE])),
+        LispAST::List(items) => {
+            let Some(LispAST::Symbol(op)) = items.first() else {
+                return Err(unsolvable(format!(
+                    "cannot read {} as a polynomial",
+                    describe(expr)
+                )));
+            };
+            let args = items[1..]
+                .iter()
+                .map(|a| rational(a, var))
+                .collect::<Result<Vec<_>, _>>();
+            match (op.as_str(), args?.as_slice()) {
+                ("+" | "add", terms) => Ok(terms
+                    .iter()
+                    .fold(constant(D512::ZERO), |acc, t| r_add(&acc, t))),
+                ("-" | "sub", [only]) => Ok(r_neg(only.clone())),
+                ("-" | "sub", [first, rest @ ..]) => Ok(rest
+                    .iter()
+                    .fold(first.clone(), |acc, t| r_add(&acc, &r_neg(t.clone())))),
+                ("*" | "mul", factors) => Ok(factors
+                    .iter()
+                    .fold(constant(D512::ONE), |acc, f| r_mul(&acc, f))),
+                ("/" | "div", [only]) => r_recip(only.clone()),
+                ("/" | "div", [first, rest @ ..]) => {
+                    rest.iter().try_fold(first.clone(), |acc, d| {
+                        Ok(r_mul(&acc, &r_recip(d.clone())?))
+                    })
+                }
+                ("^" | "pow", [base, _]) => {
+                    let n = match &items[2] {
+                        LispAST::Number(n) if n.is_integral() => n.to_i32().ok(),
+                        _ => None,
+                    }
+                    .ok_or_else(|| unsolvable(format!("{var} is raised to a non-integer power")))?;
+                    let power =
+                        (0..n.unsigned_abs()).fold(constant(D512::ONE), |acc, _| r_mul(&acc, base));
+                    if n < 0 { r_recip(power) } else { Ok(power) }
+                }
+                _ => Err(unsolvable(format!(
+                    "{} is not polynomial in {var}",
+                    describe(expr)
+                ))),
+            }
+        }
+        other => Err(unsolvable(format!(
+            "{} is neither a number nor {var}",
+            describe(other)
+        ))),
+    }
+}
+
+fn tidy(x: D512) -> D512 {
+    if x.is_zero() { D512::ZERO } else { x.reduce() }
+}
+
+/// Roots of a polynomial of degree one or two, complex ones as `(complex re im)`.
+fn low_degree_roots(c: &[D512], var: &str) -> Result<Vec<LispAST>, LispError> {
+    match c {
+        [b, a] => Ok(vec![LispAST::Number(tidy(-*b / *a))]),
+        [c, b, a] => {
+            let two_a = D512::from(2) * *a;
+            let discriminant = *b * *b - D512::from(4) * *a * *c;
+            if discriminant.is_negative() {
+                let re = tidy(-*b / two_a);
+                let im = tidy((-discriminant).sqrt() / two_a).abs();
+                Ok(vec![
+                    poly::complex_value(re, -im),
+                    poly::complex_value(re, im),
+                ])
+            } else {
+                let root = discriminant.sqrt();
+                Ok(vec![
+                    LispAST::Number(tidy((-*b - root) / two_a)),
+                    LispAST::Number(tidy((-*b + root) / two_a)),
+                ])
+            }
+        }
+        _ => Err(unsolvable(format!(
+            "the equation has degree {} in {var}; only linear and quadratic equations are solved exactly (try poly-roots)",
+            c.len() - 1
+        ))),
+    }
+}
+
+fn solve_one(equation: &LispAST, var: &str) -> Result<LispAST, LispError> {
+    let (mut num, den) = rational(&difference(equation), var)?;
+
+    // Cancel common factors so that e.g. (x² - 1)/(x - 1) = 0 is linear. The
+    // original denominator is kept: a cancelled factor can still vanish at a
+    // root of what is left, as in (x - 1)²/(x - 1) = 0.
+    let common = poly::gcd(&num, &den);
+    if common.len() > 1 {
+        num = poly::divmod(&num, &common).0;
+    }
+
+    match num.len() {
+        0 => Err(unsolvable(format!(
+            "every value of {var} satisfies the equation"
+        ))),
+        1 => Ok(LispAST::Set(Vec::new().into())),
+        _ => {
+            let roots = low_degree_roots(&num, var)?;
+            // Roots of the denominator make the original equation undefined.
+            let defined = roots.into_iter().filter(|r| match r {
+                LispAST::Number(x) => poly::eval(&den, *x).abs() > epsilon(),
+                _ => true,
+            });
+            Ok(LispAST::set_from(defined))
+        }
+    }
+}
+
+/// Read `expr` as `a₁v₁ + … + aₙvₙ + c`, returning `[a₁, …, aₙ, c]`.
+fn linear(expr: &LispAST, vars: &[String]) -> Result<Vec<D512>, LispError> {
+    let n = vars.len();
+    let unit = |i: usize| {
+        let mut row = vec![D512::ZERO; n + 1];
+        row[i] = D512::ONE;
+        row
+    };
+    let is_constant = |row: &[D512]| row[..n].iter().all(D512::is_zero);
+    let scale = |row: Vec<D512>, k: D512| row.into_iter().map(|x| x * k).collect::<Vec<_>>();
+    let not_linear = || unsolvable(format!("{} is not linear in the unknowns", describe(expr)));
+
+    match expr {
+        LispAST::Number(c) => Ok(scale(unit(n), *c)),
+        LispAST::Symbol(s) => match vars.iter().position(|v| v == s) {
+            Some(i) => Ok(unit(i)),
+            None => Err(unsolvable(format!("'{s}' is not one of the unknowns"))),
+        },
+        LispAST::List(items) => {
+            let Some(LispAST::Symbol(op)) = items.first() else {
+                return Err(not_linear());
+            };
+            let args = items[1..]
+                .iter()
+                .map(|a| linear(a, vars))
+                .collect::<Result<Vec<_>, _>>()?;
+            let add = |a: Vec<D512>, b: &Vec<D512>| a.iter().zip(b).map(|(x, y)| *x + *y).collect();
+            match (op.as_str(), args.as_slice()) {
+                ("+" | "add", terms) => Ok(terms.iter().fold(vec![D512::ZERO; n + 1], add)),
+                ("-" | "sub", [only]) => Ok(scale(only.clone(), -D512::ONE)),
+                ("-" | "sub", [first, rest @ ..]) => {
+                    Ok(rest.iter().fold(first.clone(), |acc, t| {
+                        add(acc, &scale(t.clone(), -D512::ONE))
+                    }))
+                }
+                ("*" | "mul", factors) => {
+                    let mut result = scale(unit(n), D512::ONE);
+                    for f in factors {
+                        result = if is_constant(&result) {
+                            scale(f.clone(), result[n])
+                        } else if is_constant(f) {
+                            scale(result, f[n])
+                        } else {
+                            return Err(not_linear());
+                        };
+                    }
+                    Ok(result)
+                }
+                ("/" | "div", [first, rest @ ..]) => {
+                    rest.iter().try_fold(first.clone(), |acc, d| {
+                        if !is_constant(d) {
+                            return Err(not_linear());
+                        }
+                        if d[n].is_zero() {
+                            return Err(LispError::DivisionByZero);
+                        }
+                        Ok(scale(acc, D512::ONE / d[n]))
+                    })
+                }
+                _ => Err(not_linear()),
+            }
+        }
+        _ => Err(not_linear()),
+    }
+}
+
+fn solve_system(equations: &[LispAST], vars: &[String]) -> Result<LispAST, LispError> {
+    let n = vars.len();
+    // Augmented matrix [A | b] for A·v = b.
+    let mut rows = equations
+        .iter()
+        .map(|e| {
+            let mut row = linear(&difference(e), vars)?;
+            row[n] = -row[n];
+            Ok(row)
+        })
+        .collect::<Result<Vec<_>, LispError>>()?;
+
+    let eps = epsilon();
+    let mut rank = 0;
+    let mut pivots = Vec::with_capacity(n);
+    for col in 0..n {
+        let Some(pivot) = (rank..rows.len())
+            .filter(|&r| rows[r][col].abs() > eps)
+            .max_by(|&a, &b| rows[a][col].abs().cmp(&rows[b][col].abs()))
+        else {
+            continue;
+        };
+        rows.swap(rank, pivot);
+        let lead = rows[rank][col];
+        rows[rank] = rows[rank].iter().map(|x| *x / lead).collect();
+        for r in 0..rows.len() {
+            if r != rank && !rows[r][col].is_zero() {
+                let factor = rows[r][col];
+                let pivot_row = rows[rank].clone();
+                for (x, p) in rows[r].iter_mut().zip(pivot_row) {
+                    *x -= factor * p;
+                }
+            }
+        }
+        pivots.push(col);
+        rank += 1;
+    }
+
+    // A leftover row 0 = b with b ≠ 0 means the equations contradict each other.
+    if rows[rank..].iter().any(|row| row[n].abs() > eps) {
+        return Ok(LispAST::Set(Vec::new().into()));
+    }
+    if rank < n {
+        return Err(unsolvable(format!(
+            "the system has infinitely many solutions ({rank} independent equation(s) for {n} unknowns)"
+        )));
+    }
+
+    Ok(LispAST::set_from(pivots.into_iter().enumerate().map(
+        |(r, col)| {
+            LispAST::List(vec![
+                LispAST::Symbol("=".to_string()),
+                LispAST::Symbol(vars[col].clone()),
+                LispAST::Number(tidy(rows[r][n])),
+            ].into())
+        },
+    )))
+}
+
+impl Native {
+    /// `(solve (quote (= lhs rhs)) (quote x))` gives the set of solutions;
+    /// `(solve (quote ((= ...) (= ...))) (quote (x y)))` solves a linear system,
+    /// giving `{x = …, y = …}`.
+    pub fn solve(args: &[LispAST]) -> Result<LispAST, LispError> {
+        match (&args[0], &args[1]) {
+            (equation, LispAST::Symbol(var)) => solve_one(equation, var),
+            (LispAST::List(equations), LispAST::List(vars)) => {
+                let vars = vars
+                    .iter()
+                    .map(|v| match v {
+                        LispAST::Symbol(s) => Ok(s.clone()),
+                        other => Err(LispError::TypeMismatch {
+                            form: "solve".to_string(),
+                            expected: "a list of Symbols".to_string(),
+                            got: format!("a list containing a {}", other.type_name()),
+                        }),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                solve_system(equations, &vars)
+            }
+            (_, other) => Err(LispError::TypeMismatch {
+                form: "solve".to_string(),
+                expected: "a quoted Symbol, or a list of Symbols for a system".to_string(),
+                got: other.type_name().to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sym(s: &str) -> LispAST {
+        LispAST::Symbol(s.to_string())
+    }
+
+    fn n(x: i64) -> LispAST {
+        LispAST::Number(D512::from(x))
+    }
+
+    fn call(op: &str, args: Vec<LispAST>) -> LispAST {
+        let mut items = vec![sym(op)];
+        items.extend(args);
+        LispAST::List(items.into())
+    }
+
+    #[test]
+    fn linear_and_quadratic() {
+        // 2x + 3 = 7
+        let linear = call(
+            "=",
+            vec![call("+", vec![call("*", vec![n(2), sym("x")]), n(3)]), n(7)],
+        );
+        assert_eq!(
+            Native::solve(&[linear, sym("x")]).unwrap(),
+            LispAST::Set(vec![n(2)].into())
+        );
+
+        // x² - 5x + 6 = 0
+        let quadratic = call(
+            "-",
+            vec![
+                call("^", vec![sym("x"), n(2)]),
+                call("*", vec![n(5), sym("x")]),
+                n(-6),
+            ],
+        );
+        assert_eq!(
+            Native::solve(&[quadratic, sym("x")]).unwrap(),
+            LispAST::Set(vec![n(2), n(3)].into())
+        );
+    }
+
+    #[test]
+    fn rational_equations_drop_extraneous_roots() {
+        // (x² - 1)/(x - 1) = 0 has only x = -1.
+        let expr = call(
+            "/",
+            vec![
+                call("-", vec![call("*", vec![sym("x"), sym("x")]), n(1)]),
+                call("-", vec![sym("x"), n(1)]),
+            ],
+        );
+        assert_eq!(
+            Native::solve(&[expr, sym("x")]).unwrap(),
+            LispAST::Set(vec![n(-1)].into())
+        );
+        // (x - 1)²/(x - 1) = 0 is undefined at its only candidate root.
+        let x_minus_1 = || call("-", vec![sym("x"), n(1)]);
+        let expr = call(
+            "=",
+            vec![
+                call("/", vec![call("*", vec![x_minus_1(), x_minus_1()]), x_minus_1()]),
+                n(0),
+            ],
+        );
+        assert_eq!(
+            Native::solve(&[expr, sym("x")]).unwrap(),
+            LispAST::Set(Vec::new().into())
+        );
+    }
+
+    #[test]
+    fn linear_systems() {
+        // x + y = 3, x - y = 1
+        let system = LispAST::List(vec![
+            call("=", vec![call("+", vec![sym("x"), sym("y")]), n(3)]),
+            call("=", vec![call("-", vec![sym("x"), sym("y")]), n(1)]),
+        ].into());
+        let vars = LispAST::List(vec![sym("x"), sym("y")].into());
+        assert_eq!(
+            Native::solve(&[system, vars]).unwrap(),
+            LispAST::Set(vec![
+                call("=", vec![sym("x"), n(2)]),
+                call("=", vec![sym("y"), n(1)]),
+            ].into())
+        );
+    }
+
+    #[test]
+    fn non_polynomial_input_is_rejected() {
+        let expr = call("=", vec![call("sin", vec![sym("x")]), n(0)]);
+        assert!(matches!(
+            Native::solve(&[expr, sym("x")]),
+            Err(LispError::Unsolvable { .. })
+        ));
+    }
+}
@@ -0,0 +1,296 @@
+//! String natives. Lengths and positions count extended grapheme clusters, as
+//! the `string` special form does, so `(length "né")` is 2 however the `é` is
+//! encoded.
+
+use fastnum::{D512, decimal::Context};
+use mazer_types::{LispAST, LispError, NumberFormat, Precision};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::Native;
+
+fn string<'a>(form: &str, arg: &'a LispAST) -> Result<&'a str, LispError> {
+    match arg {
+        LispAST::String(s) => Ok(s),
+        other => Err(LispError::TypeMismatch {
+            form: form.to_string(),
+            expected: "String".to_string(),
+            got: other.type_name().to_string(),
+        }),
+    }
+}
+
+fn index(form: &str, arg: &LispAST) -> Result<usize, LispError> {
+    match arg {
+        LispAST::Number(n) if n.is_integral() && !n.is_negative() => {
+            n.to_usize().map_err(|_| LispError::Domain {
+                form: form.to_string(),
+                reason: format!("index {n} is too large"),
+            })
+        }
+        LispAST::Number(n) => Err(LispError::NotAnInteger {
+            form: form.to_string(),
+            expected: "a non-negative integer".to_string(),
+            value: n.to_string(),
+        }),
+        other => Err(LispError::TypeMismatch {
+            form: form.to_string(),
+            expected: "Number".to_string(),
+            got: other.type_name().to_string(),
+        }),
+    }
+}
+
+/// How a value reads when spliced into text: strings as they are, quantities
+/// with their unit symbol, anything else as it would be written.
+pub(crate) fn text(value: &LispAST) -> String {
+    match value {
+        LispAST::String(s) => s.clone(),
+        LispAST::Quantity { value, unit } => format!("{value} {unit}"),
+        other => other.to_string(),
+    }
+}
+
+/// A one-string, one-result transformation such as `upper`.
+fn map_string(
+    form: &str,
+    args: &[LispAST],
+    f: impl Fn(&str) -> String,
+) -> Result<LispAST, LispError> {
+    Ok(LispAST::String(f(string(form, &args[0])?)))
+}
+
+impl Native {
+    /// `(concat a b ...)`: the arguments' text run together.
+    pub fn concat(args: &[LispAST]) -> Result<LispAST, LispError> {
+        Ok(LispAST::String(args.iter().map(text).collect()))
+    }
+
+    /// `(substring s start)` or `(substring s start end)`, in graphemes with
+    /// `end` exclusive.
+    pub fn substring(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let graphemes: Vec<&str> = string("substring", &args[0])?.graphemes(true).collect();
+        let start = index("substring", &args[1])?;
+        let end = match args.get(2) {
+            Some(end) => index("substring", end)?,
+            None => graphemes.len(),
+        };
+        if start > end || end > graphemes.len() {
+            return Err(LispError::Domain {
+                form: "substring".to_string(),
+                reason: format!(
+                    "range {start}..{end} is outside a string of length {}",
+                    graphemes.len()
+                ),
+            });
+        }
+        Ok(LispAST::String(graphemes[start..end].concat()))
+    }
+
+    /// `(split s sep)`: the pieces between occurrences of `sep` as a list;
+    /// an empty `sep` splits into graphemes.
+    pub fn split(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let s = string("split", &args[0])?;
+        let sep = string("split", &args[1])?;
+        let pieces: Vec<LispAST> = if sep.is_empty() {
+            s.graphemes(true)
+                .map(|g| LispAST::String(g.to_string()))
+                .collect()
+        } else {
+            s.split(sep)
+                .map(|piece| LispAST::String(piece.to_string()))
+                .collect()
+        };
+        Ok(LispAST::List(pieces.into()))
+    }
+
+    /// `(join items)` or `(join items sep)`: a list's elements as text,
+    /// separated by `sep`.
+    pub fn join(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let items = match &args[0] {
+            LispAST::List(items) | LispAST::Set(items) => items,
+            other => {
+                return Err(LispError::TypeMismatch {
+                    form: "join".to_string(),
+                    expected: "List".to_string(),
+                    got: other.type_name().to_string(),
+                });
+            }
+        };
+        let sep = match args.get(1) {
+            Some(sep) => string("join", sep)?,
+            None => "",
+        };
+        Ok(LispAST::String(
+            items.iter().map(text).collect::<Vec<_>>().join(sep),
+        ))
+    }
+
+    pub fn upper(args: &[LispAST]) -> Result<LispAST, LispError> {
+        map_string("upper", args, str::to_uppercase)
+    }
+
+    pub fn lower(args: &[LispAST]) -> Result<LispAST, LispError> {
+        map_string("lower", args, str::to_lowercase)
+    }
+
+    pub fn trim(args: &[LispAST]) -> Result<LispAST, LispError> {
+        map_string("trim", args, |s| s.trim().to_string())
+    }
+
+    /// `(replace s from to)`: every occurrence of `from` replaced by `to`.
+    pub fn replace(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let from = string("replace", &args[1])?;
+        if from.is_empty() {
+            return Err(LispError::Domain {
+                form: "replace".to_string(),
+                reason: "the text to replace is empty".to_string(),
+            });
+        }
+        Ok(LispAST::String(
+            string("replace", &args[0])?.replace(from, string("replace", &args[2])?),
+        ))
+    }
+
+    /// `(format "x = {}, y = {}" x y)`: each `{}` replaced by the next
+    /// argument's text. `{{` and `}}` stand for literal braces.
+    pub fn format(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let template = string("format", &args[0])?;
+        let mut values = args[1..].iter();
+        let mut out = String::new();
+        let mut placeholders = 0;
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('{', Some('{')) | ('}', Some('}')) => {
+                    out.push(c);
+                    chars.next();
+                }
+                ('{', Some('}')) => {
+                    chars.next();
+                    placeholders += 1;
+                    if let Some(value) = values.next() {
+                        out.push_str(&text(value));
+                    }
+                }
+                _ => out.push(c),
+            }
+        }
+        let given = args.len() - 1;
+        if placeholders != given {
+            return Err(LispError::Arity {
+                form: "format".to_string(),
+                expected: format!("{} (one per {{}} after the template)", placeholders + 1),
+                got: args.len(),
+            });
+        }
+        Ok(LispAST::String(out))
+    }
+
+    /// `(number->string x)`, `(number->string x 3)` for three decimal places,
+    /// or with quoted number format options: `(number->string x '(sig 3) '(sci))`.
+    pub fn number_to_string(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let LispAST::Number(n) = &args[0] else {
+            return Err(LispError::TypeMismatch {
+                form: "number->string".to_string(),
+                expected: "Number".to_string(),
+                got: args[0].type_name().to_string(),
+            });
+        };
+        let options = &args[1..];
+        let mut policy = NumberFormat::default();
+        for option in options {
+            match option {
+                LispAST::Number(_) => {
+                    let places = index("number->string", option)?;
+                    policy.precision =
+                        Precision::Decimals(u32::try_from(places).unwrap_or(u32::MAX));
+                }
+                other => policy.apply("number->string", other)?,
+            }
+        }
+        Ok(LispAST::String(policy.format(*n).to_string()))
+    }
+
+    /// `(string->number s)`: the number `s` spells, surrounding whitespace
+    /// allowed. `"NaN"` and `"inf"` parse as decimals but are not numbers here.
+    pub fn string_to_number(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let s = string("string->number", &args[0])?;
+        D512::from_str(s.trim(), Context::default())
+            .ok()
+            .filter(D512::is_finite)
+            .map(LispAST::Number)
+            .ok_or_else(|| LispError::Domain {
+                form: "string->number".to_string(),
+                reason: format!("{s:?} is not a number"),
+            })
+    }
+
+    /// `(length x)`: graphemes in a string, elements in a list or set, or
+    /// entries in a dict.
+    pub fn length(args: &[LispAST]) -> Result<LispAST, LispError> {
+        let n = match &args[0] {
+            LispAST::String(s) => s.graphemes(true).count(),
+            LispAST::List(items) | LispAST::Set(items) => items.len(),
+            LispAST::Dict(entries) => entries.len(),
+            other => {
+                return Err(LispError::TypeMismatch {
+                    form: "length".to_string(),
+                    expected: "String, List, Set or Dict".to_string(),
+                    got: other.type_name().to_string(),
+                });
+            }
+        };
+        Ok(LispAST::Number(D512::from(n)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn s(text: &str) -> LispAST {
+        LispAST::String(text.to_string())
+    }
+
+    fn num(n: i64) -> LispAST {
+        LispAST::Number(D512::from(n))
+    }
+
+    #[test]
+    fn graphemes_not_bytes() {
+        // "e" followed by a combining acute accent is one grapheme.
+        let word = s("cafe\u{301}s");
+        assert_eq!(Native::length(std::slice::from_ref(&word)), Ok(num(5)));
+        assert_eq!(
+            Native::substring(&[word, num(3), num(4)]),
+            Ok(s("e\u{301}"))
+        );
+    }
+
+    #[test]
+    fn format_placeholders() {
+        assert_eq!(
+            Native::format(&[s("{} = {} {{ok}}"), s("x"), num(2)]),
+            Ok(s("x = 2 {ok}"))
+        );
+        assert!(Native::format(&[s("{} {}"), num(1)]).is_err());
+    }
+
+    #[test]
+    fn numbers_round_trip() {
+        let pi = Native::string_to_number(&[s(" 3.14159 ")]).unwrap();
+        assert_eq!(
+            Native::number_to_string(&[pi.clone(), num(2)]),
+            Ok(s("3.14"))
+        );
+        let sig = LispAST::List(vec![LispAST::Symbol("sig".to_string()), num(2)].into());
+        assert_eq!(Native::number_to_string(&[pi, sig]), Ok(s("3.1")));
+        assert!(Native::string_to_number(&[s("pi")]).is_err());
+        for spelled in ["NaN", "inf", "-Infinity"] {
+            assert!(matches!(
+                Native::string_to_number(&[s(spelled)]),
+                Err(LispError::Domain { .. })
+            ));
+        }
+    }
+}
@@ -1,28 +1,44 @@
-use crate::LispAST;
+use crate::{LispAST, NumberFormat, Unit};
 use std::collections::BTreeMap;
+use std::rc::Rc;
 
 pub type EnvMap = BTreeMap<String, LispAST>;
 
+/// Cloning is cheap: clones share their bindings, units and macros until one
+/// of them changes one of those maps, at which point that clone copies it.
 #[derive(Clone)]
 pub struct Environment {
-    pub bindings: EnvMap,
+    /// Read freely; change through [`Environment::set`] and friends.
+    pub bindings: Rc<EnvMap>,
+    /// Units introduced with `defunit`, consulted before the built-in table.
+    /// Change through [`Environment::set_unit`].
+    pub units: Rc<BTreeMap<String, Unit>>,
+    /// How numbers are written when values are rendered.
+    pub number_format: NumberFormat,
+    /// Macros from `defmacro`, as [`LispAST::UserFunc`]s over unevaluated
+    /// arguments. Change through [`Environment::set_macro`].
+    pub macros: Rc<EnvMap>,
 }
 
 impl Environment {
     pub fn new() -> Self {
         Self {
-            bindings: BTreeMap::new(),
+            bindings: Rc::new(BTreeMap::new()),
+            units: Rc::new(BTreeMap::new()),
+            number_format: NumberFormat::default(),
+            macros: Rc::new(BTreeMap::new()),
         }
     }
 
     pub fn extend(&mut self, other: &EnvMap) {
+        let bindings = Rc::make_mut(&mut self.bindings);
         for (k, v) in other {
-            self.bindings.insert(k.clone(), v.clone());
+            bindings.insert(k.clone(), v.clone());
         }
     }
 
     pub fn insert(&mut self, name: &str, value: LispAST) {
-        self.bindings.insert(name.to_string(), value);
+        Rc::make_mut(&mut self.bindings).insert(name.to_string(), value);
     }
 
     pub fn get(&self, name: &str) -> Option<&LispAST> {
@@ -30,6 +46,60 @@ impl Environment {
     }
 
     pub fn set(&mut self, name: String, value: LispAST) {
-        self.bindings.insert(name, value);
+        Rc::make_mut(&mut self.bindings).insert(name, value);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<LispAST> {
+        Rc::make_mut(&mut self.bindings).remove(name)
+    }
+
+    pub fn set_macro(&mut self, name: String, value: LispAST) {
+        Rc::make_mut(&mut self.macros).insert(name, value);
+    }
+
+    pub fn set_unit(&mut self, name: String, unit: Unit) {
+        Rc::make_mut(&mut self.units).insert(name, unit);
+    }
+
+    /// Parse a unit expression, resolving `defunit` names as well as built-ins.
+    ///
+    /// # Errors
+    /// [`mazer_units::UnitError`] for unknown symbols or malformed syntax.
+    pub fn unit(&self, text: &str) -> Result<Unit, mazer_units::UnitError> {
+        Unit::parse_with(text, |symbol| self.units.get(symbol).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_until_written() {
+        let mut env = Environment::new();
+        env.insert("xs", LispAST::List(vec![LispAST::Bool(true)].into()));
+        let mut copy = env.clone();
+        assert!(Rc::ptr_eq(&env.bindings, &copy.bindings));
+        copy.insert("y", LispAST::Bool(false));
+        assert!(!Rc::ptr_eq(&env.bindings, &copy.bindings));
+        assert!(env.get("y").is_none());
+        let (Some(LispAST::List(a)), Some(LispAST::List(b))) = (env.get("xs"), copy.get("xs")) else {
+            panic!("xs is a list in both");
+        };
+        assert!(std::sync::Arc::ptr_eq(a, b));
+    }
+
+    #[test]
+    fn units_and_macros_are_shared_too() {
+        let mut env = Environment::new();
+        env.set_unit("furlong".to_string(), Unit::parse("m").unwrap());
+        env.set_macro("m".to_string(), LispAST::Bool(true));
+        let mut copy = env.clone();
+        assert!(Rc::ptr_eq(&env.units, &copy.units));
+        assert!(Rc::ptr_eq(&env.macros, &copy.macros));
+        copy.set_macro("n".to_string(), LispAST::Bool(false));
+        assert!(Rc::ptr_eq(&env.units, &copy.units));
+        assert!(!Rc::ptr_eq(&env.macros, &copy.macros));
+        assert!(!env.macros.contains_key("n"));
     }
 }
@@ -4,27 +4,91 @@
 //! the CLI renders via `miette`'s fancy reporter. The `Display` text is also
 //! what the wasm/HTML path surfaces, so every message must read well on its own.
 
-use miette::Diagnostic;
+use miette::{Diagnostic, SourceSpan};
 use thiserror::Error;
 
 /// An error produced while tokenizing, parsing, or evaluating Lisp.
 #[derive(Debug, Clone, Error, Diagnostic, PartialEq, Eq)]
 pub enum LispError {
-    /// A closing paren with no matching opener.
-    #[error("unexpected ')'")]
+    /// A closing paren or bracket with no matching opener.
+    #[error("unexpected '{found}' at line {line}, column {column}")]
     #[diagnostic(
         code(mazer::lisp::unexpected_close_paren),
-        help("remove the extra ')' or add the opening '(' it was meant to close")
+        help("remove the extra '{found}' or add the opener it was meant to close")
     )]
-    UnexpectedCloseParen,
+    UnexpectedCloseParen {
+        found: char,
+        line: usize,
+        column: usize,
+        #[label("nothing open here to close")]
+        span: SourceSpan,
+    },
+
+    /// A list closed with `]` or a vector with `)`.
+    #[error("'{opened}' at line {line}, column {column} is closed by '{found}'")]
+    #[diagnostic(
+        code(mazer::lisp::mismatched_delimiter),
+        help("close '(' with ')' and '[' with ']'")
+    )]
+    MismatchedDelimiter {
+        opened: char,
+        found: char,
+        line: usize,
+        column: usize,
+        #[label("opened here")]
+        span: SourceSpan,
+    },
 
     /// Input ended while an expression was still open.
-    #[error("unexpected end of input")]
+    #[error("unexpected end of input: '{opened}' at line {line}, column {column} is never closed")]
     #[diagnostic(
         code(mazer::lisp::unexpected_eof),
-        help("an expression is missing a closing ')'")
+        help("an expression is missing a closing delimiter")
+    )]
+    UnexpectedEof {
+        opened: char,
+        line: usize,
+        column: usize,
+        #[label("opened here")]
+        span: SourceSpan,
+    },
+
+    /// A reader prefix such as `'` with nothing after it.
+    #[error("'{prefix}' at line {line}, column {column} is not followed by an expression")]
+    #[diagnostic(code(mazer::lisp::dangling_prefix))]
+    DanglingPrefix {
+        prefix: String,
+        line: usize,
+        column: usize,
+        #[label("quotes nothing")]
+        span: SourceSpan,
+    },
+
+    /// A string literal was still open when the input ended.
+    #[error("unterminated string literal at line {line}, column {column}")]
+    #[diagnostic(
+        code(mazer::lisp::unterminated_string),
+        help("close the string with '\"'; write \\\" for a quote inside it")
+    )]
+    UnterminatedString {
+        line: usize,
+        column: usize,
+        #[label("string starts here")]
+        span: SourceSpan,
+    },
+
+    /// A `#|` block comment was still open when the input ended.
+    #[error("unterminated block comment at line {line}, column {column}")]
+    #[diagnostic(
+        code(mazer::lisp::unterminated_comment),
+        help("close the comment with '|#'; block comments nest")
     )]
-    UnexpectedEof,
+    UnterminatedComment {
+        line: usize,
+        column: usize,
+        #[label("comment starts here")]
+        span: SourceSpan,
+    },
 
     /// The block contained no expressions to evaluate.
     #[error("empty program: nothing to evaluate")]
@@ -32,11 +96,50 @@ pub enum LispError {
     EmptyProgram,
 
     /// A numeric literal could not be parsed.
-    #[error("'{text}' is not a valid number")]
+    #[error("'{text}' at line {line}, column {column} is not a valid number")]
     #[diagnostic(code(mazer::lisp::bad_number))]
     BadNumber {
         /// The offending text.
         text: String,
+        line: usize,
+        column: usize,
+        #[label("not a number")]
+        span: SourceSpan,
+    },
+
+    /// `get` on a dict without the key and without a default.
+    #[error("{form}: no key {key} in the dict")]
+    #[diagnostic(
+        code(mazer::lisp::missing_key),
+        help("pass a default as a third argument: (get d key default)")
+    )]
+    MissingKey {
+        form: String,
+        /// The key as written.
+        key: String,
+    },
+
+    /// An `import` or `require` could not load or evaluate its file.
+    #[error("cannot import '{path}': {reason}")]
+    #[diagnostic(
+        code(mazer::lisp::import_failed),
+        help("paths are relative to the importing note; .zr notes contribute their eval blocks, .mzl files are plain Lisp")
+    )]
+    ImportFailed {
+        /// The path as written in the import.
+        path: String,
+        reason: String,
+    },
+
+    /// Modules that import each other, directly or through others.
+    #[error("import cycle: {chain}")]
+    #[diagnostic(
+        code(mazer::lisp::import_cycle),
+        help("move the shared definitions into a module both can import")
+    )]
+    ImportCycle {
+        /// The files involved, e.g. `a.zr → b.zr → a.zr`.
+        chain: String,
     },
 
     /// A form was called with the wrong number of arguments.
@@ -97,9 +200,163 @@ pub enum LispError {
     #[diagnostic(code(mazer::lisp::division_by_zero))]
     DivisionByZero,
 
+    /// A number-theory form was given a non-integral (or out-of-range) number.
+    #[error("{form}: expected {expected}, got {value}")]
+    #[diagnostic(
+        code(mazer::lisp::not_an_integer),
+        help("number-theory functions only operate on whole numbers")
+    )]
+    NotAnInteger {
+        /// The form or function name, e.g. `gcd`.
+        form: String,
+        /// What was expected, e.g. `an integer` or `a positive integer`.
+        expected: String,
+        /// The offending number as written.
+        value: String,
+    },
+
+    /// `modinv` was asked for an inverse that does not exist.
+    #[error("{value} has no inverse modulo {modulus}")]
+    #[diagnostic(
+        code(mazer::lisp::no_modular_inverse),
+        help("an inverse exists only when gcd(value, modulus) = 1")
+    )]
+    NoModularInverse {
+        /// The number whose inverse was requested.
+        value: String,
+        /// The modulus.
+        modulus: String,
+    },
+
+    /// Quantities of different dimensions were added, subtracted or converted.
+    #[error("{form}: dimension mismatch between {left} and {right}")]
+    #[diagnostic(
+        code(mazer::lisp::dimension_mismatch),
+        help("only quantities of the same dimension can be added, subtracted or converted")
+    )]
+    DimensionMismatch {
+        /// The form or function name, e.g. `add`.
+        form: String,
+        /// The left operand's unit and dimension, e.g. `m (L)`.
+        left: String,
+        /// The right operand's unit and dimension, e.g. `s (T)`.
+        right: String,
+    },
+
+    /// A unit expression named an unknown unit or was malformed.
+    #[error("{form}: {reason}")]
+    #[diagnostic(
+        code(mazer::lisp::unknown_unit),
+        help(
+            "write units as symbols with optional SI prefixes, joined by * and /, e.g. kg*m/s^2, or add one with defunit"
+        )
+    )]
+    UnknownUnit {
+        /// The form or function name, e.g. `qty`.
+        form: String,
+        /// What went wrong, e.g. `unknown unit 'furlong'`.
+        reason: String,
+    },
+
+    /// A function was applied outside its domain, e.g. `(ln 0)`.
+    #[error("{form}: {reason}")]
+    #[diagnostic(code(mazer::lisp::domain))]
+    Domain {
+        /// The form or function name, e.g. `ln`.
+        form: String,
+        /// What went wrong, e.g. `ln is undefined at 0`.
+        reason: String,
+    },
+
+    /// A macro kept expanding into further macro calls.
+    #[error("macro {name}: {reason}")]
+    #[diagnostic(
+        code(mazer::lisp::macro_expansion),
+        help("a macro whose expansion calls itself needs a case that stops")
+    )]
+    MacroExpansion {
+        /// The macro being expanded when the limit was reached.
+        name: String,
+        /// What went wrong, e.g. `expansion did not finish after 512 steps`.
+        reason: String,
+    },
+
+    /// A `defmacro` or `defunc` parameter list is malformed.
+    #[error("{form} {name}: {reason}")]
+    #[diagnostic(
+        code(mazer::lisp::parameter_list),
+        help("write parameters as symbols, then any after &optional, then one after &rest")
+    )]
+    ParameterList {
+        /// The defining form, e.g. `defmacro`.
+        form: String,
+        /// The name being defined.
+        name: String,
+        /// What went wrong, e.g. `&rest must be followed by exactly one parameter`.
+        reason: String,
+    },
+
+    /// `solve` was given an equation or system outside what it can solve.
+    #[error("solve: {reason}")]
+    #[diagnostic(
+        code(mazer::lisp::unsolvable),
+        help(
+            "solve handles linear, quadratic and simple rational equations in one unknown, and linear systems"
+        )
+    )]
+    Unsolvable {
+        /// Why, e.g. `the equation has degree 3 in x`.
+        reason: String,
+    },
+
+    /// An `assert` or `expect` in a note did not hold.
+    #[error("assertion failed: {expr}: expected {expected}, got {actual}")]
+    #[diagnostic(
+        code(mazer::lisp::assertion_failed),
+        help("the note's worked answer no longer matches what it computes")
+    )]
+    AssertionFailed {
+        /// The checked expression as written, e.g. `(* 6 7)`.
+        expr: String,
+        /// The expected value, with any tolerance, e.g. `42 ± 0.01`.
+        expected: String,
+        /// The value the expression actually produced.
+        actual: String,
+    },
+
+    /// Raised by user code with `(error "message" data)`.
+    #[error("{message}")]
+    #[diagnostic(code(mazer::lisp::raised))]
+    Raised {
+        message: String,
+        /// Optional value carried to a `catch` handler.
+        data: Option<Box<crate::LispAST>>,
+    },
+
     /// A pre-rendered error message carried through the AST (e.g. a parse failure
     /// stored as a `LispAST::Error` node during document build).
     #[error("{0}")]
     #[diagnostic(code(mazer::lisp::message))]
     Message(String),
 }
+
+impl LispError {
+    /// This error as a value a `catch` handler can inspect: its diagnostic
+    /// code, its message, and for `(error "msg" data)` the data.
+    #[must_use]
+    pub fn into_value(self) -> crate::LispAST {
+        let code = self.code().map(|c| c.to_string()).unwrap_or_default();
+        match self {
+            LispError::Raised { message, data } => crate::LispAST::ErrorValue {
+                code,
+                message,
+                data,
+            },
+            other => crate::LispAST::ErrorValue {
+                code,
+                message: other.to_string(),
+                data: None,
+            },
+        }
+    }
+}
@@ -7,6 +7,8 @@
 //! - **Arity**: How many arguments the function accepts
 //! - **Documentation**: A human-readable description
 //! - **Symbol**: Optional MathML symbol override for rendering
+//! - **Implementation**: Optional native that evaluates it (`impl = Native::add`),
+//!   registered under every name by `with_native` with its arity checked first
 //!
 //! # Example
 //!
@@ -22,6 +24,8 @@ use mazer_macros::FuncMeta;
 use serde::Serialize;
 use strum_macros::EnumIter;
 
+use crate::{LispAST, LispError};
+
 /// Specifies how many arguments a function accepts.
 ///
 /// This is used for validation and LSP completions.
@@ -67,6 +71,31 @@ impl Arguments {
             Arguments::Variadic => None,
         }
     }
+
+    /// The accepted counts as an arity error states them: "2", "2 or 3",
+    /// "at least 1".
+    pub fn expected(&self) -> String {
+        match self {
+            Arguments::Fixed(n) => n.to_string(),
+            Arguments::Range(min, max) if max - min == 1 => format!("{min} or {max}"),
+            Arguments::Range(min, max) => format!("{min} to {max}"),
+            Arguments::Atleast(min) => format!("at least {min}"),
+            Arguments::Variadic => "any number".to_string(),
+        }
+    }
+
+    /// Fail with [`LispError::Arity`] against `form` unless `args` is a valid
+    /// number of arguments.
+    pub fn check(&self, form: &str, args: &[LispAST]) -> Result<(), LispError> {
+        if self.is_valid(args.len()) {
+            return Ok(());
+        }
+        Err(LispError::Arity {
+            form: form.to_string(),
+            expected: self.expected(),
+            got: args.len(),
+        })
+    }
 }
 
 /// Represents the kind of function (native or user-defined).
@@ -86,17 +115,28 @@ pub enum FuncKind {
 ///
 /// # Categories
 ///
-/// - **Core**: `define`, `defunc`, `quote`, `string`
+/// - **Core**: `define`, `defunc`, `quote`, `string`, `defmacro`, `quasiquote`, `import`, `require`
 /// - **Arithmetic**: `+`, `-`, `*`, `/`, `^`, `frac`, `sqrt`, `root`
 /// - **Comparison**: `=`, `!=`, `<`, `>`, `<=`, `>=`, `approx`
-/// - **Calculus**: `integral`, `sum`, `prod`, `limit`, `derivative`, `partial`
+/// - **Calculus**: `integral`, `sum`, `prod`, `limit`, `derivative`, `partial`, `dd`, `series`, `O`
 /// - **Trigonometry**: `sin`, `cos`, `tan`, `cot`, `sec`, `csc`, `arcsin`, `arccos`, `arctan`
 /// - **Logarithms**: `ln`, `log`, `exp`
 /// - **Other Math**: `abs`, `floor`, `ceil`, `factorial`, `binom`
+/// - **Number Theory**: `mod`, `rem`, `quotient`, `gcd`, `lcm`, `prime?`, `next-prime`,
+///   `factorize`, `factors`, `powmod`, `modinv`, `totient`
+/// - **Units**: `qty`, `convert`, `defunit`
+/// - **Formatting**: `fmt-number`
+/// - **Checks**: `assert`, `expect`
+/// - **Errors**: `error`, `try`, `error-message`, `error-kind`, `error-data`, `error?`
+/// - **Debugging**: `reflect`, `print`, `debug`
+/// - **Polynomials**: `poly`, `poly-div`, `poly-gcd`, `poly-deriv`, `poly-eval`, `poly-roots`, `solve`, `complex`
 /// - **Linear Algebra**: `matrix`, `vec`, `det`
 /// - **Tables**: `table`
 /// - **Sets**: `set`, `in`, `notin`, `subset`, `superset`, `union`, `intersect`
-/// - **Logic**: `and`, `or`, `not`, `implies`, `iff`, `forall`, `exists`
+/// - **Dicts**: `dict`, `get`, `assoc`, `dissoc`, `keys`, `vals`, `merge`
+/// - **Strings**: `concat`, `substring`, `split`, `join`, `upper`, `lower`, `trim`,
+///   `replace`, `format`, `number->string`, `string->number`, `length`
+/// - **Logic**: `and`, `or`, `not`, `implies`, `iff`, `forall`, `exists`, `truth-table`
 /// - **Grouping**: `paren`, `bracket`, `brace`
 /// - **Annotations**: `text`, `subscript`, `superscript`, `overline`, `hat`, `dot`, `ddot`, `arrow`, `box`
 #[derive(Debug, EnumIter, FuncMeta)]
@@ -109,14 +149,30 @@ pub enum ShowFunc {
     #[func(names = ["define"], arity = Fixed(2), doc = "Define a variable: (define name value)")]
     Define,
 
-    /// Define a function: `(defunc name (params...) body)`
-    #[func(names = ["defunc"], arity = AtLeast(3), doc = "Define a function: (defunc name (params) body)")]
+    /// Define a function: `(defunc name (params...) "doc" body...)`
+    #[func(names = ["defunc"], arity = AtLeast(3), doc = "Define a function: (defunc name (a &optional (b 1) &rest more) \"docstring\" body...); the docstring is optional, a missing optional without a default is false, and &rest collects the remaining arguments as a list")]
     Defunc,
 
     /// Quote an expression to prevent evaluation: `(quote expr)` or `'expr`
     #[func(names = ["quote"], arity = Fixed(1), doc = "Quote an expression to prevent evaluation")]
     Quote,
 
+    /// Define a macro over unevaluated arguments: `(defmacro name (params...) body)`
+    #[func(names = ["defmacro"], arity = AtLeast(3), doc = "Define a macro: (defmacro name (params [&rest rest]) template)")]
+    Defmacro,
+
+    /// Quote with holes: `` `(a ,b ,@rest) ``
+    #[func(names = ["quasiquote"], arity = Fixed(1), doc = "Quote a template, filling in ,unquote and ,@unquote-splicing")]
+    Quasiquote,
+
+    /// Load another note's definitions: `(import "lib.zr" :as ns :only (names...))`
+    #[func(names = ["import"], arity = AtLeast(1), doc = "Import a .zr or .mzl module: (import \"lib.zr\" [:as ns] [:only (names...)]) binds ns/name")]
+    Import,
+
+    /// Load another note's definitions unqualified: `(require "lib.zr")`
+    #[func(names = ["require"], arity = AtLeast(1), doc = "Import a module's definitions unqualified: (require \"lib.zr\")")]
+    Require,
+
     /// String literal or concatenation
     #[func(names = ["string"], arity = Variadic, doc = "String literal or concatenation")]
     String,
@@ -130,23 +186,23 @@ pub enum ShowFunc {
     Jux,
 
     /// Addition: `(+ a b ...)` or `(add a b ...)`
-    #[func(names = ["+", "add"], arity = Variadic, doc = "Addition: (+ a b ...) or (add a b ...)", symbol = "+")]
+    #[func(names = ["+", "add"], arity = AtLeast(1), doc = "Addition: (+ a b ...) or (add a b ...)", symbol = "+", impl = Native::add)]
     Add,
 
     /// Subtraction or negation: `(- a b)` or `(- a)`
-    #[func(names = ["-", "sub"], arity = AtLeast(1), doc = "Subtraction: (- a b) or negation: (- a)", symbol = "-")]
+    #[func(names = ["-", "sub"], arity = AtLeast(1), doc = "Subtraction: (- a b) or negation: (- a)", symbol = "-", impl = Native::sub)]
     Sub,
 
     /// Multiplication: `(* a b ...)`
-    #[func(names = ["*", "mul"], arity = Variadic, doc = "Multiplication: (* a b ...)", symbol = "×")]
+    #[func(names = ["*", "mul"], arity = AtLeast(1), doc = "Multiplication: (* a b ...)", symbol = "×", impl = Native::mul)]
     Mul,
 
     /// Division: `(/ a b)` renders as fraction
-    #[func(names = ["/", "div"], arity = AtLeast(1), doc = "Division: (/ a b) renders as fraction", symbol = "÷")]
+    #[func(names = ["/", "div"], arity = AtLeast(1), doc = "Division: (/ a b) renders as fraction", symbol = "÷", impl = Native::div)]
     Div,
 
     /// Exponentiation: `(^ base exp)` or `(pow base exp)`
-    #[func(names = ["^", "pow"], arity = Fixed(2), doc = "Exponentiation: (^ base exp) or (pow base exp)")]
+    #[func(names = ["^", "pow"], arity = Fixed(2), doc = "Exponentiation: (^ base exp) or (pow base exp)", impl = Native::pow)]
     Pow,
 
     /// Fraction: `(frac num denom)`
@@ -154,7 +210,7 @@ pub enum ShowFunc {
     Frac,
 
     /// Square root: `(sqrt x)`
-    #[func(names = ["sqrt"], arity = Fixed(1), doc = "Square root: (sqrt x)")]
+    #[func(names = ["sqrt"], arity = Fixed(1), doc = "Square root: (sqrt x)", impl = Native::sqrt)]
     Sqrt,
 
     /// Nth root: `(root n x)` for the nth root of x
@@ -166,19 +222,19 @@ pub enum ShowFunc {
     // =========================================================================
 
     /// Equality: `(= a b)` or `(eq a b)`
-    #[func(names = ["=", "eq"], arity = AtLeast(2), doc = "Equality: (= a b) or (eq a b)", symbol = "=")]
+    #[func(names = ["=", "eq"], arity = AtLeast(2), doc = "Equality: (= a b) or (eq a b)", symbol = "=", impl = Native::eq)]
     Eq,
 
     /// Not equal: `(!= a b)` or `(neq a b)`
-    #[func(names = ["!=", "neq"], arity = Fixed(2), doc = "Not equal: (!= a b) or (neq a b)", symbol = "≠")]
+    #[func(names = ["!=", "neq"], arity = Fixed(2), doc = "Not equal: (!= a b) or (neq a b)", symbol = "≠", impl = Native::ne)]
     Neq,
 
     /// Greater than: `(> a b)`
-    #[func(names = [">", "gt"], arity = AtLeast(2), doc = "Greater than: (> a b)", symbol = ">")]
+    #[func(names = [">", "gt"], arity = AtLeast(2), doc = "Greater than: (> a b)", symbol = ">", impl = Native::gt)]
     Gt,
 
     /// Less than: `(< a b)`
-    #[func(names = ["<", "lt"], arity = AtLeast(2), doc = "Less than: (< a b)", symbol = "<")]
+    #[func(names = ["<", "lt"], arity = AtLeast(2), doc = "Less than: (< a b)", symbol = "<", impl = Native::lt)]
     Lt,
 
     /// Approximate equality: `(approx a b)`
@@ -186,11 +242,11 @@ pub enum ShowFunc {
     Approx,
 
     /// Greater than or equal: `(>= a b)`
-    #[func(names = [">=", "geq"], arity = AtLeast(2), doc = "Greater than or equal: (>= a b)", symbol = "≥")]
+    #[func(names = [">=", "geq"], arity = AtLeast(2), doc = "Greater than or equal: (>= a b)", symbol = "≥", impl = Native::ge)]
     Geq,
 
     /// Less than or equal: `(<= a b)`
-    #[func(names = ["<=", "leq"], arity = AtLeast(2), doc = "Less than or equal: (<= a b)", symbol = "≤")]
+    #[func(names = ["<=", "leq"], arity = AtLeast(2), doc = "Less than or equal: (<= a b)", symbol = "≤", impl = Native::le)]
     Leq,
 
     // =========================================================================
@@ -225,20 +281,28 @@ pub enum ShowFunc {
     #[func(names = ["dd"], arity = Range(2, 3), doc = "Leibniz differential: (dd x t) for dx/dt or (dd x t n) for dⁿx/dtⁿ")]
     Dd,
 
+    /// Taylor series: `(series f x a n)` renders as Σₖ f⁽ᵏ⁾(a)/k! (x − a)ᵏ
+    #[func(names = ["series", "taylor"], arity = Fixed(4), doc = "Taylor series: (series (exp x) x 0 5) evaluates to the degree-5 polynomial plus O(x⁶); derivatives are symbolic for + - * / ^ exp ln sin cos tan sqrt, numeric otherwise")]
+    Series,
+
+    /// Order of growth: `(O (^ x 6))` renders as O(x⁶)
+    #[func(names = ["O", "bigo"], arity = Fixed(1), doc = "Big-O remainder term: (O (^ x 6)) renders as O(x⁶)")]
+    BigO,
+
     // =========================================================================
     // Trigonometric Functions
     // =========================================================================
 
     /// Sine: `(sin x)`
-    #[func(names = ["sin"], arity = Fixed(1), doc = "Sine: (sin x)")]
+    #[func(names = ["sin"], arity = Fixed(1), doc = "Sine: (sin x)", impl = Native::sin)]
     Sin,
 
     /// Cosine: `(cos x)`
-    #[func(names = ["cos"], arity = Fixed(1), doc = "Cosine: (cos x)")]
+    #[func(names = ["cos"], arity = Fixed(1), doc = "Cosine: (cos x)", impl = Native::cos)]
     Cos,
 
     /// Tangent: `(tan x)`
-    #[func(names = ["tan"], arity = Fixed(1), doc = "Tangent: (tan x)")]
+    #[func(names = ["tan"], arity = Fixed(1), doc = "Tangent: (tan x)", impl = Native::tan)]
     Tan,
 
     /// Cotangent: `(cot x)`
@@ -270,7 +334,7 @@ pub enum ShowFunc {
     // =========================================================================
 
     /// Natural logarithm: `(ln x)`
-    #[func(names = ["ln"], arity = Fixed(1), doc = "Natural logarithm: (ln x)")]
+    #[func(names = ["ln"], arity = Fixed(1), doc = "Natural logarithm: (ln x)", impl = Native::ln)]
     Ln,
 
     /// Logarithm: `(log x)` or `(log base x)`
@@ -278,7 +342,7 @@ pub enum ShowFunc {
     Log,
 
     /// Exponential: `(exp x)` renders as e^x
-    #[func(names = ["exp"], arity = Fixed(1), doc = "Exponential: (exp x) renders as e^x")]
+    #[func(names = ["exp"], arity = Fixed(1), doc = "Exponential: (exp x) renders as e^x", impl = Native::exp)]
     Exp,
 
     // =========================================================================
@@ -305,6 +369,174 @@ pub enum ShowFunc {
     #[func(names = ["binom", "nCr"], arity = Fixed(2), doc = "Binomial coefficient: (binom n k)")]
     Binom,
 
+    // =========================================================================
+    // Number Theory
+    // =========================================================================
+
+    /// Modulo (floored): `(mod a n)` renders as a mod n
+    #[func(names = ["mod", "modulo"], arity = Fixed(2), doc = "Modulo, taking the sign of n: (mod a n) renders as a mod n", symbol = "mod", impl = Native::modulo)]
+    Mod,
+
+    /// Remainder (truncated): `(rem a n)`
+    #[func(names = ["rem", "remainder"], arity = Fixed(2), doc = "Remainder of truncated division, taking the sign of a: (rem a n)", impl = Native::rem)]
+    Rem,
+
+    /// Integer quotient: `(quotient a n)`
+    #[func(names = ["quotient", "quot"], arity = Fixed(2), doc = "Truncated integer division: (quotient a n)", impl = Native::quotient)]
+    Quotient,
+
+    /// Greatest common divisor: `(gcd a b ...)`
+    #[func(names = ["gcd"], arity = AtLeast(1), doc = "Greatest common divisor: (gcd a b ...)", impl = Native::gcd)]
+    Gcd,
+
+    /// Least common multiple: `(lcm a b ...)`
+    #[func(names = ["lcm"], arity = AtLeast(1), doc = "Least common multiple: (lcm a b ...)", impl = Native::lcm)]
+    Lcm,
+
+    /// Primality test: `(prime? n)`
+    #[func(names = ["prime?"], arity = Fixed(1), doc = "Primality test: (prime? n) is true when n is prime", impl = Native::is_prime)]
+    IsPrime,
+
+    /// Next prime: `(next-prime n)`
+    #[func(names = ["next-prime"], arity = Fixed(1), doc = "Smallest prime greater than n: (next-prime n)", impl = Native::next_prime)]
+    NextPrime,
+
+    /// Prime factorisation: `(factorize n)` evaluates to `((p k) ...)`
+    #[func(names = ["factorize", "factorise"], arity = Fixed(1), doc = "Prime factorisation: (factorize 360) evaluates to ((2 3) (3 2) (5 1))", impl = Native::factorize)]
+    Factorize,
+
+    /// Product of prime powers: `(factors (2 3) (5 1))` renders as 2³ × 5
+    #[func(names = ["factors"], arity = Variadic, doc = "Product of prime powers: (factors (2 3) (5 1)) renders as 2³ × 5")]
+    Factors,
+
+    /// Modular exponentiation: `(powmod b e m)` renders as bᵉ mod m
+    #[func(names = ["powmod"], arity = Fixed(3), doc = "Modular exponentiation: (powmod b e m) renders as bᵉ mod m", impl = Native::powmod)]
+    PowMod,
+
+    /// Modular inverse: `(modinv a m)` renders as a⁻¹ mod m
+    #[func(names = ["modinv"], arity = Fixed(2), doc = "Modular inverse: (modinv a m) renders as a⁻¹ mod m", impl = Native::modinv)]
+    ModInv,
+
+    /// Euler's totient: `(totient n)` renders as φ(n)
+    #[func(names = ["totient"], arity = Fixed(1), doc = "Euler's totient: (totient n) renders as φ(n)", impl = Native::totient)]
+    Totient,
+
+    // =========================================================================
+    // Units
+    // =========================================================================
+
+    /// Dimensioned quantity: `(qty 9.81 m/s^2)` renders as 9.81 m/s²
+    #[func(names = ["qty"], arity = Fixed(2), doc = "Dimensioned quantity: (qty 9.81 m/s^2) renders with upright unit symbols; + and - check dimensions, * / and pow combine them. SI prefixes, N, J, W, Pa, Hz, C, V, Ω and common non-SI units (in, ft, mi, lb, eV, atm, bar, L, min, h, °C, °F) resolve")]
+    Qty,
+
+    /// Unit conversion: `(convert (qty 60 mi/h) m/s)` renders as 60 mi/h → m/s
+    #[func(names = ["convert"], arity = Fixed(2), doc = "Unit conversion: (convert (qty 60 mi/h) m/s); handles affine scales such as °C and °F, and errors on mismatched dimensions")]
+    Convert,
+
+    /// Define a unit: `(defunit furlong (qty 201.168 m))` renders as 1 furlong = 201.168 m
+    #[func(names = ["defunit"], arity = Fixed(2), doc = "Define a unit: (defunit furlong (qty 201.168 m)); the name is then usable in qty and convert")]
+    Defunit,
+
+    // =========================================================================
+    // Formatting
+    // =========================================================================
+
+    /// Number formatting: `(fmt-number 123456 (sig 3) (sci))` renders as 1.23 × 10⁵
+    #[func(names = ["fmt-number"], arity = AtLeast(1), doc = "Format a number or quantity: (fmt-number x (sig 3) (sci)). Options are (sig n), (digits n), (full), (plain), (sci), (eng), (group) and (trim); they override the document's numbers: policy")]
+    FmtNumber,
+
+    // =========================================================================
+    // Checks
+    // =========================================================================
+
+    /// Assertion: `(assert (= x 4))` renders as its condition, x = 4
+    #[func(names = ["assert"], arity = Range(1, 2), doc = "Fail the build unless the condition is true: (assert (= (* 6 7) 42)) or (assert cond \"message\"); mazer check runs every note's assertions", impl = Native::assert)]
+    Assert,
+
+    /// Expected value: `(expect (* 6 7) 42 0.01)` renders as 6 · 7 = 42 ± 0.01
+    #[func(names = ["expect"], arity = Range(2, 3), doc = "Fail the build unless expr equals value, or is within tolerance of it: (expect (/ 1 3) 0.333 0.001); quantities compare in the expected value's unit")]
+    Expect,
+
+    // =========================================================================
+    // Errors
+    // =========================================================================
+
+    /// Raise an error: `(error "negative input" x)`
+    #[func(names = ["error", "raise"], arity = Range(1, 2), doc = "Raise an error with a message and optional data: (error \"negative input\" x); (error e) re-raises a caught error", impl = Native::raise)]
+    Error,
+
+    /// Recover from an error: `(try expr (catch e handler))`
+    #[func(names = ["try"], arity = Fixed(2), doc = "Evaluate expr, or on error the handler with e bound to it: (try (/ 1 0) (catch e (error-kind e))). Inspect e with error-message, error-kind (a mazer::lisp::* code) and error-data")]
+    Try,
+
+    /// A caught error's message: `(error-message e)`
+    #[func(names = ["error-message"], arity = Fixed(1), doc = "A caught error's message, as it would have been reported: (error-message e)", impl = Native::error_message)]
+    ErrorMessage,
+
+    /// A caught error's diagnostic code: `(error-kind e)`
+    #[func(names = ["error-kind"], arity = Fixed(1), doc = "A caught error's diagnostic code, e.g. \"mazer::lisp::division_by_zero\": (error-kind e)", impl = Native::error_kind)]
+    ErrorKind,
+
+    /// The data given to `error`: `(error-data e)`
+    #[func(names = ["error-data"], arity = Fixed(1), doc = "The data given to error, or an empty list: (error-data e)", impl = Native::error_data)]
+    ErrorData,
+
+    /// Whether a value is a caught error: `(error? x)`
+    #[func(names = ["error?"], arity = Fixed(1), doc = "Whether x is a caught error: (error? x)", impl = Native::is_error)]
+    IsError,
+
+    // =========================================================================
+    // Debugging
+    // =========================================================================
+
+    /// Type of a value: `(reflect x)`
+    #[func(names = ["reflect"], arity = Fixed(1), doc = "The type of a value as a symbol: (reflect 1) is Number", impl = Native::reflect)]
+    Reflect,
+
+    /// Print a line to the block's output: `(print a b ...)`
+    #[func(names = ["print"], arity = Variadic, doc = "Print the arguments on one line, shown below the eval block: (print \"x = \" x)", impl = Native::print)]
+    Print,
+
+    /// Write values as source to the block's output: `(debug a b ...)`
+    #[func(names = ["debug"], arity = Variadic, doc = "Print each argument as source, laid out across lines when long, with its type; shown below the eval block: (debug x)", impl = Native::debug)]
+    Debug,
+
+    // =========================================================================
+    // Polynomials
+    // =========================================================================
+
+    /// Polynomial: `(poly 1 -3 2)` renders as x² − 3x + 2
+    #[func(names = ["poly"], arity = AtLeast(1), doc = "Polynomial from descending coefficients: (poly 1 -3 2) is x² − 3x + 2; a leading quoted symbol sets the variable. +, -, * and pow accept polynomials", impl = Native::poly)]
+    Poly,
+
+    /// Polynomial long division: `(poly-div a b)` gives `(quotient remainder)`
+    #[func(names = ["poly-div"], arity = Fixed(2), doc = "Polynomial long division: (poly-div a b) gives the list (quotient remainder)", impl = Native::poly_div)]
+    PolyDiv,
+
+    /// Polynomial gcd: `(poly-gcd a b)`, made monic
+    #[func(names = ["poly-gcd"], arity = Fixed(2), doc = "Monic greatest common divisor of two polynomials", impl = Native::poly_gcd)]
+    PolyGcd,
+
+    /// Polynomial derivative: `(poly-deriv p)` renders as p′
+    #[func(names = ["poly-deriv"], arity = Fixed(1), doc = "Derivative of a polynomial: (poly-deriv p)", impl = Native::poly_deriv)]
+    PolyDeriv,
+
+    /// Polynomial evaluation: `(poly-eval p x)` renders as p(x)
+    #[func(names = ["poly-eval"], arity = Fixed(2), doc = "Evaluate a polynomial at a number: (poly-eval p 2)", impl = Native::poly_eval)]
+    PolyEval,
+
+    /// Polynomial roots: `(poly-roots p)`
+    #[func(names = ["poly-roots"], arity = Fixed(1), doc = "All roots with multiplicity: real ones ascending, then complex ones as (complex re im)", impl = Native::poly_roots)]
+    PolyRoots,
+
+    /// Equation solving: `(solve (quote (= lhs rhs)) (quote x))` renders as solve(lhs = rhs, x)
+    #[func(names = ["solve"], arity = Fixed(2), doc = "Solve (= lhs rhs) for a variable, giving the solution set: linear, quadratic (complex roots as (complex re im)) and simple rational equations; a list of equations and a list of unknowns solves a linear system", impl = Native::solve)]
+    Solve,
+
+    /// Complex number: `(complex 1 -2)` renders as 1 − 2i
+    #[func(names = ["complex"], arity = Fixed(2), doc = "Complex number as returned by poly-roots: (complex re im) renders as re + im·i")]
+    Complex,
+
     // =========================================================================
     // Linear Algebra
     // =========================================================================
@@ -314,7 +546,7 @@ pub enum ShowFunc {
     Matrix,
 
     /// Column vector: `(vec a b c ...)`
-    #[func(names = ["vec", "vector"], arity = Variadic, doc = "Column vector: (vec a b c ...)")]
+    #[func(names = ["vec", "vector"], arity = Variadic, doc = "Column vector: (vec a b c ...) or [a b c]", impl = Native::vector)]
     Vec,
 
     /// Determinant: `(det (row1) (row2) ...)`
@@ -326,7 +558,8 @@ pub enum ShowFunc {
     // =========================================================================
 
     /// Pretty table: `(table (header ...) (row ...) ...)` with optional
-    /// `(align ...)` and `(style grid|clean|plain)` directives.
+    /// `(align ...)` and `(style grid|clean|plain)` directives, or their
+    /// keyword forms `:align [l r]` and `:style clean`.
     #[func(names = ["table"], arity = Variadic, doc = "Pretty table: (table (header c1 c2) (row a b) ...) with optional (align left right) and (style grid|clean|plain)")]
     Table,
 
@@ -343,43 +576,127 @@ pub enum ShowFunc {
     // =========================================================================
 
     /// Set: `(set a b c ...)` renders as {a, b, c}
-    #[func(names = ["set"], arity = Variadic, doc = "Set: (set a b c ...) renders as {a, b, c}")]
+    #[func(names = ["set"], arity = Variadic, doc = "Set: (set a b c ...) renders as {a, b, c}", impl = Native::set)]
     Set,
 
     /// Element of: `(in x S)` renders as x ∈ S
-    #[func(names = ["in"], arity = Fixed(2), doc = "Element of: (in x S) renders as x ∈ S", symbol = "∈")]
+    #[func(names = ["in"], arity = Fixed(2), doc = "Element of: (in x S) renders as x ∈ S", symbol = "∈", impl = Native::member)]
     In,
 
     /// Not element of: `(notin x S)` renders as x ∉ S
-    #[func(names = ["notin"], arity = Fixed(2), doc = "Not element of: (notin x S) renders as x ∉ S", symbol = "∉")]
+    #[func(names = ["notin"], arity = Fixed(2), doc = "Not element of: (notin x S) renders as x ∉ S", symbol = "∉", impl = Native::not_member)]
     NotIn,
 
     /// Subset: `(subset A B)` renders as A ⊆ B
-    #[func(names = ["subset"], arity = Fixed(2), doc = "Subset: (subset A B) renders as A ⊆ B", symbol = "⊆")]
+    #[func(names = ["subset"], arity = Fixed(2), doc = "Subset: (subset A B) renders as A ⊆ B", symbol = "⊆", impl = Native::subset)]
     Subset,
 
     /// Superset: `(superset A B)` renders as A ⊇ B
-    #[func(names = ["superset"], arity = Fixed(2), doc = "Superset: (superset A B) renders as A ⊇ B", symbol = "⊇")]
+    #[func(names = ["superset"], arity = Fixed(2), doc = "Superset: (superset A B) renders as A ⊇ B", symbol = "⊇", impl = Native::superset)]
     Superset,
 
     /// Union: `(union A B)` renders as A ∪ B
-    #[func(names = ["union"], arity = AtLeast(2), doc = "Union: (union A B) renders as A ∪ B", symbol = "∪")]
+    #[func(names = ["union"], arity = Variadic, doc = "Union: (union A B) renders as A ∪ B", symbol = "∪", impl = Native::union)]
     Union,
 
     /// Intersection: `(intersect A B)` renders as A ∩ B
-    #[func(names = ["intersect"], arity = AtLeast(2), doc = "Intersection: (intersect A B) renders as A ∩ B", symbol = "∩")]
+    #[func(names = ["intersect"], arity = AtLeast(1), doc = "Intersection: (intersect A B) renders as A ∩ B", symbol = "∩", impl = Native::intersect)]
     Intersect,
 
+    // =========================================================================
+    // Dicts
+    // =========================================================================
+
+    /// Dict: `(dict :a 1 :b 2)` renders as a two-column key/value table
+    #[func(names = ["dict"], arity = Variadic, doc = "Dict: (dict :a 1 :b 2) renders as a key/value table", impl = Native::dict)]
+    Dict,
+
+    /// Lookup: `(get d key)` or `(get d key default)`
+    #[func(names = ["get"], arity = Range(2, 3), doc = "Lookup: (get d key) or (get d key default)", impl = Native::get)]
+    Get,
+
+    /// Add pairs: `(assoc d k v ...)` returns a new dict
+    #[func(names = ["assoc"], arity = AtLeast(1), doc = "Add or replace pairs: (assoc d k v ...)", impl = Native::assoc)]
+    Assoc,
+
+    /// Remove keys: `(dissoc d k ...)` returns a new dict
+    #[func(names = ["dissoc"], arity = AtLeast(1), doc = "Remove keys: (dissoc d k ...)", impl = Native::dissoc)]
+    Dissoc,
+
+    /// Keys of a dict, in order: `(keys d)`
+    #[func(names = ["keys"], arity = Fixed(1), doc = "Keys of a dict, in order: (keys d)", impl = Native::keys)]
+    Keys,
+
+    /// Values of a dict, in key order: `(vals d)`
+    #[func(names = ["vals"], arity = Fixed(1), doc = "Values of a dict, in key order: (vals d)", impl = Native::vals)]
+    Vals,
+
+    /// Merge dicts, later ones winning: `(merge d1 d2 ...)`
+    #[func(names = ["merge"], arity = AtLeast(1), doc = "Merge dicts, later ones winning: (merge d1 d2 ...)", impl = Native::merge)]
+    Merge,
+
+    // =========================================================================
+    // Strings
+    // =========================================================================
+
+    /// Concatenate: `(concat a b ...)` joins the arguments' text
+    #[func(names = ["concat"], arity = Variadic, doc = "Concatenate: (concat a b ...) joins the arguments' text", impl = Native::concat)]
+    Concat,
+
+    /// Substring: `(substring s start [end])`, counted in graphemes
+    #[func(names = ["substring"], arity = Range(2, 3), doc = "Substring: (substring s start [end]), counted in graphemes", impl = Native::substring)]
+    Substring,
+
+    /// Split: `(split s sep)` into a list; an empty sep splits into graphemes
+    #[func(names = ["split"], arity = Fixed(2), doc = "Split: (split s sep) into a list; an empty sep splits into graphemes", impl = Native::split)]
+    Split,
+
+    /// Join: `(join items [sep])` a list's text with a separator
+    #[func(names = ["join"], arity = Range(1, 2), doc = "Join: (join items [sep]) a list's text with a separator", impl = Native::join)]
+    Join,
+
+    /// Upper case: `(upper s)`
+    #[func(names = ["upper"], arity = Fixed(1), doc = "Upper case: (upper s)", impl = Native::upper)]
+    Upper,
+
+    /// Lower case: `(lower s)`
+    #[func(names = ["lower"], arity = Fixed(1), doc = "Lower case: (lower s)", impl = Native::lower)]
+    Lower,
+
+    /// Trim surrounding whitespace: `(trim s)`
+    #[func(names = ["trim"], arity = Fixed(1), doc = "Trim surrounding whitespace: (trim s)", impl = Native::trim)]
+    Trim,
+
+    /// Replace every occurrence: `(replace s from to)`
+    #[func(names = ["replace"], arity = Fixed(3), doc = "Replace every occurrence: (replace s from to)", impl = Native::replace)]
+    Replace,
+
+    /// Format: `(format "{} and {}" a b)` fills each {} in turn
+    #[func(names = ["format"], arity = AtLeast(1), doc = "Format: (format \"{} and {}\" a b) fills each {} in turn", impl = Native::format)]
+    Format,
+
+    /// Number to text: `(number->string x [places or quoted format options])`
+    #[func(names = ["number->string"], arity = AtLeast(1), doc = "Number to text: (number->string x [places or quoted format options])", impl = Native::number_to_string)]
+    NumberToString,
+
+    /// Parse a number: `(string->number s)`
+    #[func(names = ["string->number"], arity = Fixed(1), doc = "Parse a number: (string->number s)", impl = Native::string_to_number)]
+    StringToNumber,
+
+    /// Length: `(length x)`, graphemes in a string or elements of a list, set or dict
+    #[func(names = ["length"], arity = Fixed(1), doc = "Length: graphemes in a string, or elements of a list, set or dict", impl = Native::length)]
+    Length,
+
     // =========================================================================
     // Logical Operations
     // =========================================================================
 
-    /// Logical AND: `(and a b)`
-    #[func(names = ["and"], arity = AtLeast(2), doc = "Logical AND: (and a b)", symbol = "∧")]
+    /// Logical AND: `(and a b ...)`, short-circuiting
+    #[func(names = ["and"], arity = Variadic, doc = "Logical AND: (and a b ...), short-circuiting; (and) is true", symbol = "∧")]
     And,
 
-    /// Logical OR: `(or a b)`
-    #[func(names = ["or"], arity = AtLeast(2), doc = "Logical OR: (or a b)", symbol = "∨")]
+    /// Logical OR: `(or a b ...)`, short-circuiting
+    #[func(names = ["or"], arity = Variadic, doc = "Logical OR: (or a b ...), short-circuiting; (or) is false", symbol = "∨")]
     Or,
 
     /// Logical NOT: `(not a)`
@@ -387,21 +704,27 @@ pub enum ShowFunc {
     Not,
 
     /// Implication: `(implies a b)` renders as a ⟹ b
-    #[func(names = ["implies"], arity = Fixed(2), doc = "Implication: (implies a b) renders as a ⟹ b", symbol = "⟹")]
+    #[func(names = ["implies"], arity = Fixed(2), doc = "Implication: (implies a b) renders as a ⟹ b", symbol = "⟹", impl = Native::implies)]
     Implies,
 
     /// Biconditional: `(iff a b)` renders as a ⟺ b
-    #[func(names = ["iff"], arity = Fixed(2), doc = "Biconditional: (iff a b) renders as a ⟺ b", symbol = "⟺")]
+    #[func(names = ["iff"], arity = Fixed(2), doc = "Biconditional: (iff a b) renders as a ⟺ b", symbol = "⟺", impl = Native::iff)]
     Iff,
 
-    /// Universal quantifier: `(forall x P)`
-    #[func(names = ["forall"], arity = Fixed(2), doc = "Universal quantifier: (forall x P)", symbol = "∀")]
+    /// Universal quantifier: `(forall x P)`; evaluates over a finite set as
+    /// `(forall (in x S) P)`
+    #[func(names = ["forall"], arity = Fixed(2), doc = "Universal quantifier: (forall x P); evaluable as (forall (in x S) P) over a finite set", symbol = "∀")]
     ForAll,
 
-    /// Existential quantifier: `(exists x P)`
-    #[func(names = ["exists"], arity = Fixed(2), doc = "Existential quantifier: (exists x P)", symbol = "∃")]
+    /// Existential quantifier: `(exists x P)`; evaluates over a finite set as
+    /// `(exists (in x S) P)`
+    #[func(names = ["exists"], arity = Fixed(2), doc = "Existential quantifier: (exists x P); evaluable as (exists (in x S) P) over a finite set", symbol = "∃")]
     Exists,
 
+    /// Truth table: `(truth-table (quote formula))` evaluates to a `table`
+    #[func(names = ["truth-table"], arity = Fixed(1), doc = "Truth table: (truth-table (quote (implies p q))) evaluates to a (table (header ...) (row ...)) over and/or/not/implies/iff/xor", impl = Native::truth_table)]
+    TruthTable,
+
     // =========================================================================
     // Grouping / Delimiters
     // =========================================================================
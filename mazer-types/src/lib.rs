@@ -1,25 +1,72 @@
 pub mod environment;
 pub mod error;
 pub mod implfuncs;
+pub mod numfmt;
+pub mod pretty;
+
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
 pub use environment::Environment;
 pub use error::LispError;
+pub use mazer_units::{Dimension, Unit};
+pub use numfmt::{FormattedNumber, Notation, NumberFormat, Precision};
 
-#[derive(Debug, Clone, Hash)]
+/// A Lisp value or form. Lists, sets, dicts and function bodies are shared
+/// behind [`Arc`], so cloning a value never copies its contents; build them from
+/// a `Vec` with `.into()`. (`Arc` rather than `Rc` because values travel inside
+/// [`LispError`]s, which diagnostics require to be `Send + Sync`.)
+#[derive(Debug, Clone)]
 pub enum LispAST {
     Error(String),
     Symbol(String),
+    /// `:name`, stored without the colon. Keywords evaluate to themselves and
+    /// name options and keys.
+    Keyword(String),
     Number(fastnum::D512),
+    /// A dimensioned number: `value` is expressed in `unit`.
+    Quantity {
+        value: fastnum::D512,
+        unit: Unit,
+    },
+    /// A polynomial in `var`, coefficients in ascending order of power with no
+    /// trailing zeros (the zero polynomial has none).
+    Poly {
+        var: String,
+        coeffs: Vec<fastnum::D512>,
+    },
     Bool(bool),
     String(String),
-    List(Vec<LispAST>),
+    List(Arc<[LispAST]>),
+    /// A finite set. Elements are unique and kept in [`LispAST::canonical_cmp`]
+    /// order; build one with [`LispAST::set_from`] to maintain that invariant.
+    Set(Arc<[LispAST]>),
+    /// An immutable map. Keys are unique and kept in
+    /// [`LispAST::canonical_cmp`] order; build one with [`LispAST::dict_from`].
+    Dict(Arc<[(LispAST, LispAST)]>),
     Application {
         name: String,
         args: Vec<LispAST>,
     },
     UserFunc {
-        params: Vec<String>,
-        body: Box<LispAST>,
+        /// Parameter names as written, including any `&optional` and `&rest`
+        /// markers.
+        params: Arc<[String]>,
+        /// The default expression of each `&optional` parameter, in order.
+        defaults: Arc<[LispAST]>,
+        body: Arc<LispAST>,
+        /// The docstring of `(defunc name (params...) "doc" body...)`.
+        doc: Option<Arc<str>>,
+    },
+    /// A caught error, as bound by `(try expr (catch e handler))`. Unlike
+    /// [`LispAST::Error`] it evaluates to itself rather than raising.
+    ErrorValue {
+        /// The diagnostic code, e.g. `mazer::lisp::division_by_zero`.
+        code: String,
+        message: String,
+        /// Whatever was passed to `(error "msg" data)`.
+        data: Option<Box<LispAST>>,
     },
     NativeFunc(fn(&[LispAST]) -> Result<LispAST, LispError>),
 }
@@ -29,15 +76,246 @@ impl LispAST {
     #[must_use]
     pub const fn type_name(&self) -> &'static str {
         match self {
-            LispAST::Error(_) => "Error",
+            LispAST::Error(_) | LispAST::ErrorValue { .. } => "Error",
             LispAST::Symbol(_) => "Symbol",
+            LispAST::Keyword(_) => "Keyword",
             LispAST::Number(_) => "Number",
+            LispAST::Quantity { .. } => "Quantity",
+            LispAST::Poly { .. } => "Poly",
             LispAST::Bool(_) => "Bool",
             LispAST::String(_) => "String",
             LispAST::List(_) => "List",
+            LispAST::Set(_) => "Set",
+            LispAST::Dict(_) => "Dict",
             LispAST::Application { .. } => "Application",
             LispAST::UserFunc { .. } => "UserFunc",
             LispAST::NativeFunc(_) => "NativeFunc",
         }
     }
+
+    /// Build a set value, dropping duplicates and sorting the elements.
+    #[must_use]
+    pub fn set_from(items: impl IntoIterator<Item = LispAST>) -> Self {
+        let mut items: Vec<LispAST> = items.into_iter().collect();
+        items.sort_by(LispAST::canonical_cmp);
+        items.dedup();
+        LispAST::Set(items.into())
+    }
+
+    /// Build a dict from key/value pairs. Keys are sorted and, when one repeats,
+    /// the last pair wins.
+    #[must_use]
+    pub fn dict_from(pairs: impl IntoIterator<Item = (LispAST, LispAST)>) -> Self {
+        let mut entries: Vec<(LispAST, LispAST)> = Vec::new();
+        for (key, value) in pairs {
+            match entries.binary_search_by(|(k, _)| k.canonical_cmp(&key)) {
+                Ok(i) => entries[i].1 = value,
+                Err(i) => entries.insert(i, (key, value)),
+            }
+        }
+        LispAST::Dict(entries.into())
+    }
+
+    /// A total order over values, used to keep sets canonical: booleans, then
+    /// numbers (by value), quantities (by dimension, then SI magnitude), polynomials,
+    /// strings, symbols, keywords, lists, sets and dicts (lexicographically),
+    /// errors, unevaluated forms and functions. Two values are `Equal` exactly
+    /// when they are `==`.
+    #[must_use]
+    pub fn canonical_cmp(&self, other: &Self) -> Ordering {
+        const fn rank(v: &LispAST) -> u8 {
+            match v {
+                LispAST::Bool(_) => 0,
+                LispAST::Number(_) => 1,
+                LispAST::Quantity { .. } => 2,
+                LispAST::Poly { .. } => 3,
+                LispAST::String(_) => 4,
+                LispAST::Symbol(_) => 5,
+                LispAST::Keyword(_) => 6,
+                LispAST::List(_) => 7,
+                LispAST::Set(_) => 8,
+                LispAST::Dict(_) => 9,
+                LispAST::Error(_) => 10,
+                LispAST::ErrorValue { .. } => 11,
+                LispAST::Application { .. } => 12,
+                LispAST::UserFunc { .. } => 13,
+                LispAST::NativeFunc(_) => 14,
+            }
+        }
+
+        fn seq(a: &[LispAST], b: &[LispAST]) -> Ordering {
+            a.iter()
+                .zip(b)
+                .map(|(x, y)| x.canonical_cmp(y))
+                .find(|o| o.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len()))
+        }
+
+        match (self, other) {
+            (LispAST::Bool(a), LispAST::Bool(b)) => a.cmp(b),
+            (LispAST::Number(a), LispAST::Number(b)) => number_cmp(a, b),
+            (
+                LispAST::Quantity { value: a, unit: ua },
+                LispAST::Quantity { value: b, unit: ub },
+            ) => ua
+                .dim
+                .cmp(&ub.dim)
+                .then_with(|| number_cmp(&ua.to_si(*a), &ub.to_si(*b))),
+            (
+                LispAST::Poly { var: va, coeffs: a },
+                LispAST::Poly { var: vb, coeffs: b },
+            ) => va.cmp(vb).then_with(|| a.len().cmp(&b.len())).then_with(|| {
+                a.iter()
+                    .zip(b)
+                    .map(|(x, y)| number_cmp(x, y))
+                    .find(|o| o.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            }),
+            (LispAST::String(a), LispAST::String(b))
+            | (LispAST::Symbol(a), LispAST::Symbol(b))
+            | (LispAST::Keyword(a), LispAST::Keyword(b))
+            | (LispAST::Error(a), LispAST::Error(b)) => a.cmp(b),
+            (LispAST::List(a), LispAST::List(b)) | (LispAST::Set(a), LispAST::Set(b)) => seq(a, b),
+            (LispAST::Dict(a), LispAST::Dict(b)) => a
+                .iter()
+                .zip(b.iter())
+                .map(|((ka, va), (kb, vb))| ka.canonical_cmp(kb).then_with(|| va.canonical_cmp(vb)))
+                .find(|o| o.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len())),
+            (
+                LispAST::ErrorValue { code: c1, message: m1, data: d1 },
+                LispAST::ErrorValue { code: c2, message: m2, data: d2 },
+            ) => c1.cmp(c2).then_with(|| m1.cmp(m2)).then_with(|| match (d1, d2) {
+                (Some(a), Some(b)) => a.canonical_cmp(b),
+                (a, b) => a.is_some().cmp(&b.is_some()),
+            }),
+            (
+                LispAST::Application { name: n1, args: a1 },
+                LispAST::Application { name: n2, args: a2 },
+            ) => n1.cmp(n2).then_with(|| seq(a1, a2)),
+            (
+                LispAST::UserFunc {
+                    params: p1,
+                    defaults: d1,
+                    body: b1,
+                    doc: doc1,
+                },
+                LispAST::UserFunc {
+                    params: p2,
+                    defaults: d2,
+                    body: b2,
+                    doc: doc2,
+                },
+            ) => p1
+                .cmp(p2)
+                .then_with(|| seq(d1, d2))
+                .then_with(|| b1.canonical_cmp(b2))
+                .then_with(|| doc1.cmp(doc2)),
+            (LispAST::NativeFunc(a), LispAST::NativeFunc(b)) => (*a as usize).cmp(&(*b as usize)),
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+}
+
+/// Hashes agree with `==`: numbers hash by value and quantities by dimension
+/// and SI magnitude.
+impl Hash for LispAST {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            LispAST::Error(s) | LispAST::Symbol(s) | LispAST::Keyword(s) | LispAST::String(s) => {
+                s.hash(state);
+            }
+            LispAST::Number(n) => n.hash(state),
+            LispAST::Quantity { value, unit } => {
+                unit.dim.hash(state);
+                unit.to_si(*value).hash(state);
+            }
+            LispAST::Poly { var, coeffs } => {
+                var.hash(state);
+                coeffs.hash(state);
+            }
+            LispAST::Bool(b) => b.hash(state),
+            LispAST::List(items) | LispAST::Set(items) => items.hash(state),
+            LispAST::Dict(entries) => entries.hash(state),
+            LispAST::Application { name, args } => {
+                name.hash(state);
+                args.hash(state);
+            }
+            LispAST::UserFunc {
+                params,
+                defaults,
+                body,
+                doc,
+            } => {
+                params.hash(state);
+                defaults.hash(state);
+                body.hash(state);
+                doc.hash(state);
+            }
+            LispAST::ErrorValue { code, message, data } => {
+                code.hash(state);
+                message.hash(state);
+                data.hash(state);
+            }
+            LispAST::NativeFunc(f) => (*f as usize).hash(state),
+        }
+    }
+}
+
+/// Structural equality. Numbers compare by value (`1` equals `1.0`), quantities
+/// by SI magnitude (`1 km` equals `1000 m`), and native functions by address.
+/// Numbers, quantities and polynomials go through [`LispAST::canonical_cmp`],
+/// so a NaN equals itself and `Eq` holds.
+impl PartialEq for LispAST {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (LispAST::Error(a), LispAST::Error(b))
+            | (LispAST::Symbol(a), LispAST::Symbol(b))
+            | (LispAST::Keyword(a), LispAST::Keyword(b))
+            | (LispAST::String(a), LispAST::String(b)) => a == b,
+            (LispAST::Number(_), LispAST::Number(_))
+            | (LispAST::Quantity { .. }, LispAST::Quantity { .. })
+            | (LispAST::Poly { .. }, LispAST::Poly { .. }) => self.canonical_cmp(other).is_eq(),
+            (LispAST::Bool(a), LispAST::Bool(b)) => a == b,
+            (
+                LispAST::ErrorValue { code: c1, message: m1, data: d1 },
+                LispAST::ErrorValue { code: c2, message: m2, data: d2 },
+            ) => c1 == c2 && m1 == m2 && d1 == d2,
+            (LispAST::List(a), LispAST::List(b)) | (LispAST::Set(a), LispAST::Set(b)) => {
+                Arc::ptr_eq(a, b) || a == b
+            }
+            (LispAST::Dict(a), LispAST::Dict(b)) => Arc::ptr_eq(a, b) || a == b,
+            (
+                LispAST::Application { name: n1, args: a1 },
+                LispAST::Application { name: n2, args: a2 },
+            ) => n1 == n2 && a1 == a2,
+            (
+                LispAST::UserFunc {
+                    params: p1,
+                    defaults: d1,
+                    body: b1,
+                    doc: doc1,
+                },
+                LispAST::UserFunc {
+                    params: p2,
+                    defaults: d2,
+                    body: b2,
+                    doc: doc2,
+                },
+            ) => p1 == p2 && d1 == d2 && (Arc::ptr_eq(b1, b2) || b1 == b2) && doc1 == doc2,
+            (LispAST::NativeFunc(a), LispAST::NativeFunc(b)) => std::ptr::fn_addr_eq(*a, *b),
+            _ => false,
+        }
+    }
+}
+
+/// [`LispAST::canonical_cmp`] is a total order that agrees with `==`, NaN
+/// included.
+impl Eq for LispAST {}
+
+/// Decimal order, made total: `-0` equals `0` (as under `==`) and a NaN equals
+/// itself, sorting above every other number.
+fn number_cmp(a: &fastnum::D512, b: &fastnum::D512) -> Ordering {
+    if a == b { Ordering::Equal } else { a.cmp(b) }
 }
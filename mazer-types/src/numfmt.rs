@@ -0,0 +1,351 @@
+//! How numbers are written out: significant figures or fixed decimals,
+//! plain/scientific/engineering notation, digit grouping and trailing-zero
+//! trimming.
+//!
+//! A [`NumberFormat`] lives on the [`crate::Environment`] so every renderer
+//! sees the same policy; documents set it from front matter and blocks or
+//! `(fmt-number x (sig 4))` override it locally. Options are written as small
+//! lists: `(sig 4)`, `(digits 2)`, `(full)`, `(sci)`, `(eng)`, `(plain)`,
+//! `(group)` and `(trim)`, the last two taking an optional `true`/`false`.
+
+use std::fmt;
+
+use fastnum::D512;
+
+use crate::{LispAST, LispError};
+
+/// Separator between digit groups: a thin space, as SI recommends.
+pub const GROUP_SEPARATOR: char = '\u{2009}';
+
+/// Integer or fractional parts longer than this are grouped in threes.
+const GROUP_THRESHOLD: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Precision {
+    /// Every digit the value carries.
+    #[default]
+    Full,
+    /// This many significant figures.
+    Significant(u32),
+    /// This many digits after the decimal point (of the mantissa, in
+    /// scientific or engineering notation).
+    Decimals(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Notation {
+    #[default]
+    Plain,
+    /// One nonzero digit before the point: 1.23×10⁴.
+    Scientific,
+    /// Exponents that are multiples of three: 12.3×10³.
+    Engineering,
+}
+
+/// A number formatting policy. The default writes numbers exactly as
+/// `D512`'s `Display` does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct NumberFormat {
+    pub precision: Precision,
+    pub notation: Notation,
+    /// Separate digit groups with thin spaces.
+    pub grouping: bool,
+    /// Drop trailing zeros after the decimal point.
+    pub trim_zeros: bool,
+}
+
+/// A number laid out by a [`NumberFormat`]: `mantissa × 10^exponent`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormattedNumber {
+    /// Sign and digits, e.g. `-1.23`, possibly with thin-space groups.
+    pub mantissa: String,
+    /// The power of ten, when the notation calls for one.
+    pub exponent: Option<i32>,
+}
+
+/// As plain text, with a Unicode superscript exponent: `1.23×10⁴`.
+impl fmt::Display for FormattedNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.mantissa)?;
+        if let Some(e) = self.exponent {
+            write!(f, "×10{}", mazer_units::superscript_digits(e))?;
+        }
+        Ok(())
+    }
+}
+
+impl NumberFormat {
+    /// Apply one option form such as `(sig 4)` or `(group false)`.
+    ///
+    /// # Errors
+    /// [`LispError::TypeMismatch`] naming `form` for anything unrecognised.
+    pub fn apply(&mut self, form: &str, option: &LispAST) -> Result<(), LispError> {
+        let bad = || LispError::TypeMismatch {
+            form: form.to_string(),
+            expected: "a number format option such as (sig 4), (digits 2), (sci), (eng), (group) or (trim)"
+                .to_string(),
+            got: match option {
+                LispAST::List(items) => match items.first() {
+                    Some(LispAST::Symbol(s)) => format!("({s} ...)"),
+                    _ => "List".to_string(),
+                },
+                other => other.type_name().to_string(),
+            },
+        };
+        let LispAST::List(items) = option else {
+            return Err(bad());
+        };
+        let flag = |rest: &[LispAST]| match rest {
+            [] => Some(true),
+            [LispAST::Bool(b)] => Some(*b),
+            _ => None,
+        };
+        let count = |rest: &[LispAST]| match rest {
+            [LispAST::Number(n)] if n.is_integral() => n.to_u32().ok(),
+            _ => None,
+        };
+        let Some((LispAST::Symbol(name), rest)) = items.split_first() else {
+            return Err(bad());
+        };
+        match name.as_str() {
+            "sig" => {
+                self.precision = Precision::Significant(count(rest).filter(|n| *n > 0).ok_or_else(bad)?);
+            }
+            "digits" => self.precision = Precision::Decimals(count(rest).ok_or_else(bad)?),
+            "full" if rest.is_empty() => self.precision = Precision::Full,
+            "plain" if rest.is_empty() => self.notation = Notation::Plain,
+            "sci" if rest.is_empty() => self.notation = Notation::Scientific,
+            "eng" if rest.is_empty() => self.notation = Notation::Engineering,
+            "group" => self.grouping = flag(rest).ok_or_else(bad)?,
+            "trim" => self.trim_zeros = flag(rest).ok_or_else(bad)?,
+            _ => return Err(bad()),
+        }
+        Ok(())
+    }
+
+    /// Whether `option` is a form [`NumberFormat::apply`] understands.
+    #[must_use]
+    pub fn is_option(option: &LispAST) -> bool {
+        Self::default().apply("", option).is_ok()
+    }
+
+    /// Lay out `n` under this policy.
+    #[must_use]
+    pub fn format(&self, n: D512) -> FormattedNumber {
+        if *self == Self::default() {
+            return FormattedNumber {
+                mantissa: n.to_string(),
+                exponent: None,
+            };
+        }
+
+        let negative = n.is_negative() && !n.is_zero();
+        let (mut digits, mut point) = decompose(n);
+
+        if let Precision::Significant(s) = self.precision {
+            point += round_digits(&mut digits, s as usize);
+        }
+        let mut exponent = self.exponent(&digits, point);
+        if let Precision::Decimals(d) = self.precision {
+            let keep = usize::try_from(point - exponent + d as i32).unwrap_or(0);
+            let carry = round_digits(&mut digits, keep);
+            if carry > 0 {
+                point += carry;
+                exponent = self.exponent(&digits, point);
+            }
+        }
+
+        // Split around the mantissa's decimal point.
+        let mantissa_point = point - exponent;
+        let (mut int_part, mut frac_part) = split(&digits, mantissa_point);
+        match self.precision {
+            Precision::Significant(s) => {
+                // Zero still shows one significant "0".
+                let shown = format!("{int_part}{frac_part}").trim_start_matches('0').len().max(1);
+                let len = frac_part.len() + (s as usize).saturating_sub(shown);
+                pad(&mut frac_part, len);
+            }
+            Precision::Decimals(d) => {
+                frac_part.truncate(d as usize);
+                pad(&mut frac_part, d as usize);
+            }
+            Precision::Full => {}
+        }
+        if self.trim_zeros {
+            frac_part.truncate(frac_part.trim_end_matches('0').len());
+        }
+        if self.grouping {
+            int_part = group(&int_part, true);
+            frac_part = group(&frac_part, false);
+        }
+
+        let mut mantissa = String::new();
+        if negative && !(digits.is_empty() && frac_part.trim_matches('0').is_empty()) {
+            mantissa.push('-');
+        }
+        mantissa.push_str(&int_part);
+        if !frac_part.is_empty() {
+            mantissa.push('.');
+            mantissa.push_str(&frac_part);
+        }
+        FormattedNumber {
+            mantissa,
+            exponent: (exponent != 0).then_some(exponent),
+        }
+    }
+
+    /// The power of ten the mantissa is scaled by.
+    fn exponent(&self, digits: &[u8], point: i32) -> i32 {
+        if digits.is_empty() {
+            return 0;
+        }
+        match self.notation {
+            Notation::Plain => 0,
+            Notation::Scientific => point - 1,
+            Notation::Engineering => (point - 1).div_euclid(3) * 3,
+        }
+    }
+}
+
+/// The significant digits of `|n|` without leading or trailing zeros, and the
+/// position of the decimal point relative to the first of them: 123.45 is
+/// (`12345`, 3), 0.0012 is (`12`, -2). Zero has no digits.
+fn decompose(n: D512) -> (Vec<u8>, i32) {
+    let text = n.abs().to_string();
+    let (body, exp) = match text.split_once(['e', 'E']) {
+        Some((body, exp)) => (body, exp.parse::<i32>().unwrap_or(0)),
+        None => (text.as_str(), 0),
+    };
+    let (int, frac) = body.split_once('.').unwrap_or((body, ""));
+    let mut digits: Vec<u8> = int.bytes().chain(frac.bytes()).map(|b| b - b'0').collect();
+    let mut point = i32::try_from(int.len()).unwrap_or(i32::MAX) + exp;
+
+    let leading = digits.iter().take_while(|d| **d == 0).count();
+    digits.drain(..leading);
+    point -= i32::try_from(leading).unwrap_or(0);
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    if digits.is_empty() {
+        point = 1;
+    }
+    (digits, point)
+}
+
+/// Round `digits` half-up to `keep` of them, returning 1 when a carry adds a
+/// leading digit (9.99 → 10.0) and 0 otherwise.
+fn round_digits(digits: &mut Vec<u8>, keep: usize) -> i32 {
+    if digits.len() <= keep {
+        return 0;
+    }
+    let round_up = digits[keep] >= 5;
+    digits.truncate(keep);
+    if !round_up {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        return 0;
+    }
+    for i in (0..digits.len()).rev() {
+        if digits[i] == 9 {
+            digits[i] = 0;
+        } else {
+            digits[i] += 1;
+            while digits.last() == Some(&0) {
+                digits.pop();
+            }
+            return 0;
+        }
+    }
+    // Every kept digit was a 9 (or none were kept): it becomes a single 1.
+    digits.clear();
+    digits.push(1);
+    1
+}
+
+/// Integer and fractional digit strings for a decimal point `point` digits
+/// into `digits`.
+fn split(digits: &[u8], point: i32) -> (String, String) {
+    let text: String = digits.iter().map(|d| char::from(b'0' + d)).collect();
+    if point <= 0 {
+        let zeros = "0".repeat(point.unsigned_abs() as usize);
+        ("0".to_string(), format!("{zeros}{text}"))
+    } else {
+        let point = point as usize;
+        if point >= text.len() {
+            (format!("{text}{}", "0".repeat(point - text.len())), String::new())
+        } else {
+            (text[..point].to_string(), text[point..].to_string())
+        }
+    }
+}
+
+fn pad(digits: &mut String, len: usize) {
+    while digits.len() < len {
+        digits.push('0');
+    }
+}
+
+/// Thin-space groups of three, counted from the decimal point.
+fn group(digits: &str, integer: bool) -> String {
+    if digits.len() <= GROUP_THRESHOLD {
+        return digits.to_string();
+    }
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        let from_point = if integer { digits.len() - i } else { i };
+        if i > 0 && from_point % 3 == 0 {
+            out.push(GROUP_SEPARATOR);
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fastnum::decimal::Context;
+
+    fn format(options: &[&str], n: &str) -> String {
+        let mut policy = NumberFormat::default();
+        for option in options {
+            let items = option
+                .split(' ')
+                .map(|word| match word.parse::<u32>() {
+                    Ok(n) => LispAST::Number(D512::from(n)),
+                    Err(_) => LispAST::Symbol(word.to_string()),
+                })
+                .collect();
+            policy.apply("test", &LispAST::List(items)).unwrap();
+        }
+        policy
+            .format(D512::from_str(n, Context::default()).unwrap())
+            .to_string()
+    }
+
+    #[test]
+    fn significant_figures_and_decimals() {
+        assert_eq!(format(&["sig 3"], "3.14159"), "3.14");
+        assert_eq!(format(&["sig 3"], "2"), "2.00");
+        assert_eq!(format(&["sig 2"], "0.0012345"), "0.0012");
+        assert_eq!(format(&["sig 2"], "99.7"), "100");
+        assert_eq!(format(&["digits 2"], "-1.005"), "-1.01");
+        assert_eq!(format(&["digits 2", "trim"], "1.5"), "1.5");
+        assert_eq!(format(&["digits 1"], "-0.04"), "0.0");
+    }
+
+    #[test]
+    fn scientific_and_engineering() {
+        assert_eq!(format(&["sci", "sig 3"], "123456"), "1.23×10⁵");
+        assert_eq!(format(&["sci", "digits 1"], "9.96"), "1.0×10¹");
+        assert_eq!(format(&["eng", "sig 3"], "0.000123456"), "123×10⁻⁶");
+        assert_eq!(format(&["eng"], "12345"), "12.345×10³");
+    }
+
+    #[test]
+    fn grouping() {
+        assert_eq!(format(&["group"], "1234567.891011"), "1\u{2009}234\u{2009}567.891\u{2009}011");
+        assert_eq!(format(&["group"], "1234"), "1234");
+    }
+}
@@ -0,0 +1,286 @@
+//! Values back as Lisp source. `Display` writes a [`LispAST`] on one line as
+//! it would be typed in a block, so it reads back to the same value;
+//! [`LispAST::pretty`] lays the same text out across lines to fit a width.
+//!
+//! Values with no source form, such as functions and caught errors, are
+//! written in angle brackets: `⟨UserFunc⟩`.
+
+use std::fmt::{self, Write};
+
+use mazer_units::Unit;
+
+use crate::LispAST;
+
+/// The width the REPL and `debug` lay values out to.
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// Forms that keep their first few arguments on the head's line and indent the
+/// rest, their body, by two: `(defunc name (params)` and then the body below.
+const BODY_FORMS: &[(&str, usize)] = &[
+    ("define", 1),
+    ("defunc", 2),
+    ("defmacro", 2),
+    ("let", 1),
+    ("begin", 0),
+    ("try", 1),
+    ("catch", 1),
+    ("forall", 1),
+    ("exists", 1),
+];
+
+impl LispAST {
+    /// This value as source, broken across lines where it does not fit in
+    /// `width` columns. A long call keeps its first argument beside the head
+    /// and lines the others up under it; a long list of data lines its
+    /// elements up under the first. `define`, `defunc`, `let` and the other
+    /// body forms indent their body by two instead.
+    #[must_use]
+    pub fn pretty(&self, width: usize) -> String {
+        layout(self, 0, width)
+    }
+}
+
+impl fmt::Display for LispAST {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((prefix, inner)) = reader_prefix(self) {
+            return write!(f, "{prefix}{inner}");
+        }
+        match self {
+            LispAST::Symbol(s) | LispAST::Error(s) => f.write_str(s),
+            LispAST::Keyword(k) => write!(f, ":{k}"),
+            LispAST::Number(n) => write!(f, "{n}"),
+            LispAST::Bool(b) => write!(f, "{b}"),
+            LispAST::String(s) => write_string(f, s),
+            LispAST::Quantity { value, unit } => write!(f, "(qty {value} {})", unit_source(unit)),
+            LispAST::Poly { var, coeffs } => {
+                write!(f, "(poly '{var}")?;
+                if coeffs.is_empty() {
+                    f.write_str(" 0")?;
+                }
+                for c in coeffs.iter().rev() {
+                    write!(f, " {c}")?;
+                }
+                f.write_char(')')
+            }
+            LispAST::List(items) => write_form(f, None, items),
+            LispAST::Set(items) => write_form(f, Some("set"), items),
+            LispAST::Application { name, args } => write_form(f, Some(name), args),
+            LispAST::Dict(entries) => {
+                f.write_str("(dict")?;
+                for (key, value) in entries.iter() {
+                    write!(f, " {key} {value}")?;
+                }
+                f.write_char(')')
+            }
+            LispAST::ErrorValue { code, message, .. } => write!(f, "⟨Error {code}: {message}⟩"),
+            LispAST::UserFunc { .. } | LispAST::NativeFunc(_) => {
+                write!(f, "⟨{}⟩", self.type_name())
+            }
+        }
+    }
+}
+
+/// `(quote x)` and its kin, which read back from `'x`, `` `x ``, `,x` and
+/// `,@x`.
+fn reader_prefix(expr: &LispAST) -> Option<(&'static str, &LispAST)> {
+    let LispAST::List(items) = expr else {
+        return None;
+    };
+    let [LispAST::Symbol(head), inner] = &items[..] else {
+        return None;
+    };
+    let prefix = match head.as_str() {
+        "quote" => "'",
+        "quasiquote" => "`",
+        "unquote" => ",",
+        "unquote-splicing" => ",@",
+        _ => return None,
+    };
+    Some((prefix, inner))
+}
+
+fn write_form(f: &mut fmt::Formatter<'_>, head: Option<&str>, items: &[LispAST]) -> fmt::Result {
+    f.write_char('(')?;
+    let mut sep = "";
+    if let Some(head) = head {
+        f.write_str(head)?;
+        sep = " ";
+    }
+    for item in items {
+        write!(f, "{sep}{item}")?;
+        sep = " ";
+    }
+    f.write_char(')')
+}
+
+/// A string literal with the escapes the reader understands.
+fn write_string(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\t' => f.write_str("\\t")?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+/// `unit` as a unit expression the reader resolves, e.g. `kg*m^2/s^2`.
+fn unit_source(unit: &Unit) -> String {
+    if unit.terms.is_empty() {
+        return unit.to_string();
+    }
+    let mut out = String::new();
+    for (i, (symbol, exp)) in unit.terms.iter().enumerate() {
+        let (sep, exp) = match (i, *exp < 0) {
+            (0, _) => ("", *exp),
+            (_, true) => ("/", -exp),
+            (_, false) => ("*", *exp),
+        };
+        out.push_str(sep);
+        out.push_str(symbol);
+        if exp != 1 {
+            let _ = write!(out, "^{exp}");
+        }
+    }
+    out
+}
+
+/// `expr` laid out starting at `column`. Lines after the first carry their
+/// full indentation.
+fn layout(expr: &LispAST, column: usize, width: usize) -> String {
+    let flat = expr.to_string();
+    if column + flat.chars().count() <= width {
+        return flat;
+    }
+    if let Some((prefix, inner)) = reader_prefix(expr) {
+        return format!("{prefix}{}", layout(inner, column + prefix.len(), width));
+    }
+    match expr {
+        LispAST::List(items) => match items.split_first() {
+            Some((LispAST::Symbol(head), args)) => form(head, args, column, width),
+            _ => {
+                let items: Vec<String> = items
+                    .iter()
+                    .map(|item| layout(item, column + 1, width))
+                    .collect();
+                format!("({})", items.join(&newline(column + 1)))
+            }
+        },
+        LispAST::Set(items) => form("set", items, column, width),
+        LispAST::Application { name, args } => form(name, args, column, width),
+        LispAST::Dict(entries) => {
+            let indent = column + "(dict ".len();
+            let entries: Vec<String> = entries
+                .iter()
+                .map(|(key, value)| {
+                    let key = key.to_string();
+                    let value = layout(value, indent + key.chars().count() + 1, width);
+                    format!("{key} {value}")
+                })
+                .collect();
+            format!("(dict {})", entries.join(&newline(indent)))
+        }
+        _ => flat,
+    }
+}
+
+/// `(head args...)` over several lines.
+fn form(head: &str, args: &[LispAST], column: usize, width: usize) -> String {
+    let head_end = column + 1 + head.chars().count();
+    let (kept, indent) = match BODY_FORMS.iter().find(|(name, _)| *name == head) {
+        Some(&(_, kept)) => (kept, column + 2),
+        // Arguments line up under the first, unless a long head would push
+        // them past the middle of the line.
+        None if head_end < width / 2 => (1, head_end + 1),
+        None => (0, column + 2),
+    };
+    let (inline, below) = args.split_at(kept.min(args.len()));
+
+    let mut out = format!("({head}");
+    let mut end = head_end;
+    for arg in inline {
+        let text = layout(arg, end + 1, width);
+        end = match text.rsplit_once('\n') {
+            Some((_, last)) => last.chars().count(),
+            None => end + 1 + text.chars().count(),
+        };
+        out.push(' ');
+        out.push_str(&text);
+    }
+    for arg in below {
+        out.push_str(&newline(indent));
+        out.push_str(&layout(arg, indent, width));
+    }
+    out.push(')');
+    out
+}
+
+fn newline(indent: usize) -> String {
+    format!("\n{}", " ".repeat(indent))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sym(s: &str) -> LispAST {
+        LispAST::Symbol(s.to_string())
+    }
+
+    fn num(n: i64) -> LispAST {
+        LispAST::Number(n.into())
+    }
+
+    fn list(items: Vec<LispAST>) -> LispAST {
+        LispAST::List(items.into())
+    }
+
+    #[test]
+    fn display_reads_back() {
+        let expr = list(vec![
+            sym("concat"),
+            LispAST::String("say \"hi\"\n".to_string()),
+            list(vec![sym("quote"), sym("x")]),
+            LispAST::Keyword("k".to_string()),
+            LispAST::set_from([num(2), num(1)]),
+            LispAST::Quantity {
+                value: 3.into(),
+                unit: Unit::parse("kg*m/s^2").unwrap(),
+            },
+        ]);
+        assert_eq!(
+            expr.to_string(),
+            r#"(concat "say \"hi\"\n" 'x :k (set 1 2) (qty 3 kg*m/s^2))"#
+        );
+        assert_eq!(list(vec![]).to_string(), "()");
+    }
+
+    #[test]
+    fn long_forms_break_by_kind() {
+        let body = list(vec![
+            sym("+"),
+            list(vec![sym("*"), sym("alpha"), sym("beta")]),
+            list(vec![sym("*"), sym("gamma"), sym("delta")]),
+        ]);
+        let defunc = list(vec![
+            sym("defunc"),
+            sym("f"),
+            list(vec![sym("alpha"), sym("beta")]),
+            body.clone(),
+        ]);
+        assert_eq!(defunc.pretty(DEFAULT_WIDTH), defunc.to_string());
+        assert_eq!(
+            defunc.pretty(30),
+            "(defunc f (alpha beta)\n  (+ (* alpha beta)\n     (* gamma delta)))"
+        );
+        let data = list(vec![body.clone(), body]);
+        assert_eq!(
+            data.pretty(30),
+            "((+ (* alpha beta)\n    (* gamma delta))\n (+ (* alpha beta)\n    (* gamma delta)))"
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Runtime dimension vectors: integer exponents over the seven SI base
+//! quantities, in the same order as the const-generic [`crate::Dimensions`].
+
+use std::fmt;
+use std::ops::{Div, Mul};
+
+/// Conventional symbols for the base dimensions, in index order.
+const SYMBOLS: [&str; 7] = ["L", "M", "T", "I", "Θ", "N", "J"];
+
+/// Exponents of length, mass, time, electric current, temperature, amount of
+/// substance and luminous intensity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, PartialOrd, Ord)]
+pub struct Dimension(pub [i32; 7]);
+
+impl Dimension {
+    pub const NONE: Self = Self([0, 0, 0, 0, 0, 0, 0]);
+    pub const LENGTH: Self = Self([1, 0, 0, 0, 0, 0, 0]);
+    pub const MASS: Self = Self([0, 1, 0, 0, 0, 0, 0]);
+    pub const TIME: Self = Self([0, 0, 1, 0, 0, 0, 0]);
+    pub const CURRENT: Self = Self([0, 0, 0, 1, 0, 0, 0]);
+    pub const TEMPERATURE: Self = Self([0, 0, 0, 0, 1, 0, 0]);
+    pub const AMOUNT: Self = Self([0, 0, 0, 0, 0, 1, 0]);
+    pub const LUMINOSITY: Self = Self([0, 0, 0, 0, 0, 0, 1]);
+
+    /// Whether every exponent is zero.
+    #[must_use]
+    pub const fn is_dimensionless(self) -> bool {
+        self.equals(Self::NONE)
+    }
+
+    /// `==` usable in const contexts (the typed API checks dimensions in
+    /// `const` blocks).
+    #[must_use]
+    pub const fn equals(self, rhs: Self) -> bool {
+        let mut i = 0;
+        while i < 7 {
+            if self.0[i] != rhs.0[i] {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    /// The dimension of a product; `*` in const contexts.
+    #[must_use]
+    pub const fn product(self, rhs: Self) -> Self {
+        let mut out = [0; 7];
+        let mut i = 0;
+        while i < 7 {
+            out[i] = self.0[i] + rhs.0[i];
+            i += 1;
+        }
+        Self(out)
+    }
+
+    /// The dimension of a quotient; `/` in const contexts.
+    #[must_use]
+    pub const fn quotient(self, rhs: Self) -> Self {
+        let mut out = [0; 7];
+        let mut i = 0;
+        while i < 7 {
+            out[i] = self.0[i] - rhs.0[i];
+            i += 1;
+        }
+        Self(out)
+    }
+
+    /// Raise to an integer power, scaling every exponent by `n`.
+    #[must_use]
+    pub fn powi(self, n: i32) -> Self {
+        Self(self.0.map(|e| e * n))
+    }
+}
+
+impl Mul for Dimension {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        self.product(rhs)
+    }
+}
+
+impl Div for Dimension {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        self.quotient(rhs)
+    }
+}
+
+/// Written as a product of base symbols, e.g. `L·T⁻²`; dimensionless is `1`.
+impl fmt::Display for Dimension {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_dimensionless() {
+            return f.write_str("1");
+        }
+        let terms: Vec<String> = SYMBOLS
+            .iter()
+            .zip(self.0)
+            .filter(|(_, e)| *e != 0)
+            .map(|(s, e)| format!("{s}{}", crate::superscript(e)))
+            .collect();
+        f.write_str(&terms.join("·"))
+    }
+}
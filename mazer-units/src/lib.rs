@@ -1,25 +1,45 @@
-struct Dimensions<
-    // meter
-    const Length: i32, 
-    // kilogram
-    const Mass: i32,
-    // second
-    const Time: i32,
-    // Ampere
-    const ElectricCurrent: i32,
-    //
-    const Temperature: i32,
-    const AmountOfSubstance: i32,
-    const LuminousIntensity: i32,
->;
+//! Physical dimensions and units.
+//!
+//! [`Dimension`] and [`Unit`] are the runtime representation used by Lisp
+//! quantities (`(qty 9.81 m/s^2)`); the const-generic [`Dimensions`] is the
+//! compile-time counterpart.
 
-type Length = Dimensions<1, 0, 0, 0, 0, 0, 0>;
-type Time   = Dimensions<0, 0, 1, 0, 0, 0, 0>;
-type Speed  = Dimensions<1, 0, -1, 0, 0, 0, 0>;
-type Force  = Dimensions<1, 1, -2, 0, 0, 0, 0>;
+mod dimension;
+mod typed;
+mod unit;
 
-struct Quantity<D> {
-    value: f64,
-    _marker: std::marker::PhantomData<D>,
+pub use dimension::Dimension;
+pub use typed::{
+    Acceleration, Dim, Dimensionless, Dimensions, Energy, Force, Length, Mass, Power, Pressure,
+    Quantity, Speed, Time,
+};
+pub use unit::{Unit, UnitError};
+
+/// Render an exponent as Unicode superscripts; `1` renders as nothing.
+fn superscript(n: i32) -> String {
+    if n == 1 {
+        return String::new();
+    }
+    superscript_digits(n)
 }
 
+/// Render an integer in Unicode superscripts, e.g. `-3` as `⁻³`.
+#[must_use]
+pub fn superscript_digits(n: i32) -> String {
+    n.to_string()
+        .chars()
+        .map(|c| match c {
+            '-' => '⁻',
+            '0' => '⁰',
+            '1' => '¹',
+            '2' => '²',
+            '3' => '³',
+            '4' => '⁴',
+            '5' => '⁵',
+            '6' => '⁶',
+            '7' => '⁷',
+            '8' => '⁸',
+            _ => '⁹',
+        })
+        .collect()
+}
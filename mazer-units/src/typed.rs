@@ -0,0 +1,212 @@
+//! Compile-time dimensions for Rust callers.
+//!
+//! A [`Quantity<D>`] carries its dimension in the type, so adding a length to a
+//! time does not compile. Products and quotients name their result type and
+//! are checked when monomorphised:
+//!
+//! ```
+//! use mazer_units::{Acceleration, Force, Mass, Quantity};
+//!
+//! let m: Quantity<Mass> = Quantity::new(2.0);
+//! let a: Quantity<Acceleration> = Quantity::new(9.81);
+//! let f: Quantity<Force> = m.times(a);
+//! assert!((f.value() - 19.62).abs() < 1e-12);
+//! ```
+//!
+//! ```compile_fail
+//! use mazer_units::{Force, Mass, Quantity};
+//!
+//! let m: Quantity<Mass> = Quantity::new(2.0);
+//! let f: Quantity<Force> = m.times(m);
+//! ```
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use crate::{Dimension, Unit, UnitError};
+
+/// A dimension as a type: exponents of length, mass, time, electric current,
+/// temperature, amount of substance and luminous intensity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dimensions<
+    const LENGTH: i32,
+    const MASS: i32,
+    const TIME: i32,
+    const CURRENT: i32,
+    const TEMPERATURE: i32,
+    const AMOUNT: i32,
+    const LUMINOSITY: i32,
+>;
+
+/// Links a type-level dimension to its runtime [`Dimension`].
+pub trait Dim {
+    const DIM: Dimension;
+}
+
+impl<
+    const L: i32,
+    const M: i32,
+    const T: i32,
+    const I: i32,
+    const K: i32,
+    const N: i32,
+    const J: i32,
+> Dim for Dimensions<L, M, T, I, K, N, J>
+{
+    const DIM: Dimension = Dimension([L, M, T, I, K, N, J]);
+}
+
+pub type Dimensionless = Dimensions<0, 0, 0, 0, 0, 0, 0>;
+pub type Length = Dimensions<1, 0, 0, 0, 0, 0, 0>;
+pub type Mass = Dimensions<0, 1, 0, 0, 0, 0, 0>;
+pub type Time = Dimensions<0, 0, 1, 0, 0, 0, 0>;
+pub type Speed = Dimensions<1, 0, -1, 0, 0, 0, 0>;
+pub type Acceleration = Dimensions<1, 0, -2, 0, 0, 0, 0>;
+pub type Force = Dimensions<1, 1, -2, 0, 0, 0, 0>;
+pub type Pressure = Dimensions<-1, 1, -2, 0, 0, 0, 0>;
+pub type Energy = Dimensions<2, 1, -2, 0, 0, 0, 0>;
+pub type Power = Dimensions<2, 1, -3, 0, 0, 0, 0>;
+
+/// A value in coherent SI units whose dimension is fixed by `D`.
+pub struct Quantity<D> {
+    value: f64,
+    _marker: PhantomData<D>,
+}
+
+impl<D: Dim> Quantity<D> {
+    /// A quantity of `value` coherent SI units (metres, kilograms, seconds, ...).
+    #[must_use]
+    pub const fn new(value: f64) -> Self {
+        Self {
+            value,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The value in coherent SI units.
+    #[must_use]
+    pub const fn value(self) -> f64 {
+        self.value
+    }
+
+    /// `value` expressed in `unit`, e.g. 60 in `mi/h`.
+    ///
+    /// # Errors
+    /// [`UnitError::Incompatible`] when `unit` is not of dimension `D`.
+    pub fn from_unit(value: f64, unit: &Unit) -> Result<Self, UnitError> {
+        Self::check(unit)?;
+        let value = unit.scale.to_f64().mul_add(value, unit.offset.to_f64());
+        Ok(Self::new(value))
+    }
+
+    /// This quantity expressed in `unit`.
+    ///
+    /// # Errors
+    /// [`UnitError::Incompatible`] when `unit` is not of dimension `D`.
+    pub fn in_unit(self, unit: &Unit) -> Result<f64, UnitError> {
+        Self::check(unit)?;
+        Ok((self.value - unit.offset.to_f64()) / unit.scale.to_f64())
+    }
+
+    fn check(unit: &Unit) -> Result<(), UnitError> {
+        if unit.dim == D::DIM {
+            Ok(())
+        } else {
+            Err(UnitError::Incompatible {
+                from: D::DIM,
+                to: unit.dim,
+            })
+        }
+    }
+
+    /// The product, typed as `R`; fails to compile unless `R = D × E`.
+    #[must_use]
+    pub const fn times<E: Dim, R: Dim>(self, rhs: Quantity<E>) -> Quantity<R> {
+        const {
+            assert!(
+                D::DIM.product(E::DIM).equals(R::DIM),
+                "product has the wrong dimension"
+            );
+        };
+        Quantity::new(self.value * rhs.value)
+    }
+
+    /// The quotient, typed as `R`; fails to compile unless `R = D / E`.
+    #[must_use]
+    pub const fn per<E: Dim, R: Dim>(self, rhs: Quantity<E>) -> Quantity<R> {
+        const {
+            assert!(
+                D::DIM.quotient(E::DIM).equals(R::DIM),
+                "quotient has the wrong dimension"
+            );
+        };
+        Quantity::new(self.value / rhs.value)
+    }
+}
+
+impl<D> Clone for Quantity<D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<D> Copy for Quantity<D> {}
+
+impl<D> PartialEq for Quantity<D> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<D> PartialOrd for Quantity<D> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl<D: Dim> fmt::Debug for Quantity<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Quantity({} {})", self.value, D::DIM)
+    }
+}
+
+impl<D: Dim> Add for Quantity<D> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.value + rhs.value)
+    }
+}
+
+impl<D: Dim> Sub for Quantity<D> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.value - rhs.value)
+    }
+}
+
+impl<D: Dim> Neg for Quantity<D> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.value)
+    }
+}
+
+impl<D: Dim> Mul<f64> for Quantity<D> {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self {
+        Self::new(self.value * rhs)
+    }
+}
+
+impl<D: Dim> Div<f64> for Quantity<D> {
+    type Output = Self;
+
+    fn div(self, rhs: f64) -> Self {
+        Self::new(self.value / rhs)
+    }
+}
@@ -0,0 +1,375 @@
+//! Runtime units: a product of unit symbols raised to integer powers, together
+//! with the factor that converts a value in that unit to coherent SI.
+
+use std::fmt;
+use std::ops::{Div, Mul};
+
+use fastnum::{D512, decimal::Context};
+
+use crate::Dimension;
+
+/// A named unit that may appear as a term in a unit expression.
+struct Atom {
+    symbol: &'static str,
+    /// Size of one of this unit in coherent SI, as a decimal literal or a
+    /// `num/den` fraction.
+    scale: &'static str,
+    dim: Dimension,
+    /// Whether SI prefixes apply (`km`, `MeV`, `mbar`, but not `kin`).
+    prefixable: bool,
+    /// Zero point relative to SI for affine units (`°C`, `°F`), in kelvin.
+    offset: &'static str,
+}
+
+const fn si(symbol: &'static str, scale: &'static str, dim: [i32; 7]) -> Atom {
+    Atom {
+        symbol,
+        scale,
+        dim: Dimension(dim),
+        prefixable: true,
+        offset: "0",
+    }
+}
+
+const fn other(symbol: &'static str, scale: &'static str, dim: [i32; 7]) -> Atom {
+    Atom {
+        prefixable: false,
+        ..si(symbol, scale, dim)
+    }
+}
+
+const LENGTH: [i32; 7] = [1, 0, 0, 0, 0, 0, 0];
+const MASS: [i32; 7] = [0, 1, 0, 0, 0, 0, 0];
+const TIME: [i32; 7] = [0, 0, 1, 0, 0, 0, 0];
+const TEMPERATURE: [i32; 7] = [0, 0, 0, 0, 1, 0, 0];
+const VOLUME: [i32; 7] = [3, 0, 0, 0, 0, 0, 0];
+const PRESSURE: [i32; 7] = [-1, 1, -2, 0, 0, 0, 0];
+const ENERGY: [i32; 7] = [2, 1, -2, 0, 0, 0, 0];
+
+/// SI base units (with the gram standing in for the kilogram so that prefixes
+/// compose), the named derived units, and common non-SI units.
+const ATOMS: &[Atom] = &[
+    si("m", "1", LENGTH),
+    si("g", "1e-3", MASS),
+    si("s", "1", TIME),
+    si("A", "1", [0, 0, 0, 1, 0, 0, 0]),
+    si("K", "1", TEMPERATURE),
+    si("mol", "1", [0, 0, 0, 0, 0, 1, 0]),
+    si("cd", "1", [0, 0, 0, 0, 0, 0, 1]),
+    si("Hz", "1", [0, 0, -1, 0, 0, 0, 0]),
+    si("N", "1", [1, 1, -2, 0, 0, 0, 0]),
+    si("Pa", "1", PRESSURE),
+    si("J", "1", ENERGY),
+    si("W", "1", [2, 1, -3, 0, 0, 0, 0]),
+    si("C", "1", [0, 0, 1, 1, 0, 0, 0]),
+    si("V", "1", [2, 1, -3, -1, 0, 0, 0]),
+    si("Ω", "1", [2, 1, -3, -2, 0, 0, 0]),
+    si("ohm", "1", [2, 1, -3, -2, 0, 0, 0]),
+    // Accepted alongside SI, and prefixable in practice.
+    si("L", "1e-3", VOLUME),
+    si("eV", "1.602176634e-19", ENERGY),
+    si("bar", "1e5", PRESSURE),
+    other("min", "60", TIME),
+    other("h", "3600", TIME),
+    other("d", "86400", TIME),
+    other("atm", "101325", PRESSURE),
+    // Imperial / US customary, by their exact SI definitions.
+    other("in", "0.0254", LENGTH),
+    other("inch", "0.0254", LENGTH),
+    other("ft", "0.3048", LENGTH),
+    other("yd", "0.9144", LENGTH),
+    other("mi", "1609.344", LENGTH),
+    other("mile", "1609.344", LENGTH),
+    other("lb", "0.45359237", MASS),
+    other("oz", "0.028349523125", MASS),
+    other("psi", "4.4482216152605/0.00064516", PRESSURE),
+    // Affine temperature scales: SI = value × scale + offset.
+    Atom {
+        offset: "273.15",
+        ..other("°C", "1", TEMPERATURE)
+    },
+    Atom {
+        offset: "273.15",
+        ..other("degC", "1", TEMPERATURE)
+    },
+    Atom {
+        offset: "45967/180",
+        ..other("°F", "5/9", TEMPERATURE)
+    },
+    Atom {
+        offset: "45967/180",
+        ..other("degF", "5/9", TEMPERATURE)
+    },
+];
+
+/// SI prefixes and their powers of ten. Both micro signs and `u` are accepted.
+const PREFIXES: &[(&str, i32)] = &[
+    ("Q", 30),
+    ("R", 27),
+    ("Y", 24),
+    ("Z", 21),
+    ("E", 18),
+    ("P", 15),
+    ("T", 12),
+    ("G", 9),
+    ("M", 6),
+    ("k", 3),
+    ("h", 2),
+    ("da", 1),
+    ("d", -1),
+    ("c", -2),
+    ("m", -3),
+    ("µ", -6),
+    ("μ", -6),
+    ("u", -6),
+    ("n", -9),
+    ("p", -12),
+    ("f", -15),
+    ("a", -18),
+    ("z", -21),
+    ("y", -24),
+    ("r", -27),
+    ("q", -30),
+];
+
+fn decimal(literal: &str) -> D512 {
+    let parse = |s| D512::from_str(s, Context::default()).expect("unit table holds valid decimals");
+    match literal.split_once('/') {
+        Some((num, den)) => parse(num) / parse(den),
+        None => parse(literal),
+    }
+}
+
+/// Why a unit expression could not be resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UnitError {
+    /// A term that is neither a known unit nor a prefixed one.
+    Unknown(String),
+    /// The expression itself is malformed, e.g. an empty term or `m^x`.
+    Malformed(String),
+    /// A conversion between units of different dimensions.
+    Incompatible { from: Dimension, to: Dimension },
+}
+
+impl fmt::Display for UnitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unknown(term) => write!(f, "unknown unit '{term}'"),
+            Self::Malformed(text) => write!(f, "malformed unit expression '{text}'"),
+            Self::Incompatible { from, to } => {
+                write!(f, "cannot convert between dimensions {from} and {to}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for UnitError {}
+
+/// A unit such as `km`, `m/s^2` or `kg*m^2/s^2`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unit {
+    /// `(symbol, exponent)` factors in written order, e.g. `[("m", 1), ("s", -2)]`.
+    pub terms: Vec<(String, i32)>,
+    /// Multiply a value in this unit by `scale` to express it in coherent SI.
+    pub scale: D512,
+    /// Added after scaling for affine units; zero for everything else,
+    /// including any product or power of an affine unit (a temperature
+    /// *difference*).
+    pub offset: D512,
+    pub dim: Dimension,
+}
+
+impl Unit {
+    /// The dimensionless unit with no terms.
+    #[must_use]
+    pub fn one() -> Self {
+        Self {
+            terms: Vec::new(),
+            scale: D512::from(1),
+            offset: D512::from(0),
+            dim: Dimension::NONE,
+        }
+    }
+
+    /// A named unit defined as a multiple of another, e.g. a furlong as
+    /// 201.168 m.
+    #[must_use]
+    pub fn named(symbol: &str, scale: D512, dim: Dimension) -> Self {
+        Self {
+            terms: vec![(symbol.to_string(), 1)],
+            scale,
+            offset: D512::from(0),
+            dim,
+        }
+    }
+
+    /// Express `value` (in this unit) in coherent SI.
+    #[must_use]
+    pub fn to_si(&self, value: D512) -> D512 {
+        value * self.scale + self.offset
+    }
+
+    /// Express an SI `value` in this unit.
+    #[must_use]
+    pub fn from_si(&self, value: D512) -> D512 {
+        (value - self.offset) / self.scale
+    }
+
+    /// Re-express `value` from this unit in `target`.
+    ///
+    /// # Errors
+    /// [`UnitError::Incompatible`] when the dimensions differ.
+    pub fn convert(&self, value: D512, target: &Self) -> Result<D512, UnitError> {
+        if self.dim != target.dim {
+            return Err(UnitError::Incompatible {
+                from: self.dim,
+                to: target.dim,
+            });
+        }
+        Ok(target.from_si(self.to_si(value)))
+    }
+
+    /// Resolve a single unit symbol, trying an exact match before splitting off
+    /// an SI prefix (so `Pa` is pascal, `cd` is candela and `h` is the hour).
+    ///
+    /// # Errors
+    /// [`UnitError::Unknown`] when no reading of `symbol` names a unit.
+    pub fn symbol(symbol: &str) -> Result<Self, UnitError> {
+        let exact = ATOMS
+            .iter()
+            .find(|a| a.symbol == symbol)
+            .map(|a| (a, D512::from(1)));
+        let prefixed = || {
+            PREFIXES.iter().find_map(|(prefix, power)| {
+                let rest = symbol.strip_prefix(prefix)?;
+                let a = ATOMS.iter().find(|a| a.prefixable && a.symbol == rest)?;
+                Some((a, decimal(&format!("1e{power}"))))
+            })
+        };
+
+        exact
+            .or_else(prefixed)
+            .map(|(a, factor)| Self {
+                terms: vec![(symbol.to_string(), 1)],
+                scale: decimal(a.scale) * factor,
+                offset: decimal(a.offset),
+                dim: a.dim,
+            })
+            .ok_or_else(|| UnitError::Unknown(symbol.to_string()))
+    }
+
+    /// Parse a unit expression of built-in units; see [`Unit::parse_with`].
+    ///
+    /// # Errors
+    /// [`UnitError`] for unknown symbols or malformed syntax.
+    pub fn parse(text: &str) -> Result<Self, UnitError> {
+        Self::parse_with(text, |_| None)
+    }
+
+    /// Parse a unit expression: symbols joined by `*`, `·` or `.`, each
+    /// optionally raised with `^n`; a `/` divides by the term that follows it,
+    /// so `kg/m/s` is kg·m⁻¹·s⁻¹. `custom` is consulted before the built-in
+    /// table, so user-defined units may shadow built-in ones.
+    ///
+    /// # Errors
+    /// [`UnitError`] for unknown symbols or malformed syntax.
+    pub fn parse_with(
+        text: &str,
+        custom: impl Fn(&str) -> Option<Self>,
+    ) -> Result<Self, UnitError> {
+        let malformed = || UnitError::Malformed(text.to_string());
+        let mut unit = Self::one();
+        let mut sign = 1;
+        let mut rest = text;
+        loop {
+            let end = rest.find(['*', '·', '.', '/']).unwrap_or(rest.len());
+            let (term, tail) = rest.split_at(end);
+            let (symbol, exp) = match term.split_once('^') {
+                Some((s, e)) => (s, e.parse::<i32>().map_err(|_| malformed())?),
+                None => (term, 1),
+            };
+            if symbol.is_empty() {
+                return Err(malformed());
+            }
+            let atom = match custom(symbol) {
+                Some(u) => u,
+                None => Self::symbol(symbol)?,
+            };
+            unit = unit * atom.powi(sign * exp);
+
+            let mut chars = tail.chars();
+            match chars.next() {
+                None => return Ok(unit),
+                Some(sep) => sign = if sep == '/' { -1 } else { 1 },
+            }
+            rest = chars.as_str();
+        }
+    }
+
+    /// Raise to an integer power.
+    #[must_use]
+    pub fn powi(self, n: i32) -> Self {
+        match n {
+            0 => Self::one(),
+            1 => self,
+            _ => Self {
+                terms: self.terms.into_iter().map(|(s, e)| (s, e * n)).collect(),
+                scale: self.scale.powi(n),
+                offset: D512::from(0),
+                dim: self.dim.powi(n),
+            },
+        }
+    }
+
+    #[must_use]
+    pub const fn is_dimensionless(&self) -> bool {
+        self.dim.is_dimensionless()
+    }
+}
+
+impl Mul for Unit {
+    type Output = Self;
+
+    /// Like symbols merge their exponents (`m·m` is `m²`); cancelled terms drop
+    /// out. An affine offset survives only a product with the unit `1`.
+    fn mul(mut self, rhs: Self) -> Self {
+        if self.terms.is_empty() {
+            self.offset = rhs.offset;
+        } else if !rhs.terms.is_empty() {
+            self.offset = D512::from(0);
+        }
+        for (symbol, exp) in rhs.terms {
+            match self.terms.iter_mut().find(|(s, _)| *s == symbol) {
+                Some((_, e)) => *e += exp,
+                None => self.terms.push((symbol, exp)),
+            }
+        }
+        self.terms.retain(|(_, e)| *e != 0);
+        self.scale *= rhs.scale;
+        self.dim = self.dim * rhs.dim;
+        self
+    }
+}
+
+impl Div for Unit {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        self * rhs.powi(-1)
+    }
+}
+
+/// Written with a middle dot and superscript exponents, e.g. `m·s⁻²`.
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.terms.is_empty() {
+            return f.write_str("1");
+        }
+        let terms: Vec<String> = self
+            .terms
+            .iter()
+            .map(|(s, e)| format!("{s}{}", crate::superscript(*e)))
+            .collect();
+        f.write_str(&terms.join("·"))
+    }
+}
@@ -1,5 +1,5 @@
 use mazer_html::document::{Document, Metadata, DocOutputType};
-use mazer_lisp::{environment::EnvironmentExt, interpreter::Interpreter};
+use mazer_lisp::{environment::EnvironmentExt, interpreter::Interpreter, modules::Modules};
 use mazer_parser::Parser;
 use mazer_types::Environment;
 
@@ -25,6 +25,19 @@ fn escape_html(s: &str) -> String {
 /// the page — so one malformed `.zr` block must not break the others.
 #[wasm_bindgen]
 pub fn run_mazer(content: &str, window_name: &str) -> String {
+    run_mazer_with_files(content, window_name, Vec::new(), Vec::new())
+}
+
+/// Like [`run_mazer`], with a virtual filesystem for `import`/`require`:
+/// `paths[i]` holds `sources[i]`. Paths are relative to the note, which is
+/// taken to live at `window_name`.
+#[wasm_bindgen]
+pub fn run_mazer_with_files(
+    content: &str,
+    window_name: &str,
+    paths: Vec<String>,
+    sources: Vec<String>,
+) -> String {
     // An empty/whitespace-only note renders to nothing rather than an error.
     if content.trim().is_empty() {
         return String::new();
@@ -48,9 +61,11 @@ pub fn run_mazer(content: &str, window_name: &str) -> String {
     });
     d.build();
 
-    let ctx = Environment::new().with_native().with_prelude();
+    let mut ctx = Environment::new().with_native().with_prelude();
+    ctx.number_format = d.number_format();
     let frg = d.fragments();
-    let mut interp = Interpreter::new(frg, ctx);
+    let modules = Modules::in_memory(paths.into_iter().zip(sources), window_name);
+    let mut interp = Interpreter::new(frg, ctx).with_modules(modules);
     if let Err(e) = interp.run() {
         return format!(
             "<pre class=\"mazer-error\">mazer eval error: {}</pre>",
@@ -58,8 +73,8 @@ pub fn run_mazer(content: &str, window_name: &str) -> String {
         );
     }
     let rst = interp.results();
-    d.inject(rst);
-    d.fmt(interp.env());
+    d.inject(rst, interp.outputs());
+    d.fmt(&interp);
 
     d.output()
 }